@@ -0,0 +1,185 @@
+//! # libui-derive -- derive macros for libui.
+//!
+//! `#[derive(GuiWindow)]` implements `GuiWindowCore`, the part of every
+//! window which is the same: the ID, and whether the window stays open.
+//! Mark the fields:
+//!
+//! ```ignore
+//! #[derive(GuiWindow)]
+//! pub struct MyWindow {
+//!     #[gui(id)]
+//!     id: egui::Id,
+//!     #[gui(is_open)]
+//!     is_open: bool,
+//!     ...
+//! }
+//! ```
+//!
+//! Without an `is_open` field, the window stays open until removed.
+//! Mark a `title` field `#[gui(title)]` to list the window in the Window menu.
+//!
+//! `#[derive(MenuGroup)]` implements `MenuGroupCore`. The name, for
+//! logging, is `#[gui(name = "...")]` on the struct, or the struct name.
+//!
+//! The window still implements `GuiWindow` itself, for drawing.
+//! Downcasting comes from libui's `AsAny`, for all `'static` types.
+//
+//  Animats
+//  October 2026
+//
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Implement GuiWindowCore from the `#[gui(id)]`, `#[gui(is_open)]` and `#[gui(title)]` fields.
+#[proc_macro_derive(GuiWindow, attributes(gui))]
+pub fn derive_gui_window(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    gui_window_impl(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Implement MenuGroupCore, with the name from `#[gui(name = "...")]`.
+#[proc_macro_derive(MenuGroup, attributes(gui))]
+pub fn derive_menu_group(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    menu_group_impl(&input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn gui_window_impl(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let id_field = marked_field(input, "id")?.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &input.ident,
+            "#[derive(GuiWindow)] needs a field marked #[gui(id)]",
+        )
+    })?;
+    let retain = match marked_field(input, "is_open")? {
+        Some(open_field) => quote! {
+            fn retain(&self) -> bool {
+                self.#open_field
+            }
+        },
+        None => quote! {},
+    };
+    let title = match marked_field(input, "title")? {
+        Some(title_field) => quote! {
+            fn get_title(&self) -> Option<&str> {
+                Some(&self.#title_field)
+            }
+        },
+        None => quote! {},
+    };
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::libui::GuiWindowCore for #name #ty_generics #where_clause {
+            fn get_id(&self) -> ::egui::Id {
+                self.#id_field
+            }
+            #retain
+            #title
+        }
+    })
+}
+
+fn menu_group_impl(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let mut menu_name = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("gui")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                menu_name = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected #[gui(name = \"...\")]"))
+            }
+        })?;
+    }
+    let name = &input.ident;
+    let menu_name = menu_name.unwrap_or_else(|| LitStr::new(&name.to_string(), name.span()));
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::libui::MenuGroupCore for #name #ty_generics #where_clause {
+            fn get_name(&self) -> &'static str {
+                #menu_name
+            }
+        }
+    })
+}
+
+/// The field marked `#[gui(which)]`, if any. Two marked the same way is an error.
+fn marked_field(input: &DeriveInput, which: &str) -> syn::Result<Option<Ident>> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(input, "expected named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(input, "expected a struct")),
+    };
+    let mut found: Option<Ident> = None;
+    for field in fields {
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("gui")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(which) {
+                    if found.is_some() {
+                        return Err(meta.error(format!("more than one #[gui({})] field", which)));
+                    }
+                    found = field.ident.clone();
+                    Ok(())
+                } else if ["id", "is_open", "title"]
+                    .iter()
+                    .any(|name| meta.path.is_ident(name))
+                {
+                    Ok(()) // some other marking
+                } else {
+                    Err(meta.error("expected #[gui(id)], #[gui(is_open)] or #[gui(title)]"))
+                }
+            })?;
+        }
+    }
+    Ok(found)
+}
+
+#[test]
+fn test_derive_gui_window() {
+    let input: DeriveInput = syn::parse_quote! {
+        struct TestWindow {
+            #[gui(id)]
+            id: egui::Id,
+            #[gui(is_open)]
+            open: bool,
+            #[gui(title)]
+            title: String,
+        }
+    };
+    let output = gui_window_impl(&input).unwrap().to_string();
+    assert!(output.contains("self . id"));
+    assert!(output.contains("self . open"));
+    assert!(output.contains("self . title"));
+    //  No ID field is an error, and so is an unknown marking.
+    let input: DeriveInput = syn::parse_quote! {
+        struct NoId {
+            #[gui(is_open)]
+            is_open: bool,
+        }
+    };
+    assert!(gui_window_impl(&input).is_err());
+    let input: DeriveInput = syn::parse_quote! {
+        struct Typo {
+            #[gui(idd)]
+            id: egui::Id,
+        }
+    };
+    assert!(gui_window_impl(&input).is_err());
+    //  Menu group name defaults to the struct name.
+    let input: DeriveInput = syn::parse_quote! {
+        struct MenuThing {}
+    };
+    assert!(menu_group_impl(&input)
+        .unwrap()
+        .to_string()
+        .contains("\"MenuThing\""));
+}
@@ -0,0 +1,312 @@
+//! crashreport.rs -- crash reports, written by a panic hook.
+//
+//  A panic anywhere, even in the main loop, writes a crash report
+//  to the log directory: the panic message, where it happened,
+//  a backtrace, the current application state and menu group,
+//  and the last few log lines. Next launch, the user is offered
+//  the report, to view or to discard.
+//
+//  The hook can't touch GUI state, which may be borrowed or broken,
+//  so what it reports is kept here, as text, as things change.
+//  Nothing is taken from dialogs, so typed passwords are never seen.
+//  Text which looks like it holds a secret, in the panic message
+//  or the log, is redacted anyway.
+//
+//  Animats
+//  October 2026
+//
+use crate::{t, CommonState, ModalButton, ModalDialog, TextWindow};
+use core::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Mutex;
+
+/// Crash report from a crash not yet seen by the user, in the log directory.
+pub(crate) const CRASH_REPORT_FILE_NAME: &str = "crash_report.txt";
+/// Crash report the user has viewed, kept for diagnostics.
+pub(crate) const LAST_CRASH_REPORT_FILE_NAME: &str = "last_crash_report.txt";
+/// Log lines kept for a crash report.
+const CRASH_LOG_LINES: usize = 100;
+/// Words after which the rest of a line is redacted. Lower case.
+const SECRET_WORDS: &[&str] = &["password", "passwd", "token", "secret", "credential"];
+/// Replaces redacted text.
+const REDACTED: &str = "[redacted]";
+
+/// What was going on, for the crash report.
+struct CrashContext {
+    app_states: Vec<(&'static str, String)>, // (state machine, current state)
+    menu_group: &'static str,                // menu group shown
+    log_lines: VecDeque<String>,             // most recent log lines
+}
+
+/// Kept up to date as things change, for the panic hook.
+static CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext {
+    app_states: Vec::new(),
+    menu_group: "",
+    log_lines: VecDeque::new(),
+});
+
+/// Run a change on the crash context. Never fails, since a crash report is better than nothing.
+fn update_context(f: impl FnOnce(&mut CrashContext)) {
+    let mut context = CRASH_CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut context);
+}
+
+/// A state machine changed state.
+pub(crate) fn note_app_state(machine: &'static str, state: String) {
+    update_context(
+        |context| match context.app_states.iter_mut().find(|(m, _)| *m == machine) {
+            Some(entry) => entry.1 = state,
+            None => context.app_states.push((machine, state)),
+        },
+    )
+}
+
+/// A new menu group is shown.
+pub(crate) fn note_menu_group(name: &'static str) {
+    update_context(|context| context.menu_group = name)
+}
+
+/// Something was logged.
+pub(crate) fn note_log_line(line: String) {
+    update_context(|context| {
+        while context.log_lines.len() >= CRASH_LOG_LINES {
+            context.log_lines.pop_front();
+        }
+        context.log_lines.push_back(line);
+    })
+}
+
+/// Write a crash report to this directory on any panic.
+/// The usual panic message is still printed.
+pub fn install_crash_handler(dir: &Path) {
+    let path = dir.join(CRASH_REPORT_FILE_NAME);
+    let previous_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = if let Some(s) = info.payload().downcast_ref::<&str>() {
+            s.to_string()
+        } else if let Some(s) = info.payload().downcast_ref::<String>() {
+            s.clone()
+        } else {
+            "(not text)".to_string()
+        };
+        let location = info.location().map(|l| l.to_string()).unwrap_or_default();
+        let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+        //  A panic while the context is locked must not deadlock here.
+        let report = match CRASH_CONTEXT.try_lock() {
+            Ok(context) => crash_report_text(&message, &location, &backtrace, Some(&context)),
+            Err(std::sync::TryLockError::Poisoned(e)) => {
+                crash_report_text(&message, &location, &backtrace, Some(&e.into_inner()))
+            }
+            Err(std::sync::TryLockError::WouldBlock) => {
+                crash_report_text(&message, &location, &backtrace, None)
+            }
+        };
+        match std::fs::write(&path, report) {
+            Ok(()) => eprintln!("Crash report written to {:?}", path),
+            Err(e) => eprintln!("Unable to write crash report {:?}: {:?}", path, e),
+        }
+        previous_hook(info);
+    }));
+}
+
+/// The crash report, as text, with secrets redacted.
+fn crash_report_text(
+    message: &str,
+    location: &str,
+    backtrace: &str,
+    context: Option<&CrashContext>,
+) -> String {
+    let mut report = String::new();
+    let now = time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_default();
+    let thread = std::thread::current();
+    let _ = writeln!(report, "Crash at {}", now);
+    let _ = writeln!(report, "Thread: {}", thread.name().unwrap_or("unnamed"));
+    let _ = writeln!(report, "Panic: {}", message);
+    let _ = writeln!(report, "Location: {}", location);
+    match context {
+        Some(context) => {
+            for (machine, state) in &context.app_states {
+                let _ = writeln!(report, "State of {}: {}", machine, state);
+            }
+            let _ = writeln!(report, "Menu group: {}", context.menu_group);
+            let _ = writeln!(report, "\nLast {} log lines:", context.log_lines.len());
+            for line in &context.log_lines {
+                let _ = writeln!(report, "{}", line);
+            }
+        }
+        None => {
+            let _ = writeln!(report, "(State unavailable)");
+        }
+    }
+    let _ = writeln!(report, "\nBacktrace:\n{}", backtrace);
+    redact(&report)
+}
+
+/// Redact the rest of any line after a word which suggests a secret.
+fn redact(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for line in text.lines() {
+        //  ASCII lower case keeps byte positions the same.
+        let lower = line.to_ascii_lowercase();
+        match SECRET_WORDS
+            .iter()
+            .filter_map(|word| lower.find(word).map(|pos| pos + word.len()))
+            .min()
+        {
+            Some(end) => {
+                out.push_str(&line[..end]);
+                out.push(' ');
+                out.push_str(REDACTED);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// A crash report left by an earlier session.
+pub struct CrashReport {
+    dir: PathBuf, // log directory
+    text: String, // the report
+}
+
+impl CrashReport {
+    /// The crash report in this directory, if there is one.
+    pub fn find(dir: &Path) -> Option<Self> {
+        let path = dir.join(CRASH_REPORT_FILE_NAME);
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Some(CrashReport {
+                dir: dir.to_path_buf(),
+                text,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::error!("Unable to read crash report {:?}: {:?}", path, e);
+                None
+            }
+        }
+    }
+
+    /// The report.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Seen. Kept, for diagnostics, but not offered again.
+    pub fn keep(&self) -> Result<(), std::io::Error> {
+        std::fs::rename(
+            self.dir.join(CRASH_REPORT_FILE_NAME),
+            self.dir.join(LAST_CRASH_REPORT_FILE_NAME),
+        )
+    }
+
+    /// Delete the report.
+    pub fn discard(&self) -> Result<(), std::io::Error> {
+        std::fs::remove_file(self.dir.join(CRASH_REPORT_FILE_NAME))
+    }
+}
+
+/// Offers a crash report, to view or discard.
+pub struct CrashReportDialog {
+    id: egui::Id,          // unique ID
+    title: String,         // title of dialog
+    message: String,       // what happened
+    view_label: String,    // "View" button
+    discard_label: String, // "Discard" button
+    report: CrashReport,   // the report
+}
+
+impl CrashReportDialog {
+    /// If an earlier session crashed, offer its report.
+    /// Call once, at startup, once the GUI is up.
+    pub fn offer(state: &mut CommonState, dir: &Path) {
+        let Some(report) = CrashReport::find(dir) else {
+            return;
+        };
+        let lang = state.get_lang();
+        let dialog = CrashReportDialog {
+            id: egui::Id::new("crash report dialog"),
+            title: t!("window.crash_report", lang).to_string(),
+            message: t!("message.crash_report", lang).to_string(),
+            view_label: t!("menu.crash_report.view", lang).to_string(),
+            discard_label: t!("menu.crash_report.discard", lang).to_string(),
+            report,
+        };
+        state.add_modal(Rc::new(RefCell::new(dialog)));
+    }
+}
+
+impl ModalDialog for CrashReportDialog {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// No cancel button. Escape views, which is harmless.
+    fn button_labels(&self) -> (&str, Option<&str>) {
+        (&self.view_label, None)
+    }
+
+    fn extra_button_labels(&self) -> Vec<&str> {
+        vec![&self.discard_label]
+    }
+
+    fn draw_contents(&mut self, ui: &mut egui::Ui, _state: &mut CommonState) {
+        ui.label(self.message.as_str());
+    }
+
+    /// View opens the report in a window. Discard deletes it.
+    fn finish(&mut self, state: &mut CommonState, button: ModalButton) -> bool {
+        let result = if button != ModalButton::Other(0) {
+            let lines: Vec<&str> = self.report.text().lines().collect();
+            let window = TextWindow::new_link(
+                egui::Id::new(CRASH_REPORT_FILE_NAME),
+                &self.title,
+                &lines,
+                None,
+            );
+            state.add_window(window);
+            self.report.keep()
+        } else {
+            self.report.discard()
+        };
+        if let Err(e) = result {
+            log::error!("Unable to put away crash report: {:?}", e);
+        }
+        true
+    }
+
+    fn get_id(&self) -> egui::Id {
+        self.id
+    }
+}
+
+#[test]
+fn test_crash_report() {
+    assert_eq!(
+        redact("Login failed\nPassword: \"hunter2\" for joe\n"),
+        "Login failed\nPassword [redacted]\n"
+    );
+    let mut context = CrashContext {
+        app_states: Vec::new(),
+        menu_group: "connected",
+        log_lines: VecDeque::new(),
+    };
+    context
+        .app_states
+        .push(("system mode", "Connected".to_string()));
+    context
+        .log_lines
+        .push_back("12:00:00.000 [INFO] (login): auth token=abc123".to_string());
+    let report = crash_report_text("oops", "main.rs:10:5", "0: main", Some(&context));
+    assert!(report.contains("Panic: oops"));
+    assert!(report.contains("State of system mode: Connected"));
+    assert!(report.contains("Menu group: connected"));
+    assert!(report.contains("auth token [redacted]"));
+    assert!(!report.contains("abc123"));
+}
@@ -0,0 +1,347 @@
+//! dialogbuilder.rs -- dialogs assembled from parts.
+//
+//  Most dialogs are a title, some text, a few input fields, and buttons.
+//  Those don't need a hand-written window. Describe the dialog, and
+//  supply a function which turns what the user entered into an event.
+//  The dialog is modal, and the event is sent when a button is used.
+//
+//      let dialog = DialogBuilder::new(id, "Rename")
+//          .text("New name for the landmark?")
+//          .text_field("name", "Name", &old_name)
+//          .button("ok", t!("menu.ok", lang))
+//          .cancel_button("cancel", t!("menu.cancel", lang))
+//          .build(|response| match response.button {
+//              "ok" => Some(MyEvent::Rename(response.text("name")?.to_string())),
+//              _ => None,
+//          });
+//      state.add_modal(dialog);
+//
+//  Animats
+//  October 2026
+//
+use crate::modalstack::{ModalButton, ModalDialog, ModalDialogLink};
+use crate::{t, CommonState, Dictionary, SendAnyBoxed};
+use core::any::Any;
+use core::cell::RefCell;
+use std::rc::Rc;
+use zeroize::Zeroizing;
+
+/// Minimum width of text fields, in points.
+const TEXT_FIELD_WIDTH: f32 = 200.0;
+
+/// Makes the event for a dialog response. None means no event.
+type RespondFn = Box<dyn FnMut(&DialogResponse) -> Option<SendAnyBoxed>>;
+
+/// Kinds of input field.
+enum FieldValue {
+    Text(String),
+    Password(Zeroizing<String>),
+    Checkbox(bool),
+    Combo(Vec<String>, usize), // choices, selected one
+}
+
+/// One input field.
+struct DialogField {
+    key: &'static str, // for finding it in the response
+    label: String,     // shown to the left
+    value: FieldValue, // current value
+}
+
+/// What the user did with a dialog. Passed to the dialog's respond function.
+pub struct DialogResponse<'a> {
+    /// Key of the button used
+    pub button: &'static str,
+    /// The input fields
+    fields: &'a [DialogField],
+}
+
+impl DialogResponse<'_> {
+    /// Find a field.
+    fn field(&self, key: &str) -> Option<&FieldValue> {
+        self.fields.iter().find(|f| f.key == key).map(|f| &f.value)
+    }
+
+    /// Contents of a text or password field.
+    pub fn text(&self, key: &str) -> Option<&str> {
+        match self.field(key)? {
+            FieldValue::Text(s) => Some(s.as_str()),
+            FieldValue::Password(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Is a checkbox checked? False if there is no such checkbox.
+    pub fn checked(&self, key: &str) -> bool {
+        matches!(self.field(key), Some(FieldValue::Checkbox(true)))
+    }
+
+    /// Index of the selected choice in a combo box.
+    pub fn choice(&self, key: &str) -> Option<usize> {
+        match self.field(key)? {
+            FieldValue::Combo(_, selected) => Some(*selected),
+            _ => None,
+        }
+    }
+}
+
+/// Builds a modal dialog.
+pub struct DialogBuilder {
+    id: egui::Id,
+    title: String,
+    body: Vec<String>,
+    fields: Vec<DialogField>,
+    buttons: Vec<(&'static str, String)>, // (key, label). First is the default.
+    cancel_button: Option<(&'static str, String)>,
+}
+
+impl DialogBuilder {
+    /// Start a dialog.
+    pub fn new(id: egui::Id, title: &str) -> Self {
+        DialogBuilder {
+            id,
+            title: title.to_string(),
+            body: Vec::new(),
+            fields: Vec::new(),
+            buttons: Vec::new(),
+            cancel_button: None,
+        }
+    }
+
+    /// A line of text, above the fields.
+    pub fn text(mut self, line: &str) -> Self {
+        self.body.push(line.to_string());
+        self
+    }
+
+    /// Add an input field.
+    fn field(mut self, key: &'static str, label: &str, value: FieldValue) -> Self {
+        self.fields.push(DialogField {
+            key,
+            label: label.to_string(),
+            value,
+        });
+        self
+    }
+
+    /// A one-line text field.
+    pub fn text_field(self, key: &'static str, label: &str, initial: &str) -> Self {
+        self.field(key, label, FieldValue::Text(initial.to_string()))
+    }
+
+    /// A password field. Starts empty, and is zeroized when the dialog goes away.
+    pub fn password_field(self, key: &'static str, label: &str) -> Self {
+        self.field(key, label, FieldValue::Password(Default::default()))
+    }
+
+    /// A checkbox.
+    pub fn checkbox(self, key: &'static str, label: &str, initial: bool) -> Self {
+        self.field(key, label, FieldValue::Checkbox(initial))
+    }
+
+    /// A dropdown list of choices.
+    pub fn combo(self, key: &'static str, label: &str, choices: &[&str], selected: usize) -> Self {
+        let choices: Vec<String> = choices.iter().map(|s| s.to_string()).collect();
+        let selected = selected.min(choices.len().saturating_sub(1));
+        self.field(key, label, FieldValue::Combo(choices, selected))
+    }
+
+    /// A button. The first one is the default, used by Enter.
+    pub fn button(mut self, key: &'static str, label: &str) -> Self {
+        self.buttons.push((key, label.to_string()));
+        self
+    }
+
+    /// The cancel button, used by Escape.
+    pub fn cancel_button(mut self, key: &'static str, label: &str) -> Self {
+        self.cancel_button = Some((key, label.to_string()));
+        self
+    }
+
+    /// Make the dialog. The response is turned into an event by `respond`.
+    /// There must be at least one button, from `button`.
+    pub fn build<T: Any + Send>(
+        self,
+        mut respond: impl FnMut(&DialogResponse) -> Option<T> + 'static,
+    ) -> ModalDialogLink {
+        self.build_boxed(move |response| {
+            respond(response).map(|event| Box::new(event) as SendAnyBoxed)
+        })
+    }
+
+    /// Make the dialog, for `respond` functions which return events already boxed.
+    pub fn build_boxed(
+        self,
+        respond: impl FnMut(&DialogResponse) -> Option<SendAnyBoxed> + 'static,
+    ) -> ModalDialogLink {
+        assert!(
+            !self.buttons.is_empty(),
+            "Dialog \"{}\" has no buttons",
+            self.title
+        );
+        Rc::new(RefCell::new(BuiltDialog {
+            builder: self,
+            respond: Box::new(respond),
+        }))
+    }
+}
+
+/// A dialog made by DialogBuilder.
+struct BuiltDialog {
+    builder: DialogBuilder, // what's in the dialog
+    respond: RespondFn,     // makes the result event
+}
+
+impl ModalDialog for BuiltDialog {
+    fn title(&self) -> &str {
+        &self.builder.title
+    }
+
+    fn button_labels(&self) -> (&str, Option<&str>) {
+        (
+            &self.builder.buttons[0].1,
+            self.builder
+                .cancel_button
+                .as_ref()
+                .map(|(_, label)| label.as_str()),
+        )
+    }
+
+    fn extra_button_labels(&self) -> Vec<&str> {
+        self.builder.buttons[1..]
+            .iter()
+            .map(|(_, label)| label.as_str())
+            .collect()
+    }
+
+    /// Text, then the fields in a grid.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, _state: &mut CommonState) {
+        for line in &self.builder.body {
+            ui.label(line.as_str());
+        }
+        if self.builder.fields.is_empty() {
+            return;
+        }
+        let id = self.builder.id;
+        egui::Grid::new(id.with("fields")).show(ui, |ui| {
+            for field in &mut self.builder.fields {
+                match &mut field.value {
+                    FieldValue::Text(s) => {
+                        ui.label(field.label.as_str());
+                        ui.add(
+                            egui::TextEdit::singleline(s)
+                                .min_size(egui::vec2(TEXT_FIELD_WIDTH, 0.0)),
+                        );
+                    }
+                    FieldValue::Password(s) => {
+                        ui.label(field.label.as_str());
+                        ui.add(
+                            egui::TextEdit::singleline(&mut **s)
+                                .password(true)
+                                .min_size(egui::vec2(TEXT_FIELD_WIDTH, 0.0)),
+                        );
+                    }
+                    FieldValue::Checkbox(checked) => {
+                        ui.label("");
+                        ui.checkbox(checked, field.label.as_str());
+                    }
+                    FieldValue::Combo(choices, selected) => {
+                        ui.label(field.label.as_str());
+                        egui::ComboBox::from_id_salt(id.with(field.key)).show_index(
+                            ui,
+                            selected,
+                            choices.len(),
+                            |n| choices[n].as_str(),
+                        );
+                    }
+                }
+                ui.end_row();
+            }
+        });
+    }
+
+    /// Send the event for the button used.
+    fn finish(&mut self, state: &mut CommonState, button: ModalButton) -> bool {
+        let key = match button {
+            ModalButton::Default => self.builder.buttons[0].0,
+            ModalButton::Other(n) => self.builder.buttons[n + 1].0,
+            ModalButton::Cancel => match &self.builder.cancel_button {
+                Some((key, _)) => key,
+                None => return true,
+            },
+        };
+        let response = DialogResponse {
+            button: key,
+            fields: &self.builder.fields,
+        };
+        if let Some(event) = (self.respond)(&response) {
+            if let Err(e) = state.send_boxed_gui_event(event) {
+                log::error!("Dialog \"{}\": {:?}", self.builder.title, e);
+            }
+        }
+        true
+    }
+
+    fn get_id(&self) -> egui::Id {
+        self.builder.id
+    }
+}
+
+/// Asks a yes/no question.
+#[derive(Debug)]
+pub struct ConfirmRequest {
+    /// Title of the dialog
+    pub title: String,
+    /// The question
+    pub question: String,
+    /// Sent if the answer is yes
+    pub on_yes: SendAnyBoxed,
+    /// Sent if the answer is no. If present, there is also a cancel button, which sends nothing.
+    pub on_no: Option<SendAnyBoxed>,
+}
+
+/// The stock confirmation dialog. Yes and No, plus Cancel if there is something to do on No.
+pub fn confirm_dialog(id: egui::Id, request: ConfirmRequest, lang: &Dictionary) -> ModalDialogLink {
+    let ConfirmRequest {
+        title,
+        question,
+        on_yes,
+        on_no,
+    } = request;
+    let builder = DialogBuilder::new(id, &title)
+        .text(&question)
+        .button("yes", t!("menu.yes", lang));
+    let builder = if on_no.is_some() {
+        builder
+            .button("no", t!("menu.no", lang))
+            .cancel_button("cancel", t!("menu.cancel", lang))
+    } else {
+        builder.cancel_button("no", t!("menu.no", lang))
+    };
+    let mut on_yes = Some(on_yes);
+    let mut on_no = on_no;
+    builder.build_boxed(move |response| match response.button {
+        "yes" => on_yes.take(),
+        "no" => on_no.take(),
+        _ => None,
+    })
+}
+
+#[test]
+fn test_dialog_response() {
+    let builder = DialogBuilder::new(egui::Id::new("test"), "Test")
+        .text_field("name", "Name", "Joe")
+        .password_field("pass", "Password")
+        .checkbox("remember", "Remember", true)
+        .combo("color", "Color", &["red", "green"], 5)
+        .button("ok", "OK");
+    let response = DialogResponse {
+        button: "ok",
+        fields: &builder.fields,
+    };
+    assert_eq!(response.text("name"), Some("Joe"));
+    assert_eq!(response.text("pass"), Some(""));
+    assert!(response.checked("remember"));
+    assert!(!response.checked("name"));
+    assert_eq!(response.choice("color"), Some(1)); // clamped to the last choice
+    assert_eq!(response.text("nonexistent"), None);
+}
@@ -0,0 +1,364 @@
+//! docklayer.rs -- dock windows to the edges of the screen, as tabs.
+//
+//  Windows which can be docked, those whose GuiWindow::dock_title
+//  returns Some, are shown either as floating egui windows or as
+//  tabs in a dock at the left, right or bottom of the screen.
+//  Docked windows are drawn with GuiWindow::draw_contents, inside
+//  the dock, instead of with their own draw.
+//
+//  Drag a tab to another dock to move it, or away from the docks
+//  to float it. Drag a floating window to an edge of the screen to
+//  dock it. Tabs also have a context menu for this.
+//
+//  Which windows are docked where is saved with the window layout.
+//  Dock sizes are kept in egui's memory, which is saved too.
+//
+//  Animats
+//  October 2026
+//
+use crate::{t, CommonState, GuiWindowLink};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+
+/// How close to the screen edge, in points, a drop has to be to dock there.
+const EDGE_ZONE: f32 = 40.0;
+/// Initial dock width or height, in points.
+const DEFAULT_DOCK_SIZE: f32 = 300.0;
+
+/// Where a dock is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DockEdge {
+    Left,
+    Right,
+    Bottom,
+}
+
+impl DockEdge {
+    /// All the docks.
+    pub const ALL: [DockEdge; 3] = [DockEdge::Left, DockEdge::Right, DockEdge::Bottom];
+
+    /// The strip along this edge of the screen where drops dock.
+    fn zone(&self, screen: egui::Rect) -> egui::Rect {
+        match self {
+            DockEdge::Left => egui::Rect::from_min_max(
+                screen.left_top(),
+                egui::pos2(screen.left() + EDGE_ZONE, screen.bottom()),
+            ),
+            DockEdge::Right => egui::Rect::from_min_max(
+                egui::pos2(screen.right() - EDGE_ZONE, screen.top()),
+                screen.right_bottom(),
+            ),
+            DockEdge::Bottom => egui::Rect::from_min_max(
+                egui::pos2(screen.left(), screen.bottom() - EDGE_ZONE),
+                screen.right_bottom(),
+            ),
+        }
+    }
+}
+
+/// One dock.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+struct Dock {
+    tabs: Vec<egui::Id>,      // docked windows in tab order, open or not
+    active: Option<egui::Id>, // tab being shown
+}
+
+/// Drag payload for a dock tab.
+struct TabDrag(egui::Id);
+
+/// Changes to the docks, made after drawing.
+enum DockAction {
+    Dock(egui::Id, DockEdge),   // dock a window at an edge
+    Float(egui::Id),            // undock a window
+    Select(DockEdge, egui::Id), // show a tab
+}
+
+/// Which windows are docked where.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockLayer {
+    left: Dock,
+    right: Dock,
+    bottom: Dock,
+    #[serde(skip)]
+    dragged_window: Option<egui::Id>, // floating dockable window being dragged last frame
+}
+
+impl DockLayer {
+    /// Access a dock.
+    fn dock(&self, edge: DockEdge) -> &Dock {
+        match edge {
+            DockEdge::Left => &self.left,
+            DockEdge::Right => &self.right,
+            DockEdge::Bottom => &self.bottom,
+        }
+    }
+
+    /// Access a dock, mutably.
+    fn dock_mut(&mut self, edge: DockEdge) -> &mut Dock {
+        match edge {
+            DockEdge::Left => &mut self.left,
+            DockEdge::Right => &mut self.right,
+            DockEdge::Bottom => &mut self.bottom,
+        }
+    }
+
+    /// Dock a window, as the last tab. Moves it if docked elsewhere.
+    pub fn dock_window(&mut self, id: egui::Id, edge: DockEdge) {
+        self.undock_window(id);
+        let dock = self.dock_mut(edge);
+        dock.tabs.push(id);
+        dock.active = Some(id);
+    }
+
+    /// Undock a window, so it floats.
+    pub fn undock_window(&mut self, id: egui::Id) {
+        for edge in DockEdge::ALL {
+            let dock = self.dock_mut(edge);
+            dock.tabs.retain(|tab| *tab != id);
+            if dock.active == Some(id) {
+                dock.active = dock.tabs.first().copied();
+            }
+        }
+    }
+
+    /// Where a window is docked, if it is.
+    pub fn edge_of(&self, id: egui::Id) -> Option<DockEdge> {
+        DockEdge::ALL
+            .into_iter()
+            .find(|edge| self.dock(*edge).tabs.contains(&id))
+    }
+
+    /// Make a docked window the selected tab of its dock.
+    pub fn show_tab(&mut self, id: egui::Id) {
+        if let Some(edge) = self.edge_of(id) {
+            self.dock_mut(edge).active = Some(id);
+        }
+    }
+
+    /// True if the window is docked.
+    pub fn is_docked(&self, id: egui::Id) -> bool {
+        self.edge_of(id).is_some()
+    }
+}
+
+/// Which dock a drop at this position goes to. None means float.
+fn drop_edge(
+    pos: egui::Pos2,
+    screen: egui::Rect,
+    dock_rects: &[(DockEdge, egui::Rect)],
+) -> Option<DockEdge> {
+    dock_rects
+        .iter()
+        .find(|(_, rect)| rect.contains(pos))
+        .map(|(edge, _)| *edge)
+        .or_else(|| {
+            DockEdge::ALL
+                .into_iter()
+                .find(|edge| edge.zone(screen).contains(pos))
+        })
+}
+
+/// Show a dock panel. Returns where it is.
+//  Must be called before any central panel.
+fn show_panel(
+    ctx: &egui::Context,
+    edge: DockEdge,
+    add_contents: impl FnOnce(&mut egui::Ui),
+) -> egui::Rect {
+    let id = egui::Id::new(("dock", edge));
+    match edge {
+        DockEdge::Left => {
+            egui::SidePanel::left(id)
+                .default_width(DEFAULT_DOCK_SIZE)
+                .show(ctx, add_contents)
+                .response
+                .rect
+        }
+        DockEdge::Right => {
+            egui::SidePanel::right(id)
+                .default_width(DEFAULT_DOCK_SIZE)
+                .show(ctx, add_contents)
+                .response
+                .rect
+        }
+        DockEdge::Bottom => {
+            egui::TopBottomPanel::bottom(id)
+                .resizable(true)
+                .default_height(DEFAULT_DOCK_SIZE)
+                .show(ctx, add_contents)
+                .response
+                .rect
+        }
+    }
+}
+
+/// Draw the docks, with the docked windows among those given.
+/// Called from CommonState::draw_docks, before any central panel.
+//  Dock panel IDs are their own, so an app's own side panels stack beside the docks, not on them.
+pub(crate) fn draw_docks(ctx: &egui::Context, state: &mut CommonState, windows: &[GuiWindowLink]) {
+    let screen = ctx.screen_rect();
+    let dockable_window = |id: egui::Id| {
+        windows
+            .iter()
+            .find(|w| w.borrow().get_id() == id && w.borrow().dock_title().is_some())
+            .map(Rc::clone)
+    };
+    let float_label = t!("menu.dock.float", state.get_lang()).to_string();
+    let edge_labels = [
+        t!("menu.dock.left", state.get_lang()).to_string(),
+        t!("menu.dock.right", state.get_lang()).to_string(),
+        t!("menu.dock.bottom", state.get_lang()).to_string(),
+    ];
+    let mut actions = Vec::new();
+    let mut dock_rects = Vec::new();
+    for edge in DockEdge::ALL {
+        //  Only open windows get tabs. A dock with none is not shown.
+        let dock = state.window_layout.docks.dock(edge).clone();
+        let tabs: Vec<(egui::Id, GuiWindowLink)> = dock
+            .tabs
+            .iter()
+            .filter_map(|id| dockable_window(*id).map(|w| (*id, w)))
+            .collect();
+        if tabs.is_empty() {
+            continue;
+        }
+        let active = dock
+            .active
+            .filter(|id| tabs.iter().any(|(tab, _)| tab == id))
+            .unwrap_or(tabs[0].0);
+        let rect = show_panel(ctx, edge, |ui| {
+            ui.horizontal(|ui| {
+                for (id, window) in &tabs {
+                    let title = window.borrow().dock_title().unwrap_or_default().to_string();
+                    let response = ui.dnd_drag_source(id.with("dock tab"), TabDrag(*id), |ui| {
+                        ui.selectable_label(*id == active, title)
+                    });
+                    if response.inner.clicked() {
+                        actions.push(DockAction::Select(edge, *id));
+                    }
+                    response.inner.context_menu(|ui| {
+                        if ui.button(&float_label).clicked() {
+                            actions.push(DockAction::Float(*id));
+                            ui.close_menu();
+                        }
+                        for (other_edge, label) in DockEdge::ALL.into_iter().zip(&edge_labels) {
+                            if other_edge != edge && ui.button(label).clicked() {
+                                actions.push(DockAction::Dock(*id, other_edge));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                }
+            });
+            ui.separator();
+            //  Windows do their own scrolling, as they do when floating.
+            if let Some((_, window)) = tabs.iter().find(|(id, _)| *id == active) {
+                window.borrow_mut().draw_contents(ui, state);
+            }
+        });
+        dock_rects.push((edge, rect));
+    }
+    let pointer_pos = ctx.input(|i| i.pointer.latest_pos());
+    let released = ctx.input(|i| i.pointer.any_released());
+    //  Where a drop would go, for highlighting.
+    let mut drop_target_opt = None;
+    //  A tab being dragged. Dropped on a dock, it moves there. Dropped elsewhere, it floats.
+    if let (Some(drag), Some(pos)) = (egui::DragAndDrop::payload::<TabDrag>(ctx), pointer_pos) {
+        let target = drop_edge(pos, screen, &dock_rects);
+        if released {
+            let current = state.window_layout.docks.edge_of(drag.0);
+            match target {
+                Some(edge) if Some(edge) != current => actions.push(DockAction::Dock(drag.0, edge)),
+                Some(_) => {}
+                None => actions.push(DockAction::Float(drag.0)),
+            }
+        } else {
+            drop_target_opt = target;
+        }
+    }
+    //  A floating window being dragged. Dropped at a screen edge, it docks.
+    //  egui moves windows by dragging the window's area, whose Id is the window Id with "move".
+    let dragged_id = ctx.dragged_id();
+    if let Some(window_id) = state.window_layout.docks.dragged_window {
+        let zone_edge = pointer_pos.and_then(|pos| drop_edge(pos, screen, &[]));
+        if dragged_id == Some(window_id.with("move")) {
+            drop_target_opt = zone_edge;
+        } else if let Some(edge) = zone_edge {
+            actions.push(DockAction::Dock(window_id, edge)); // drag ended at an edge
+        }
+    }
+    state.window_layout.docks.dragged_window = windows
+        .iter()
+        .map(|w| w.borrow().get_id())
+        .find(|id| dragged_id == Some(id.with("move")) && dockable_window(*id).is_some())
+        .filter(|id| !state.window_layout.docks.is_docked(*id));
+    //  Show where a drop would go.
+    if let Some(edge) = drop_target_opt {
+        let rect = dock_rects
+            .iter()
+            .find(|(e, _)| *e == edge)
+            .map(|(_, rect)| *rect)
+            .unwrap_or_else(|| edge.zone(screen));
+        let highlight = ctx.style().visuals.selection.bg_fill.gamma_multiply(0.3);
+        ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("dock highlight"),
+        ))
+        .rect_filled(rect, 0.0, highlight);
+    }
+    //  Now that drawing is done, change the docks.
+    let docks = &mut state.window_layout.docks;
+    for action in actions {
+        match action {
+            DockAction::Dock(id, edge) => docks.dock_window(id, edge),
+            DockAction::Float(id) => docks.undock_window(id),
+            DockAction::Select(edge, id) => docks.dock_mut(edge).active = Some(id),
+        }
+    }
+}
+
+#[test]
+fn test_dock_layer() {
+    let a = egui::Id::new("a");
+    let b = egui::Id::new("b");
+    let mut docks = DockLayer::default();
+    docks.dock_window(a, DockEdge::Left);
+    docks.dock_window(b, DockEdge::Left);
+    assert_eq!(docks.edge_of(a), Some(DockEdge::Left));
+    assert_eq!(docks.left.active, Some(b)); // newest tab shows
+    docks.show_tab(a);
+    assert_eq!(docks.left.active, Some(a));
+    docks.dock_window(b, DockEdge::Bottom); // move
+    assert_eq!(docks.left.tabs, vec![a]);
+    assert_eq!(docks.left.active, Some(a));
+    assert_eq!(docks.edge_of(b), Some(DockEdge::Bottom));
+    docks.undock_window(a);
+    assert!(!docks.is_docked(a));
+    assert_eq!(docks.left.active, None);
+    //  Saved and restored.
+    let json = serde_json::to_string(&docks).unwrap();
+    let read_back: DockLayer = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_back.edge_of(b), Some(DockEdge::Bottom));
+    //  Drops
+    let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1000.0, 800.0));
+    let left_dock = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(300.0, 800.0));
+    let dock_rects = [(DockEdge::Left, left_dock)];
+    assert_eq!(
+        drop_edge(egui::pos2(200.0, 400.0), screen, &dock_rects),
+        Some(DockEdge::Left)
+    );
+    assert_eq!(
+        drop_edge(egui::pos2(990.0, 400.0), screen, &dock_rects),
+        Some(DockEdge::Right)
+    );
+    assert_eq!(
+        drop_edge(egui::pos2(500.0, 790.0), screen, &dock_rects),
+        Some(DockEdge::Bottom)
+    );
+    assert_eq!(
+        drop_edge(egui::pos2(500.0, 400.0), screen, &dock_rects),
+        None
+    );
+}
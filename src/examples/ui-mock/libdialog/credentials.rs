@@ -0,0 +1,455 @@
+//! # credentials.rs  --  saved login credentials.
+//!
+//! Secrets go into the platform keyring. For headless test machines,
+//! which have none, a plain file can be named instead. Otherwise, with
+//! no keyring, passwords can't be remembered. The keyring can't be enumerated,
+//! so a separate, non-secret index records which accounts have
+//! something stored.
+//
+//  Animats
+//  October 2026
+//
+use super::passwordscheme::PasswordSchemeId;
+use anyhow::{anyhow, Context, Error};
+use keyring::Entry;
+use libui::get_config_dir;
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Credential keys are prefixed with this.
+pub const CREDENTIAL_PREFIX: &str = "metaverse";
+/// Set this to a file name to use the file-backed store instead of the keyring.
+const CREDENTIAL_FILE_ENV: &str = "UIMOCK_CREDENTIAL_FILE";
+/// The non-secret index of saved accounts, in the config dir.
+const ACCOUNT_INDEX_FILE_NAME: &str = "accounts.json";
+
+/// Where a credential is kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialKey {
+    pub metaverse: String, // Second Life, OsGrid, etc.
+    pub grid: String,      // agni, etc.
+    pub user_name: String, // as typed by user
+}
+
+impl CredentialKey {
+    /// Usual new
+    pub fn new(metaverse: &str, grid: &str, user_name: &str) -> Self {
+        CredentialKey {
+            metaverse: metaverse.to_string(),
+            grid: grid.to_string(),
+            user_name: user_name.trim().to_string(),
+        }
+    }
+
+    /// Translate special characters
+    fn translate_special_characters(c: char) -> char {
+        match c {
+            '.' => '#',
+            '/' => '|',
+            _ => c,
+        }
+    }
+    /// Prep string for use as credential storage key.
+    /// Remove all whitespace.
+    /// Translate "." and "/" to something else.
+    pub fn prep_string(s: &str) -> String {
+        let s: String = s
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(Self::translate_special_characters)
+            .collect();
+        s.to_lowercase() // and force to lower case
+    }
+    /// Returns the "service" string needed for credential storage.
+    /// Format is "PREFIX/SYSTEM/GRID/TYPE".
+    pub fn get_service(&self, cred_type: &str) -> String {
+        format!(
+            "{}/{}/{}/{}",
+            CREDENTIAL_PREFIX,
+            Self::prep_string(&self.metaverse),
+            Self::prep_string(&self.grid),
+            cred_type
+        )
+    }
+    /// Returns the "user" string needed for credential storage.
+    pub fn get_user(&self) -> String {
+        Self::prep_string(&self.user_name)
+    }
+    /// Same metaverse and grid?
+    pub fn same_grid(&self, metaverse: &str, grid: &str) -> bool {
+        Self::prep_string(&self.metaverse) == Self::prep_string(metaverse)
+            && Self::prep_string(&self.grid) == Self::prep_string(grid)
+    }
+    /// Same account? User names are not case sensitive.
+    pub fn same_account(&self, other: &CredentialKey) -> bool {
+        self.same_grid(&other.metaverse, &other.grid) && self.get_user() == other.get_user()
+    }
+}
+
+/// Storage for secrets.
+//  Missing entries are Ok(None), not errors.
+pub trait CredentialStore: Send + Sync {
+    /// Store a secret, replacing any old one.
+    fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), Error>;
+    /// Fetch a secret.
+    fn get(&self, service: &str, user: &str) -> Result<Option<Zeroizing<String>>, Error>;
+    /// Delete a secret. Deleting something not there is not an error.
+    fn delete(&self, service: &str, user: &str) -> Result<(), Error>;
+    /// Name for debug and logging purposes only
+    fn get_name(&self) -> &'static str;
+    /// Why secrets can't be stored, if they can't.
+    fn problem(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// The platform keyring.
+pub struct KeyringStore {}
+
+impl KeyringStore {
+    /// True if there is a working keyring on this machine.
+    //  Looks up a key which should not exist. NoEntry means the keyring answered.
+    fn is_available() -> bool {
+        match Entry::new(CREDENTIAL_PREFIX, "keyring-probe").get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => true,
+            Err(e) => {
+                log::warn!("No usable platform keyring: {:?}", e);
+                false
+            }
+        }
+    }
+}
+
+impl CredentialStore for KeyringStore {
+    fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), Error> {
+        Entry::new(service, user)
+            .set_password(secret)
+            .map_err(anyhow::Error::msg)
+    }
+
+    fn get(&self, service: &str, user: &str) -> Result<Option<Zeroizing<String>>, Error> {
+        match Entry::new(service, user).get_password() {
+            Ok(secret) => Ok(Some(Zeroizing::new(secret))),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow::Error::msg(e)),
+        }
+    }
+
+    fn delete(&self, service: &str, user: &str) -> Result<(), Error> {
+        match Entry::new(service, user).delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(anyhow::Error::msg(e)),
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        "keyring"
+    }
+}
+
+/// Secrets in a plain JSON file.
+//  Not secure. For headless test machines without a keyring.
+//  Only used when UIMOCK_CREDENTIAL_FILE names the file.
+pub struct FileStore {
+    path: PathBuf,                           // the file
+    entries: Mutex<HashMap<String, String>>, // "SERVICE USER" -> secret
+}
+
+impl FileStore {
+    /// Open file store, reading existing contents if any.
+    pub fn new(path: &Path) -> Result<Self, Error> {
+        let entries = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read credential file {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Unable to parse credential file {:?}", path))?
+        } else {
+            HashMap::new()
+        };
+        Ok(FileStore {
+            path: path.to_path_buf(),
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Key for the map
+    fn key(service: &str, user: &str) -> String {
+        format!("{} {}", service, user)
+    }
+
+    /// Write the whole file.
+    fn write(&self, entries: &HashMap<String, String>) -> Result<(), Error> {
+        let mut content = serde_json::to_string_pretty(entries)?;
+        let result = Self::open_private(&self.path)
+            .and_then(|mut file| Ok(file.write_all(content.as_bytes())?))
+            .with_context(|| format!("Unable to write credential file {:?}", self.path));
+        content.zeroize();
+        result
+    }
+
+    /// Open for writing, owner access only, where the platform has that.
+    //  The file is created with that access, so it is never readable by others.
+    //  A file made some other way is tightened before anything is written.
+    fn open_private(path: &Path) -> Result<std::fs::File, Error> {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            if path.exists() {
+                std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+        Ok(options.open(path)?)
+    }
+}
+
+impl CredentialStore for FileStore {
+    fn set(&self, service: &str, user: &str, secret: &str) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(Self::key(service, user), secret.to_string());
+        self.write(&entries)
+    }
+
+    fn get(&self, service: &str, user: &str) -> Result<Option<Zeroizing<String>>, Error> {
+        Ok(self
+            .entries
+            .lock()
+            .unwrap()
+            .get(&Self::key(service, user))
+            .map(|secret| Zeroizing::new(secret.clone())))
+    }
+
+    fn delete(&self, service: &str, user: &str) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(mut secret) = entries.remove(&Self::key(service, user)) {
+            secret.zeroize();
+            self.write(&entries)?;
+        }
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "file"
+    }
+}
+
+impl Drop for FileStore {
+    fn drop(&mut self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.values_mut().for_each(|v| v.zeroize());
+        }
+    }
+}
+
+/// No storage. Passwords can't be remembered.
+pub struct NoStore {
+    reason: String, // why not, for the user
+}
+
+impl CredentialStore for NoStore {
+    fn set(&self, _service: &str, _user: &str, _secret: &str) -> Result<(), Error> {
+        Err(anyhow!("Passwords cannot be remembered: {}", self.reason))
+    }
+
+    fn get(&self, _service: &str, _user: &str) -> Result<Option<Zeroizing<String>>, Error> {
+        Ok(None)
+    }
+
+    fn delete(&self, _service: &str, _user: &str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_name(&self) -> &'static str {
+        "none"
+    }
+
+    fn problem(&self) -> Option<&str> {
+        Some(&self.reason)
+    }
+}
+
+/// The credential store for this run.
+/// The file store if UIMOCK_CREDENTIAL_FILE is set, else the keyring.
+/// If that can't be used, nothing is stored, and the store's problem says why.
+pub fn credential_store() -> &'static dyn CredentialStore {
+    static STORE: OnceCell<Box<dyn CredentialStore>> = OnceCell::new();
+    STORE
+        .get_or_init(|| {
+            if let Some(file) = std::env::var_os(CREDENTIAL_FILE_ENV) {
+                let file = PathBuf::from(file);
+                return match FileStore::new(&file) {
+                    Ok(store) => {
+                        log::warn!("Saved passwords are in plain file {:?}", file);
+                        Box::new(store)
+                    }
+                    Err(e) => {
+                        log::error!("Credential file store unusable: {:?}", e);
+                        Box::new(NoStore {
+                            reason: format!("{:#}", e),
+                        })
+                    }
+                };
+            }
+            if KeyringStore::is_available() {
+                Box::new(KeyringStore {})
+            } else {
+                Box::new(NoStore {
+                    reason: "no keyring on this system".to_string(),
+                })
+            }
+        })
+        .as_ref()
+}
+
+/// One account in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountEntry {
+    pub key: CredentialKey, // which account
+    pub last_used: u64,     // seconds since UNIX epoch
+    #[serde(default)]
+    pub password_scheme: PasswordSchemeId, // how the saved password was prepared
+}
+
+/// The non-secret index of accounts with saved credentials.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AccountIndex {
+    pub accounts: Vec<AccountEntry>, // most recently used first
+    #[serde(skip)]
+    path: Option<PathBuf>, // where it came from
+}
+
+impl AccountIndex {
+    /// Read the index from the config dir. Missing file is an empty index.
+    pub fn load() -> Result<Self, Error> {
+        Self::load_from(&get_config_dir()?.join(ACCOUNT_INDEX_FILE_NAME))
+    }
+
+    /// Read the index from a file. Missing file is an empty index.
+    pub fn load_from(path: &Path) -> Result<Self, Error> {
+        let mut index: AccountIndex = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read account index {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Unable to parse account index {:?}", path))?
+        } else {
+            Default::default()
+        };
+        index.path = Some(path.to_path_buf());
+        Ok(index)
+    }
+
+    /// Write the index back where it came from.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Account index has no file"))?;
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Unable to write account index {:?}", path))
+    }
+
+    /// Add or update an account, making it the most recent.
+    pub fn touch(&mut self, key: &CredentialKey, password_scheme: PasswordSchemeId) {
+        self.remove(key);
+        let last_used = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.accounts.insert(
+            0,
+            AccountEntry {
+                key: key.clone(),
+                last_used,
+                password_scheme,
+            },
+        );
+    }
+
+    /// The entry for an account, if any.
+    pub fn get(&self, key: &CredentialKey) -> Option<&AccountEntry> {
+        self.accounts.iter().find(|a| a.key.same_account(key))
+    }
+
+    /// Remove an account. Not an error if not present.
+    pub fn remove(&mut self, key: &CredentialKey) {
+        self.accounts.retain(|a| !a.key.same_account(key));
+    }
+
+    /// All accounts for one grid, most recent first.
+    pub fn accounts_for_grid<'a>(
+        &'a self,
+        metaverse: &'a str,
+        grid: &'a str,
+    ) -> impl Iterator<Item = &'a AccountEntry> + 'a {
+        self.accounts
+            .iter()
+            .filter(move |a| a.key.same_grid(metaverse, grid))
+    }
+}
+
+#[test]
+fn test_file_store() {
+    let path = std::env::temp_dir().join(format!("uimock-test-creds-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let key = CredentialKey::new("Second Life", "Agni", "Joe Example");
+    let service = key.get_service("pass");
+    assert_eq!(service, "metaverse/secondlife/agni/pass");
+    {
+        let store = FileStore::new(&path).unwrap();
+        assert!(store.get(&service, &key.get_user()).unwrap().is_none());
+        store.set(&service, &key.get_user(), "secret").unwrap();
+    }
+    //  Reopen, to check that it was written.
+    let store = FileStore::new(&path).unwrap();
+    assert_eq!(
+        store
+            .get(&service, &key.get_user())
+            .unwrap()
+            .as_ref()
+            .map(|s| s.as_str()),
+        Some("secret")
+    );
+    store.delete(&service, &key.get_user()).unwrap();
+    store.delete(&service, &key.get_user()).unwrap(); // twice is OK
+    assert!(store.get(&service, &key.get_user()).unwrap().is_none());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_account_index() {
+    let path = std::env::temp_dir().join(format!("uimock-test-index-{}.json", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut index = AccountIndex::load_from(&path).unwrap();
+    index.touch(
+        &CredentialKey::new("Second Life", "Agni", "Joe Example"),
+        PasswordSchemeId::Md5,
+    );
+    index.touch(
+        &CredentialKey::new("Open Simulator", "OsGrid", "Joe"),
+        PasswordSchemeId::PlainTls,
+    );
+    index.touch(
+        &CredentialKey::new("Second Life", "Agni", "Jane Example"),
+        PasswordSchemeId::Md5,
+    );
+    index.touch(
+        &CredentialKey::new("second life", "agni", "joe example"),
+        PasswordSchemeId::Md5,
+    ); // same account
+    index.save().unwrap();
+    let index = AccountIndex::load_from(&path).unwrap();
+    let names: Vec<&str> = index
+        .accounts_for_grid("Second Life", "Agni")
+        .map(|a| a.key.user_name.as_str())
+        .collect();
+    assert_eq!(names, vec!["joe example", "Jane Example"]);
+    let _ = std::fs::remove_file(&path);
+}
@@ -5,7 +5,6 @@
 //  Animats
 //  March 2024
 //
-use core::any::Any;
 use core::cell::RefCell;
 use std::rc::Rc;
 ////use crate::GuiAssets;
@@ -13,10 +12,13 @@ use libui::{CommonState, GuiWindow, GuiWindowLink, PieMenu};
 
 /// The circular click dialog.
 /// The persistent part.
+#[derive(GuiWindow)]
 pub struct ClickWindow {
     /// Unique ID
+    #[gui(id)]
     id: egui::Id,
     /// True if open. Set to false to make it close.
+    #[gui(is_open)]
     is_open: bool,
     /// Location of window on screen
     location: egui::Pos2,
@@ -96,7 +98,7 @@ impl ClickWindow {
     
     /// Tell somebody that something was clicked.
     pub fn report_result(&mut self, wedge_number: usize) {
-        println!("ClickWindow result: {}", wedge_number); // ***TEMP***
+        log::info!("ClickWindow result: {}", wedge_number);
     }
 }
 
@@ -150,25 +152,4 @@ impl GuiWindow for ClickWindow {
             }
         }
     */
-    /// If this is in the dynamic widgets list, drop if retain is false.
-    fn retain(&self) -> bool {
-        self.is_open
-    }
-
-    //  Access ID
-    fn get_id(&self) -> egui::Id {
-        self.id
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        todo!(); // lifetime problem
-                 ////self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        todo!(); // lifetime problem
-                 ////self
-    }
 }
@@ -0,0 +1,242 @@
+//! #  dialogcredentials.rs  -- saved password manager
+//!
+//! Lists the accounts with saved credentials, per grid,
+//! and lets the user forget them or enter a new password.
+//
+//  Animats
+//  October 2026
+//
+use super::super::credentials::{credential_store, AccountIndex, CredentialKey};
+use super::super::passwordscheme::PasswordSchemeId;
+use super::dialoglogin::{forget_account, save_password_for, LoginParams};
+use core::cell::RefCell;
+use libui::t;
+use libui::{CommonState, GuiWindow, GuiWindowLink};
+use std::rc::Rc;
+use zeroize::Zeroize;
+
+/// One row of the display.
+struct AccountRow {
+    key: CredentialKey,                // which account
+    password_scheme: PasswordSchemeId, // how the saved password is prepared
+    has_password: bool,                // a password is stored
+    has_token: bool,                   // a "trust this device" token is stored
+}
+
+/// What the user asked for this frame.
+enum CredentialAction {
+    Forget(usize),      // forget one account
+    ForgetAll,          // forget everything
+    SetPassword(usize), // save the newly typed password
+}
+
+/// The saved password manager window.
+/// The persistent part.
+#[derive(GuiWindow)]
+pub struct CredentialManagerWindow {
+    /// Title of window
+    #[gui(title)]
+    title: String,
+    /// Unique ID
+    #[gui(id)]
+    id: egui::Id,
+    /// True if open. Set to false to make it close.
+    #[gui(is_open)]
+    is_open: bool,
+    /// Accounts, in index order
+    rows: Vec<AccountRow>,
+    /// Row whose password is being re-entered, if any
+    editing: Option<usize>,
+    /// New password being typed. Zeroized after use.
+    new_password: String,
+}
+
+impl CredentialManagerWindow {
+    /// Name, for remembering the window layout.
+    pub const WINDOW_NAME: &'static str = "credentials";
+
+    /// Open the credential manager window.
+    pub fn open_window(state: &mut CommonState) {
+        let window = Self::create(state);
+        state.add_window(window);
+    }
+
+    /// Create the credential manager window. Also used to reopen it next session.
+    pub fn create(state: &mut CommonState) -> GuiWindowLink {
+        Self::new_link(
+            Self::WINDOW_NAME,
+            t!("window.credentials", state.get_lang()),
+            state,
+        )
+    }
+
+    /// Create window, reading the account index.
+    fn new(id: &str, title: &str, state: &mut CommonState) -> Self {
+        let mut window = CredentialManagerWindow {
+            id: egui::Id::new(id),
+            title: title.to_string(),
+            is_open: true,
+            rows: Vec::new(),
+            editing: None,
+            new_password: String::new(),
+        };
+        window.reload(state);
+        window
+    }
+
+    /// As link
+    fn new_link(id: &str, title: &str, state: &mut CommonState) -> GuiWindowLink {
+        Rc::new(RefCell::new(Self::new(id, title, state)))
+    }
+
+    /// Re-read the index and check what is actually stored.
+    fn reload(&mut self, state: &mut CommonState) {
+        let index = match AccountIndex::load() {
+            Ok(index) => index,
+            Err(e) => {
+                Self::report_error(state, &e);
+                Default::default()
+            }
+        };
+        let store = credential_store();
+        let is_stored = |key: &CredentialKey, cred_type: &str| {
+            matches!(
+                store.get(&key.get_service(cred_type), &key.get_user()),
+                Ok(Some(_))
+            )
+        };
+        self.rows = index
+            .accounts
+            .into_iter()
+            .map(|a| AccountRow {
+                has_password: is_stored(&a.key, LoginParams::CRED_TYPE_PASS),
+                has_token: is_stored(&a.key, LoginParams::CRED_TYPE_TOKEN),
+                password_scheme: a.password_scheme,
+                key: a.key,
+            })
+            .collect();
+        //  Grids together, for display.
+        self.rows
+            .sort_by(|a, b| (&a.key.metaverse, &a.key.grid).cmp(&(&b.key.metaverse, &b.key.grid)));
+        self.editing = None;
+        self.new_password.zeroize();
+    }
+
+    /// Pop up an error.
+    fn report_error(state: &mut CommonState, e: &anyhow::Error) {
+        log::error!("Saved password problem: {:?}", e);
+        let errmsg = format!("{}", e);
+        state.add_error_window(
+            t!("window.credentials", state.get_lang()),
+            &[errmsg.as_str()],
+        );
+    }
+
+    /// Do what the user asked.
+    fn do_action(&mut self, state: &mut CommonState, action: CredentialAction) {
+        let result = match action {
+            CredentialAction::Forget(n) => forget_account(&self.rows[n].key),
+            CredentialAction::ForgetAll => self
+                .rows
+                .iter()
+                .try_for_each(|row| forget_account(&row.key)),
+            CredentialAction::SetPassword(n) => {
+                let row = &self.rows[n];
+                let pass = row.password_scheme.scheme().prepare(&self.new_password);
+                save_password_for(&row.key, row.password_scheme, &pass)
+            }
+        };
+        if let Err(e) = result {
+            Self::report_error(state, &e);
+        }
+        self.reload(state);
+    }
+}
+
+impl GuiWindow for CredentialManagerWindow {
+    /// Usual draw function
+    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
+        if self.is_open {
+            let mut not_cancelled = true;
+            let mut action_opt = None;
+            let lang = state.get_lang();
+            let window = egui::containers::Window::new(self.title.as_str())
+                .id(self.id)
+                .collapsible(false)
+                .open(&mut not_cancelled);
+            window.show(ctx, |ui| {
+                if self.rows.is_empty() {
+                    ui.label(t!("menu.credentials.none", lang));
+                }
+                egui::Grid::new("credentials grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let mut last_grid: Option<(&str, &str)> = None;
+                        for (n, row) in self.rows.iter().enumerate() {
+                            //  Grid heading when the grid changes
+                            let this_grid = (row.key.metaverse.as_str(), row.key.grid.as_str());
+                            if last_grid != Some(this_grid) {
+                                ui.strong(format!("{} -- {}", this_grid.0, this_grid.1));
+                                ui.end_row();
+                                last_grid = Some(this_grid);
+                            }
+                            ui.label(&row.key.user_name);
+                            ui.label(if row.has_password { "••••" } else { "" });
+                            if row.has_token {
+                                ui.label(t!("menu.credentials.trusted", lang));
+                            } else {
+                                ui.label("");
+                            }
+                            if self.editing == Some(n) {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut self.new_password)
+                                        .password(true),
+                                );
+                                if ui
+                                    .add_enabled(
+                                        !self.new_password.trim().is_empty(),
+                                        egui::Button::new(t!("menu.save", lang)),
+                                    )
+                                    .clicked()
+                                {
+                                    action_opt = Some(CredentialAction::SetPassword(n));
+                                }
+                            } else if ui.button(t!("menu.credentials.change", lang)).clicked() {
+                                self.new_password.zeroize();
+                                self.editing = Some(n);
+                            }
+                            if ui.button(t!("menu.credentials.forget", lang)).clicked() {
+                                action_opt = Some(CredentialAction::Forget(n));
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(
+                            !self.rows.is_empty(),
+                            egui::Button::new(t!("menu.credentials.forget_all", lang)),
+                        )
+                        .clicked()
+                    {
+                        action_opt = Some(CredentialAction::ForgetAll);
+                    }
+                });
+            });
+            if let Some(action) = action_opt {
+                self.do_action(state, action);
+            }
+            if !not_cancelled {
+                self.new_password.zeroize();
+                self.is_open = false;
+            } // do here to avoid borrow clash
+        }
+    }
+}
+
+impl Drop for CredentialManagerWindow {
+    fn drop(&mut self) {
+        self.new_password.zeroize();
+    }
+}
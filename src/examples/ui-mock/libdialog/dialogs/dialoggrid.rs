@@ -1,23 +1,54 @@
-use super::super::uiinfo::GridSelectParams;
+use super::super::gridstatus::{GridStatus, GridStatusMonitor};
+use super::super::uiinfo::{GridSelectParams, GridSelectParamsData};
+use super::super::usergrids::{grid_matches_search, GridPreferences, GridSortOrder};
 use crate::GuiAssets;
 ///#  guigrid.rs -- grid selection
 //
 //  This is the first screen displayed on startup.
 //
+//  The grids can be searched, and the user's favourites
+//  are pinned at the top. Each grid's login server is
+//  checked in the background, and shown as up or down.
+//  Up/down arrows and Enter work on the list.
+//
 //  Animats
 //  October 2022
 //
+use core::cell::RefCell;
+use libui::{t, Dictionary};
 use std::rc::Rc;
+use std::time::Duration;
 /// Basic info about a grid for the splash page
 
+/// What the user did in the grid selection window.
+pub enum GridSelectAction {
+    Select(GridSelectParams), // log in to this grid
+    Add,                      // add a user grid
+    Edit(GridSelectParams),   // edit a user grid
+    Delete(GridSelectParams), // delete a user grid
+}
+
+/// Full size of a picture bar. Shrinks to fit narrow windows.
+const PICTURE_BAR_SIZE: egui::Vec2 = egui::Vec2::new(1024.0, 128.0);
+/// Picture bars never get narrower than this.
+const PICTURE_BAR_MIN_WIDTH: f32 = 256.0;
+/// Room for the window frame and the buttons to the right of the picture bar.
+const GRID_ROW_MARGIN: f32 = 120.0;
+/// How often to check for grid status answers.
+const GRID_STATUS_POLL: Duration = Duration::from_millis(250);
+
 /// The grid selection window.
 //  Appears at startup.
 //  The persistent part
 pub struct GridSelectWindow {
-    title: String,                    // title of window
-    id: egui::Id,                     // unique ID
-    web_icon: egui::TextureId,        // icon for web button
-    grids: Rc<Vec<GridSelectParams>>, // available grids
+    title: String,                                  // title of window
+    id: egui::Id,                                   // unique ID
+    web_icon: egui::TextureId,                      // icon for web button
+    grids: Rc<Vec<GridSelectParams>>,               // available grids
+    search: String,                                 // search text
+    preferences: GridPreferences,                   // favourites and sort order
+    status_monitor: Rc<RefCell<GridStatusMonitor>>, // which grids are up
+    selected: Option<usize>,                        // keyboard selection, position in display order
 }
 
 impl GridSelectWindow {
@@ -27,64 +58,214 @@ impl GridSelectWindow {
         title: &str,
         assets: &GuiAssets,
         grids: Rc<Vec<GridSelectParams>>,
+        status_monitor: Rc<RefCell<GridStatusMonitor>>,
     ) -> Self {
+        let preferences = GridPreferences::load().unwrap_or_else(|e| {
+            log::error!("Unable to load grid preferences, using defaults: {:?}", e);
+            Default::default()
+        });
         GridSelectWindow {
             id: egui::Id::new(id),
             title: title.to_string(),
-            web_icon: assets.web_icon,
+            web_icon: assets.icons.get("web").texture,
             grids,
+            search: String::new(),
+            preferences,
+            status_monitor,
+            selected: None,
+        }
+    }
+
+    /// Grids to show, as indices into grids, in display order.
+    fn display_list(&self) -> Vec<usize> {
+        let grids_data: Vec<&GridSelectParamsData> = self.grids.iter().map(|g| &g.data).collect();
+        self.preferences
+            .display_order(&grids_data)
+            .into_iter()
+            .filter(|&n| grid_matches_search(&self.grids[n].data, &self.search))
+            .collect()
+    }
+
+    /// Save preferences after a change. Not serious if this fails.
+    fn save_preferences(&self) {
+        if let Err(e) = self.preferences.save() {
+            log::error!("Unable to save grid preferences: {:?}", e);
         }
     }
 
+    /// Keyboard navigation. Returns true if Enter was pressed.
+    //  Not while anything has keyboard focus, such as the search box, or a modal dialog is open.
+    fn handle_keys(&mut self, ctx: &egui::Context, modal_open: bool, count: usize) -> bool {
+        use egui::{Key, Modifiers};
+        if modal_open || ctx.memory(|m| m.focused().is_some()) {
+            return false;
+        }
+        let (up, down, home, end, enter) = ctx.input_mut(|i| {
+            (
+                i.consume_key(Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(Modifiers::NONE, Key::Home),
+                i.consume_key(Modifiers::NONE, Key::End),
+                i.consume_key(Modifiers::NONE, Key::Enter),
+            )
+        });
+        if count == 0 {
+            self.selected = None;
+            return false;
+        }
+        let last = count - 1;
+        self.selected = match self.selected {
+            _ if home => Some(0),
+            _ if end => Some(last),
+            None if down || up => Some(0),
+            Some(n) if down => Some((n + 1).min(last)),
+            Some(n) if up => Some(n.saturating_sub(1)),
+            other => other.map(|n| n.min(last)),
+        };
+        enter && self.selected.is_some()
+    }
+
     /// Draw window of text
-    pub fn draw(&self, ctx: &egui::Context) -> Option<GridSelectParams> {
+    pub fn draw(
+        &mut self,
+        ctx: &egui::Context,
+        lang: &Dictionary,
+        modal_open: bool,
+    ) -> Option<GridSelectAction> {
+        if self.status_monitor.borrow_mut().update() {
+            ctx.request_repaint_after(GRID_STATUS_POLL); // still waiting for some grids
+        }
+        //  Picture bars shrink to fit the screen, keeping their shape.
+        let bar_width = (ctx.screen_rect().width() - PICTURE_BAR_SIZE.y - GRID_ROW_MARGIN)
+            .clamp(PICTURE_BAR_MIN_WIDTH, PICTURE_BAR_SIZE.x);
+        let bar_size = PICTURE_BAR_SIZE * (bar_width / PICTURE_BAR_SIZE.x);
+        let icon_size = egui::Vec2::splat(bar_size.y);
+        let search_id = self.id.with("search");
+        let display_list = self.display_list();
+        let previous_selection = self.selected;
+        let enter_pressed = self.handle_keys(ctx, modal_open, display_list.len());
+        let key_moved = previous_selection != self.selected;
         let window = egui::containers::Window::new(self.title.as_str())
             .id(self.id)
             .anchor(egui::Align2::CENTER_TOP, egui::Vec2::ZERO)
             .auto_sized()
             .collapsible(false);
         let mut result = None; // what, if anything, was clicked upon
+        if enter_pressed {
+            if let Some(n) = self.selected {
+                result = Some(GridSelectAction::Select(
+                    self.grids[display_list[n]].clone(),
+                ));
+            }
+        }
+        let mut preferences_changed = false;
         window.show(ctx, |ui| {
-            //  Ref: https://docs.rs/egui/latest/egui/containers/struct.ScrollArea.html#method.show_rows
-            ////let text_style = egui::TextStyle::Body;
-            ////let row_height = ui.text_style_height(&text_style);
-            let row_height = ui.spacing().interact_size.y; // if you are adding buttons instead of labels.
-                                                           //  Add image and website link to each row
-            egui::ScrollArea::vertical().show_rows(
-                ui,
-                row_height,
-                self.grids.len(),
-                |ui, row_range| {
-                    ////println!("Rows: {:?} of {}, row height {}", row_range, self.grids.len(), row_height);  // ***TEMP***
-                    for row in row_range {
+            //  Search and sort
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.search)
+                        .id(search_id)
+                        .hint_text(t!("menu.grid_select.search", lang)),
+                );
+                if response.changed() {
+                    self.selected = None;
+                }
+                egui::ComboBox::from_id_salt(self.id.with("sort"))
+                    .selected_text(match self.preferences.sort_order {
+                        GridSortOrder::Listed => t!("menu.grid_select.sort_listed", lang),
+                        GridSortOrder::Name => t!("menu.grid_select.sort_name", lang),
+                    })
+                    .show_ui(ui, |ui| {
+                        preferences_changed |= ui
+                            .selectable_value(
+                                &mut self.preferences.sort_order,
+                                GridSortOrder::Listed,
+                                t!("menu.grid_select.sort_listed", lang),
+                            )
+                            .changed();
+                        preferences_changed |= ui
+                            .selectable_value(
+                                &mut self.preferences.sort_order,
+                                GridSortOrder::Name,
+                                t!("menu.grid_select.sort_name", lang),
+                            )
+                            .changed();
+                    });
+            });
+            ui.separator();
+            //  Room for the search bar and the buttons at the bottom.
+            let max_height = ctx.screen_rect().height() - 4.0 * ui.spacing().interact_size.y;
+            egui::ScrollArea::vertical()
+                .max_height(max_height)
+                .show(ui, |ui| {
+                    if display_list.is_empty() {
+                        ui.label(t!("menu.grid_select.no_match", lang));
+                    }
+                    for (pos, &row) in display_list.iter().enumerate() {
                         let grid = &self.grids[row];
+                        let is_favourite = self
+                            .preferences
+                            .favourite_position(&grid.data.metaverse, &grid.data.grid);
                         ui.horizontal(|ui| {
+                            //  Up/down indicator. Replays have none.
+                            match self.status_monitor.borrow().get_status(&grid.data) {
+                                Some(GridStatus::Online) => {
+                                    ui.colored_label(egui::Color32::GREEN, "●")
+                                        .on_hover_text(t!("menu.grid_select.online", lang));
+                                }
+                                Some(GridStatus::Offline) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, "●")
+                                        .on_hover_text(t!("menu.grid_select.offline", lang));
+                                }
+                                Some(GridStatus::Checking) => {
+                                    ui.weak("●")
+                                        .on_hover_text(t!("menu.grid_select.checking", lang));
+                                }
+                                None => {}
+                            }
+                            //  Favourite toggle, and move up for favourites
+                            if ui
+                                .small_button(if is_favourite.is_some() { "★" } else { "☆" })
+                                .on_hover_text(t!("menu.grid_select.favourite", lang))
+                                .clicked()
+                            {
+                                self.preferences
+                                    .toggle_favourite(&grid.data.metaverse, &grid.data.grid);
+                                preferences_changed = true;
+                            }
+                            if is_favourite.is_some_and(|n| n > 0)
+                                && ui
+                                    .small_button("⏶")
+                                    .on_hover_text(t!("menu.grid_select.move_up", lang))
+                                    .clicked()
+                            {
+                                self.preferences
+                                    .raise_favourite(&grid.data.metaverse, &grid.data.grid);
+                                preferences_changed = true;
+                            }
                             ui.label(&grid.data.metaverse);
                             ui.label(" -- ");
                             ui.label(&grid.data.grid);
                         });
                         ui.horizontal(|ui| {
-                            //  Grid select
-                            if ui
-                                .add(
-                                    egui::widgets::ImageButton::new((
-                                        grid.picture_bar,
-                                        egui::Vec2::new(1024.0, 128.0),
-                                    ))
-                                    .frame(true),
-                                )
-                                .clicked()
-                            {
-                                result = Some(grid.clone());
+                            //  Grid select. Picture may still be loading.
+                            let picture_bar = grid.picture_bar.get();
+                            let response = ui.add(
+                                egui::widgets::ImageButton::new((picture_bar, bar_size))
+                                    .frame(true)
+                                    .selected(self.selected == Some(pos)),
+                            );
+                            if response.clicked() {
+                                result = Some(GridSelectAction::Select(grid.clone()));
+                            }
+                            if key_moved && self.selected == Some(pos) {
+                                response.scroll_to_me(Some(egui::Align::Center));
                             }
                             //  Grid page open
                             if ui
                                 .add(
-                                    egui::widgets::ImageButton::new((
-                                        self.web_icon,
-                                        egui::Vec2::new(128.0, 128.0),
-                                    ))
-                                    .frame(true),
+                                    egui::widgets::ImageButton::new((self.web_icon, icon_size))
+                                        .frame(true),
                                 )
                                 .clicked()
                             {
@@ -106,11 +287,28 @@ impl GridSelectWindow {
                                     }
                                 }
                             }
+                            //  The user's own grids can be changed.
+                            if grid.user_defined {
+                                ui.vertical(|ui| {
+                                    if ui.button(t!("menu.grid_edit.edit", lang)).clicked() {
+                                        result = Some(GridSelectAction::Edit(grid.clone()));
+                                    }
+                                    if ui.button(t!("menu.grid_edit.delete", lang)).clicked() {
+                                        result = Some(GridSelectAction::Delete(grid.clone()));
+                                    }
+                                });
+                            }
                         });
                     }
-                },
-            );
+                });
+            ui.separator();
+            if ui.button(t!("menu.grid_edit.add", lang)).clicked() {
+                result = Some(GridSelectAction::Add);
+            }
         });
+        if preferences_changed {
+            self.save_preferences();
+        }
         result // selected grid, or None
     }
 }
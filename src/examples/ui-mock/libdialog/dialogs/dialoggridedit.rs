@@ -0,0 +1,259 @@
+//! #  dialoggridedit.rs  -- add or edit a user grid
+//!
+//! The user types a login URL and clicks Fetch. The grid is
+//! asked for its grid info, which fills in the other fields.
+//! Everything can be edited before saving.
+//
+//  Animats
+//  October 2026
+//
+use super::super::passwordscheme::PasswordSchemeId;
+use super::super::uiinfo::{GridSelectParamsData, GuiEvent};
+use super::super::usergrids::{
+    GridInfo, GridInfoSource, HttpGridInfoSource, DEFAULT_USER_METAVERSE,
+};
+use anyhow::Error;
+use core::cell::RefCell;
+use crossbeam_channel::{Receiver, TryRecvError};
+use libui::t;
+use libui::{CommonState, GuiWindow, GuiWindowLink};
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Grid add/edit window.
+/// The persistent part.
+#[derive(GuiWindow)]
+pub struct GridEditWindow {
+    /// Title of window
+    #[gui(title)]
+    title: String,
+    /// Unique ID
+    #[gui(id)]
+    id: egui::Id,
+    /// True if open. Set to false to make it close.
+    #[gui(is_open)]
+    is_open: bool,
+    /// (metaverse, grid) of the entry being edited. None if adding.
+    old_key: Option<(String, String)>,
+    /// The entry as edited
+    data: GridSelectParamsData,
+    /// Login URL as typed. Empty is not allowed.
+    login_url: String,
+    /// Sign up page as typed. Empty for none.
+    signup_url: String,
+    /// Where grid info comes from
+    grid_info_source: Arc<dyn GridInfoSource>,
+    /// Grid info fetch in progress
+    fetch_opt: Option<Receiver<Result<GridInfo, Error>>>,
+    /// Last problem, for display
+    error_opt: Option<String>,
+}
+
+impl GridEditWindow {
+    /// Open the window. Edits the given grid, or adds a new one.
+    pub fn open_window(state: &mut CommonState, grid_opt: Option<&GridSelectParamsData>) {
+        let window = Self::new_link(
+            "grid edit",
+            t!("window.grid_edit", state.get_lang()),
+            grid_opt,
+            Arc::new(HttpGridInfoSource {}),
+        );
+        state.add_window(window);
+    }
+
+    /// Usual new.
+    pub fn new(
+        id: &str,
+        title: &str,
+        grid_opt: Option<&GridSelectParamsData>,
+        grid_info_source: Arc<dyn GridInfoSource>,
+    ) -> Self {
+        let data = grid_opt.cloned().unwrap_or_else(|| GridSelectParamsData {
+            metaverse: DEFAULT_USER_METAVERSE.to_string(),
+            grid: String::new(),
+            picture_bar: String::new(), // user grids use the default picture bar
+            home_url: String::new(),
+            signup_url: None,
+            login_url: None,
+            news_url: None,
+            mfa: None,
+            password_scheme: PasswordSchemeId::default(),
+            comment: None,
+        });
+        GridEditWindow {
+            title: title.to_string(),
+            id: egui::Id::new(id),
+            is_open: true,
+            old_key: grid_opt.map(|g| (g.metaverse.clone(), g.grid.clone())),
+            login_url: data.login_url.clone().unwrap_or_default(),
+            signup_url: data.signup_url.clone().unwrap_or_default(),
+            data,
+            grid_info_source,
+            fetch_opt: None,
+            error_opt: None,
+        }
+    }
+
+    /// As link
+    pub fn new_link(
+        id: &str,
+        title: &str,
+        grid_opt: Option<&GridSelectParamsData>,
+        grid_info_source: Arc<dyn GridInfoSource>,
+    ) -> GuiWindowLink {
+        Rc::new(RefCell::new(Self::new(
+            id,
+            title,
+            grid_opt,
+            grid_info_source,
+        )))
+    }
+
+    /// Start fetching grid info in the background.
+    //  Grids can take a while to answer, so this can't be done on the GUI thread.
+    fn start_fetch(&mut self) {
+        let (send, recv) = crossbeam_channel::bounded(1);
+        let source = Arc::clone(&self.grid_info_source);
+        let login_url = self.login_url.trim().to_string();
+        std::thread::spawn(move || {
+            let _ = send.send(source.get_grid_info(&login_url)); // window may be gone
+        });
+        self.fetch_opt = Some(recv);
+        self.error_opt = None;
+    }
+
+    /// Check for fetch completion. Fill in the fields from the answer.
+    fn poll_fetch(&mut self) {
+        if let Some(recv) = &self.fetch_opt {
+            match recv.try_recv() {
+                Ok(Ok(info)) => {
+                    self.data.grid = info.grid_name;
+                    self.login_url = info.login_url;
+                    if let Some(home_url) = info.home_url {
+                        self.data.home_url = home_url;
+                    }
+                    if let Some(signup_url) = info.signup_url {
+                        self.signup_url = signup_url;
+                    }
+                    self.fetch_opt = None;
+                }
+                Ok(Err(e)) => {
+                    log::warn!("Grid info fetch failed: {:?}", e);
+                    self.error_opt = Some(format!("{:#}", e));
+                    self.fetch_opt = None;
+                }
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => self.fetch_opt = None,
+            }
+        }
+    }
+
+    /// Enough filled in to save?
+    fn is_valid(&self) -> bool {
+        let login_url = self.login_url.trim().to_lowercase();
+        !self.data.metaverse.trim().is_empty()
+            && !self.data.grid.trim().is_empty()
+            && (login_url.starts_with("http://") || login_url.starts_with("https://"))
+    }
+
+    /// The entry to save.
+    fn get_data(&self) -> GridSelectParamsData {
+        fn non_empty(s: &str) -> Option<String> {
+            let s = s.trim();
+            if s.is_empty() {
+                None
+            } else {
+                Some(s.to_string())
+            }
+        }
+        let mut data = self.data.clone();
+        data.metaverse = data.metaverse.trim().to_string();
+        data.grid = data.grid.trim().to_string();
+        data.home_url = data.home_url.trim().to_string();
+        data.login_url = non_empty(&self.login_url);
+        data.signup_url = non_empty(&self.signup_url);
+        data
+    }
+}
+
+impl GuiWindow for GridEditWindow {
+    /// Draw the grid entry form.
+    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
+        const MINIMUM_TEXT_BOX_WIDTH: f32 = 300.0;
+        if self.is_open {
+            self.poll_fetch();
+            if self.fetch_opt.is_some() {
+                ctx.request_repaint(); // keep polling until the answer comes back
+            }
+            let mut accepted = false;
+            let mut not_cancelled = true;
+            let lang = state.get_lang();
+            let window = egui::containers::Window::new(self.title.as_str())
+                .id(self.id)
+                .collapsible(false)
+                .open(&mut not_cancelled)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0]);
+            window.show(ctx, |ui| {
+                egui::Grid::new("grid edit box")
+                    .num_columns(2)
+                    .min_col_width(MINIMUM_TEXT_BOX_WIDTH / 2.0)
+                    .show(ui, |ui| {
+                        ui.label(t!("menu.grid_edit.login_url", lang));
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.login_url)
+                                    .desired_width(MINIMUM_TEXT_BOX_WIDTH),
+                            );
+                            if self.fetch_opt.is_some() {
+                                ui.spinner();
+                            } else if ui
+                                .add_enabled(
+                                    !self.login_url.trim().is_empty(),
+                                    egui::Button::new(t!("menu.grid_edit.fetch", lang)),
+                                )
+                                .clicked()
+                            {
+                                self.start_fetch();
+                            }
+                        });
+                        ui.end_row();
+                        ui.label(t!("menu.grid_edit.metaverse", lang));
+                        ui.text_edit_singleline(&mut self.data.metaverse);
+                        ui.end_row();
+                        ui.label(t!("menu.grid_edit.grid", lang));
+                        ui.text_edit_singleline(&mut self.data.grid);
+                        ui.end_row();
+                        ui.label(t!("menu.grid_edit.home_url", lang));
+                        ui.text_edit_singleline(&mut self.data.home_url);
+                        ui.end_row();
+                        ui.label(t!("menu.grid_edit.signup_url", lang));
+                        ui.text_edit_singleline(&mut self.signup_url);
+                        ui.end_row();
+                    });
+                if let Some(error) = &self.error_opt {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+                ui.vertical_centered(|ui| {
+                    if ui
+                        .add_enabled(
+                            self.is_valid() && self.fetch_opt.is_none(),
+                            egui::Button::new(t!("menu.save", lang)),
+                        )
+                        .clicked()
+                    {
+                        accepted = true;
+                    }
+                });
+            });
+            if accepted {
+                let _ = state.send_boxed_gui_event(Box::new(GuiEvent::SaveUserGrid(
+                    self.old_key.clone(),
+                    self.get_data(),
+                )));
+            }
+            if accepted || !not_cancelled {
+                self.is_open = false;
+            } // do here to avoid borrow clash
+        }
+    }
+}
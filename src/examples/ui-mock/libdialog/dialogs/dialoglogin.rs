@@ -1,27 +1,28 @@
+use super::super::credentials::{credential_store, AccountIndex, CredentialKey};
+use super::super::passwordscheme::{PasswordScheme, PasswordSchemeId};
 use super::super::uiinfo::{GridSelectParams, GuiEvent};
 use anyhow::{anyhow, Error};
-use core::any::Any;
 use core::cell::RefCell;
-use keyring::Entry;
 use libui::t;
-use libui::{CommonState, Dictionary, GuiWindow, GuiWindowLink};
+use libui::{CommonState, Dictionary, ModalButton, ModalDialog, ModalDialogLink};
 ///#  guilogin.rs -- login dialog support
 //
 //  Animats
 //  October 2022
 //
 use std::rc::Rc;
-use zeroize::{Zeroize, ZeroizeOnDrop};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
 
 //  Dialog box parameters required for login.
 //  The password is zeroized as soon as it can be
-//  converted to MD5, and zeroized on drop if
+//  prepared for the grid's password scheme, and zeroized on drop if
 //  auth is cancelled.
+//  Not Debug, so it can't end up in a log or a crash report.
 #[derive(Default, ZeroizeOnDrop)]
 struct LoginDialogInput {
     user_name: String,
-    password: String,              // zeroize this as soon as MD5 is computed
-    _auth_token: Option<usize>,    // future when 2FA implemented.
+    password: String,              // zeroize this as soon as it is prepared
     destination: LoginDestination, // where do we want to go today?
 }
 
@@ -47,21 +48,36 @@ pub enum LoginDestination {
 
 /// Data needed to do a login.
 //  This is passed to the client, and contains the data the server needs for a login.
-#[derive(Debug)]
+//  Secrets are in zeroizing buffers, and are not shown by Debug.
+#[derive(Clone)]
 pub struct LoginParams {
-    pub grid: GridSelectParams,        // which grid
-    pub destination: LoginDestination, // where on grid
-    pub user_name: String,             // user name
-    password_md5_opt: Option<String>, // MD5 of the password, including the PASSWORD_PREFIX, ready for login.
-    pub auth_token: Option<usize>,    // future when 2FA implemented.
+    /// Which grid
+    pub grid: GridSelectParams,
+    /// Where on grid
+    pub destination: LoginDestination,
+    /// User name
+    pub user_name: String,
+    /// Password in the grid's scheme, ready for login.
+    prepared_password_opt: Option<Zeroizing<String>>,
+    /// One-time code from the second login step, if asked for
+    pub auth_token: Option<Zeroizing<String>>,
+    /// "Trust this device" token from an earlier login, if any
+    pub device_token: Option<Zeroizing<String>>,
+    /// Ask the server for a device token on this login
+    pub trust_device: bool,
+    /// Save the password once the login succeeds
+    pub remember_password: bool,
+    /// Delete the saved password once the login succeeds.
+    /// Only when the user unticked "remember" for an account with a saved password.
+    pub forget_password: bool,
+    /// Password was typed, not fetched from storage
+    password_is_new: bool,
 }
 
 #[allow(dead_code)]
 impl LoginParams {
-    pub const CREDENTIAL_PREFIX: &'static str = "metaverse"; // credential keys are prefixed with this.
     pub const CRED_TYPE_PASS: &'static str = "pass";
     pub const CRED_TYPE_TOKEN: &'static str = "token";
-    const PASSWORD_PREFIX: &'static str = "$1$"; // precedes password MD5 in hex. SL convention.
 
     /// New, without password    
     pub fn new(grid: GridSelectParams, destination: LoginDestination, user_name: String) -> Self {
@@ -69,129 +85,351 @@ impl LoginParams {
             grid,
             destination,
             user_name,
-            password_md5_opt: None,
+            prepared_password_opt: None,
             auth_token: None,
+            device_token: None,
+            trust_device: false,
+            remember_password: false,
+            forget_password: false,
+            password_is_new: false,
         }
     }
 
-    /// Translate special characters and make lower case
-    fn translate_special_characters(c: char) -> char {
-        match c {
-            '.' => '#',
-            '/' => '|',
-            _ => c,
-        }
-    }
-    /// Prep string for use as credential storage key.
-    /// Remove all whitespace.
-    /// Translate "." and "/" to something else.
-    fn prep_string(s: &str) -> String {
-        let s: String = s
-            .chars()
-            .filter(|c| !c.is_whitespace())
-            .map(Self::translate_special_characters)
-            .collect();
-        s.to_lowercase() // and force to lower case
+    /// Key for credential storage.
+    pub fn get_credential_key(&self) -> CredentialKey {
+        CredentialKey::new(
+            &self.grid.data.metaverse,
+            &self.grid.data.grid,
+            &self.user_name,
+        )
     }
     /// Returns the "service" string needed for credential storage.
-    /// Format is "PREFIX/SYSTEM/GRID".
+    /// Format is "PREFIX/SYSTEM/GRID/TYPE".
     pub fn get_service(&self, cred_type: &str) -> String {
-        format!(
-            "{}/{}/{}/{}",
-            Self::CREDENTIAL_PREFIX,
-            Self::prep_string(&self.grid.data.metaverse),
-            Self::prep_string(&self.grid.data.grid),
-            cred_type
-        )
+        self.get_credential_key().get_service(cred_type)
     }
-    /// Password as md5
-    pub fn get_password_md5(&self) -> Option<String> {
-        self.password_md5_opt.clone()
+    /// The grid's password scheme
+    pub fn get_password_scheme(&self) -> &'static dyn PasswordScheme {
+        self.grid.data.password_scheme.scheme()
     }
-    /// Set password from MD5
-    #[allow(clippy::manual_map)] // avoid excessive use of functional notation when it makes things more confusing
-    pub fn set_password_md5(&mut self, digest_opt: Option<md5::Digest>) {
-        self.password_md5_opt = if let Some(digest) = digest_opt {
-            Some(format!("{}{:032x}", Self::PASSWORD_PREFIX, digest)) // includes the prefix
-        } else {
-            None
+    /// Password, prepared for the grid's scheme.
+    pub fn get_prepared_password(&self) -> Option<&Zeroizing<String>> {
+        self.prepared_password_opt.as_ref()
+    }
+    /// Set password from what the user typed, using the grid's scheme.
+    pub fn set_password(&mut self, password: &str) -> Result<(), Error> {
+        let scheme = self.get_password_scheme();
+        if let Some(login_url) = &self.grid.data.login_url {
+            scheme.check_login_url(login_url)?;
         }
+        self.prepared_password_opt = Some(scheme.prepare(password));
+        self.password_is_new = true;
+        Ok(())
     }
 
-    /// Save password in platform secure storage
+    /// Save password in secure storage, and note the account in the index.
     pub fn save_password(&self) -> Result<(), Error> {
-        println!("Saving password {:?}", &self.password_md5_opt); // ***TEMP***
-        match &self.password_md5_opt {
-            Some(pass) => Entry::new(
-                &self.get_service(Self::CRED_TYPE_PASS),
-                &Self::prep_string(&self.user_name),
-            )
-            .set_password(pass)
-            .map_err(anyhow::Error::msg),
+        match &self.prepared_password_opt {
+            Some(pass) => save_password_for(
+                &self.get_credential_key(),
+                self.grid.data.password_scheme,
+                pass,
+            ),
             None => Err(anyhow!("Attempt to save empty password")),
         }
     }
-    /// Fetch password from platform secure storage.
+    /// Fetch password from secure storage.
+    /// A password prepared for some other scheme than the grid's is discarded,
+    /// so it has to be typed again.
     pub fn fetch_password(&mut self) -> Result<(), Error> {
-        let pass = Entry::new(
-            &self.get_service(Self::CRED_TYPE_PASS),
-            &Self::prep_string(&self.user_name),
-        )
-        .get_password()?;
-        self.password_md5_opt = Some(pass);
+        let key = self.get_credential_key();
+        let (pass, saved_scheme) = fetch_password_for(&key)?
+            .ok_or_else(|| anyhow!("No saved password for {}", self.user_name))?;
+        let scheme_id = self.grid.data.password_scheme;
+        if saved_scheme != scheme_id {
+            //  Sending it would send, say, an MD5 hash as a plain password.
+            log::warn!(
+                "Saved password for {} is {}, grid now wants {}. Discarding.",
+                self.user_name,
+                saved_scheme.scheme().get_name(),
+                scheme_id.scheme().get_name()
+            );
+            self.delete_password()?;
+            return Err(anyhow!(
+                "The grid has changed how it takes passwords. Please enter the password for {} again.",
+                self.user_name
+            ));
+        }
+        if let Some(login_url) = &self.grid.data.login_url {
+            scheme_id.scheme().check_login_url(login_url)?;
+        }
+        self.prepared_password_opt = Some(pass);
+        self.password_is_new = false;
         Ok(())
     }
     /// Delete password
     pub fn delete_password(&mut self) -> Result<(), Error> {
-        Entry::new(
-            &self.get_service(Self::CRED_TYPE_PASS),
-            &Self::prep_string(&self.user_name),
+        let key = self.get_credential_key();
+        credential_store().delete(&key.get_service(Self::CRED_TYPE_PASS), &key.get_user())
+    }
+
+    /// Login worked. Remember the user name for this grid, and
+    /// save or forget the password as the user asked.
+    //  Called only after the server accepts the login, so a mistyped password is never saved.
+    pub fn login_succeeded(&self) -> Result<(), Error> {
+        let key = self.get_credential_key();
+        match self.saved_password_action() {
+            SavedPasswordAction::Save => return self.save_password(), // also updates the index
+            SavedPasswordAction::Delete => credential_store()
+                .delete(&key.get_service(Self::CRED_TYPE_PASS), &key.get_user())?,
+            SavedPasswordAction::Keep => {}
+        }
+        let mut index = AccountIndex::load()?;
+        index.touch(&key, self.grid.data.password_scheme);
+        index.save()
+    }
+
+    /// What to do with the saved password once the login succeeds.
+    //  Not remembering a password doesn't forget one saved earlier.
+    //  Only unticking "remember" for it does.
+    fn saved_password_action(&self) -> SavedPasswordAction {
+        if self.remember_password && self.password_is_new {
+            SavedPasswordAction::Save
+        } else if self.forget_password {
+            SavedPasswordAction::Delete
+        } else {
+            SavedPasswordAction::Keep
+        }
+    }
+
+    /// Save "trust this device" token in secure storage.
+    /// Stored as "EXPIRY:TOKEN", expiry in seconds since the UNIX epoch.
+    pub fn save_device_token(&self, token: &str, expires: SystemTime) -> Result<(), Error> {
+        let key = self.get_credential_key();
+        let expiry_secs = expires.duration_since(UNIX_EPOCH)?.as_secs();
+        let stored = Zeroizing::new(format!("{}:{}", expiry_secs, token));
+        credential_store().set(
+            &key.get_service(Self::CRED_TYPE_TOKEN),
+            &key.get_user(),
+            &stored,
         )
-        .delete_password()
-        .map_err(anyhow::Error::msg)
     }
-    //  ***NEED TO ADD TOKEN ACCESS FOR 2FA***
+    /// Fetch "trust this device" token from secure storage.
+    /// Expired or unreadable tokens are deleted and None is returned.
+    pub fn fetch_device_token(&mut self) -> Result<Option<Zeroizing<String>>, Error> {
+        let key = self.get_credential_key();
+        let service = key.get_service(Self::CRED_TYPE_TOKEN);
+        let stored = match credential_store().get(&service, &key.get_user())? {
+            Some(stored) => stored,
+            None => return Ok(None), // no token, the usual case
+        };
+        let token_opt = Self::parse_device_token(&stored, SystemTime::now());
+        if token_opt.is_none() {
+            //  Expired or garbled. Get rid of it.
+            log::info!("Deleting expired device token for {}", self.user_name);
+            credential_store().delete(&service, &key.get_user())?;
+        }
+        self.device_token = token_opt.clone();
+        Ok(token_opt)
+    }
+    /// Parse stored "EXPIRY:TOKEN". None if expired or garbled.
+    fn parse_device_token(stored: &str, now: SystemTime) -> Option<Zeroizing<String>> {
+        let (expiry, token) = stored.split_once(':')?;
+        let expiry = UNIX_EPOCH.checked_add(Duration::from_secs(expiry.parse::<u64>().ok()?))?;
+        if token.is_empty() || expiry <= now {
+            None
+        } else {
+            Some(Zeroizing::new(token.to_string()))
+        }
+    }
+}
+
+/// What happens to the saved password after a login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SavedPasswordAction {
+    Save,   // the newly typed one
+    Keep,   // whatever is there, if anything
+    Delete, // user asked not to remember it
+}
+
+impl std::fmt::Debug for LoginParams {
+    /// Debug output, without the secrets.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let redact = |secret: &Option<Zeroizing<String>>| secret.as_ref().map(|_| "<redacted>");
+        f.debug_struct("LoginParams")
+            .field("grid", &self.grid.data.grid)
+            .field("metaverse", &self.grid.data.metaverse)
+            .field("destination", &self.destination)
+            .field("user_name", &self.user_name)
+            .field("password", &redact(&self.prepared_password_opt))
+            .field("auth_token", &redact(&self.auth_token))
+            .field("device_token", &redact(&self.device_token))
+            .field("trust_device", &self.trust_device)
+            .field("remember_password", &self.remember_password)
+            .field("forget_password", &self.forget_password)
+            .finish()
+    }
+}
+
+/// Save an already prepared password for an account, and note the account in the index.
+pub fn save_password_for(
+    key: &CredentialKey,
+    password_scheme: PasswordSchemeId,
+    pass: &str,
+) -> Result<(), Error> {
+    credential_store().set(
+        &key.get_service(LoginParams::CRED_TYPE_PASS),
+        &key.get_user(),
+        pass,
+    )?;
+    let mut index = AccountIndex::load()?;
+    index.touch(key, password_scheme);
+    index.save()
+}
+
+/// Fetch a saved password for an account, with the scheme it was prepared for.
+/// None if there is none, or if the index doesn't say how it was prepared.
+pub fn fetch_password_for(
+    key: &CredentialKey,
+) -> Result<Option<(Zeroizing<String>, PasswordSchemeId)>, Error> {
+    let Some(pass) = credential_store().get(
+        &key.get_service(LoginParams::CRED_TYPE_PASS),
+        &key.get_user(),
+    )?
+    else {
+        return Ok(None);
+    };
+    match AccountIndex::load()?.get(key) {
+        Some(entry) => Ok(Some((pass, entry.password_scheme))),
+        None => {
+            log::warn!(
+                "Saved password for {} is not in the account index",
+                key.user_name
+            );
+            Ok(None)
+        }
+    }
 }
 
-/// Login dialog window.
+/// Forget everything saved for an account: password, device token, and index entry.
+pub fn forget_account(key: &CredentialKey) -> Result<(), Error> {
+    let store = credential_store();
+    store.delete(
+        &key.get_service(LoginParams::CRED_TYPE_PASS),
+        &key.get_user(),
+    )?;
+    store.delete(
+        &key.get_service(LoginParams::CRED_TYPE_TOKEN),
+        &key.get_user(),
+    )?;
+    let mut index = AccountIndex::load()?;
+    index.remove(key);
+    index.save()
+}
+
+/// Login dialog. Modal.
 //  The persistent part.
 pub struct LoginDialogWindow {
-    title: String,                        // title of window
-    id: egui::Id,                         // unique ID
-    is_open: bool,                        // true if open
+    title: String,             // title of window
+    id: egui::Id,              // unique ID
+    login_label: &'static str, // translated button labels
+    cancel_label: &'static str,
     grid: GridSelectParams,               // info about grid
     login_dialog_input: LoginDialogInput, // user-provided data needed for login
     remember_password: bool,
+    known_users: Vec<String>, // user names used before on this grid, most recent first
+    has_saved_password: bool, // storage has a password for the current user name
+    use_saved_password: bool, // log in with the saved password instead of a typed one
+    checked_user_name: String, // user name for which has_saved_password was computed
 }
 
 impl LoginDialogWindow {
-    /// Create persistent text window, multiline
-    pub fn new(id: egui::Id, grid: &GridSelectParams) -> Self {
+    /// Create login dialog for a grid
+    pub fn new(id: egui::Id, grid: &GridSelectParams, lang: &Dictionary) -> Self {
         let title = format!("{} -- {}", grid.data.metaverse, grid.data.grid); // title is just grid name for now.
-        LoginDialogWindow {
+
+        //  Names previously used on this grid. Most recent one is the default.
+        let known_users: Vec<String> = match AccountIndex::load() {
+            Ok(index) => index
+                .accounts_for_grid(&grid.data.metaverse, &grid.data.grid)
+                .map(|a| a.key.user_name.clone())
+                .collect(),
+            Err(e) => {
+                log::error!("Unable to read account index: {:?}", e);
+                Vec::new()
+            }
+        };
+        let mut window = LoginDialogWindow {
             title,
             id,
+            login_label: t!("menu.login", lang),
+            cancel_label: t!("menu.cancel", lang),
             grid: grid.clone(),
-            is_open: true,
             login_dialog_input: Default::default(),
-            remember_password: true,
+            remember_password: credential_store().problem().is_none(),
+            known_users,
+            has_saved_password: false,
+            use_saved_password: false,
+            checked_user_name: String::new(),
+        };
+        if let Some(user_name) = window.known_users.first() {
+            window.login_dialog_input.user_name = user_name.clone();
         }
+        window.check_saved_password();
+        window
     }
 
     /// As link
-    pub fn new_link(id: egui::Id, grid: &GridSelectParams) -> GuiWindowLink {
-        Rc::new(RefCell::new(Self::new(id, grid)))
+    pub fn new_link(id: egui::Id, grid: &GridSelectParams, lang: &Dictionary) -> ModalDialogLink {
+        Rc::new(RefCell::new(Self::new(id, grid, lang)))
+    }
+
+    /// Look in storage for a password for the current user name.
+    /// Only looks when the user name has changed, not every frame.
+    fn check_saved_password(&mut self) {
+        let user_name = self.login_dialog_input.user_name.trim();
+        if user_name == self.checked_user_name {
+            return;
+        }
+        self.checked_user_name = user_name.to_string();
+        self.has_saved_password = if user_name.is_empty() {
+            false
+        } else {
+            let key =
+                CredentialKey::new(&self.grid.data.metaverse, &self.grid.data.grid, user_name);
+            match credential_store().get(
+                &key.get_service(LoginParams::CRED_TYPE_PASS),
+                &key.get_user(),
+            ) {
+                Ok(pass_opt) => pass_opt.is_some(),
+                Err(e) => {
+                    log::warn!("Unable to check for saved password: {:?}", e);
+                    false
+                }
+            }
+        };
+        //  Saved password is used unless the user types a new one.
+        self.use_saved_password = self.has_saved_password;
+        self.login_dialog_input.password.zeroize();
     }
 }
 
-impl GuiWindow for LoginDialogWindow {
+impl ModalDialog for LoginDialogWindow {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Log in, or cancel.
+    fn button_labels(&self) -> (&str, Option<&str>) {
+        (self.login_label, Some(self.cancel_label))
+    }
+
     /// Draw username/password form.
-    //  If username is blank, look up default user name for grid (future feature once prefs are implemented.)
-    //  If username is blank, and default name is found, look up password in password storage.
-    //  In that case, show actual username in username field and •••• in the password field.
-    //  If username is present, but password is blank, look up password MD5 in password storage.
-    //  New password is not stored here,
-    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
+    //  The user name starts as the one last used on this grid, with the others in a dropdown.
+    //  If storage has a password for the user name, show •••• in the password field.
+    //  Typing in the password field replaces the saved password for this login.
+    //  New password is not stored here. It is stored after the login succeeds.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, state: &mut CommonState) {
         //  Translated name of destination
         fn destination_name(dest: &LoginDestination, lang: &Dictionary) -> &'static str {
             match dest {
@@ -202,168 +440,231 @@ impl GuiWindow for LoginDialogWindow {
         }
 
         const MIMIMUM_TEXT_BOX_WIDTH: f32 = 200.0;
-        if self.is_open {
-            let mut accepted = false; // true if dismiss button pushed
-            let mut not_cancelled = true;
-            let window = egui::containers::Window::new(self.title.as_str())
-                .id(self.id)
-                .collapsible(false)
-                .open(&mut not_cancelled)
-                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0]);
-            window.show(ctx, |ui| {
-                egui::Grid::new("login box")
-                    .min_col_width(MIMIMUM_TEXT_BOX_WIDTH)
-                    .show(ui, |ui| {
-                        //  User name
-                        ui.horizontal(|ui| {
-                            ui.label(t!("menu.username", &state.params.lang));
-                            let _response = ui.add(egui::TextEdit::singleline(
-                                &mut self.login_dialog_input.user_name,
-                            ));
+        egui::Grid::new("login box")
+            .min_col_width(MIMIMUM_TEXT_BOX_WIDTH)
+            .show(ui, |ui| {
+                //  User name
+                ui.horizontal(|ui| {
+                    ui.label(t!("menu.username", &state.params.lang));
+                    let _response = ui.add(egui::TextEdit::singleline(
+                        &mut self.login_dialog_input.user_name,
+                    ));
+                    //  Dropdown of names used before on this grid
+                    if !self.known_users.is_empty() {
+                        ui.menu_button("⏷", |ui| {
+                            for name in &self.known_users {
+                                if ui.button(name).clicked() {
+                                    self.login_dialog_input.user_name = name.clone();
+                                    ui.close_menu();
+                                }
+                            }
                         });
-                        ui.end_row();
-                        //  Password
-                        ui.horizontal(|ui| {
-                            ui.label(t!("menu.password", &state.params.lang));
-                            let _ = ui.add(
-                                egui::TextEdit::singleline(&mut self.login_dialog_input.password)
+                    }
+                });
+                self.check_saved_password(); // user name may have changed
+                ui.end_row();
+                //  Password
+                ui.horizontal(|ui| {
+                    ui.label(t!("menu.password", &state.params.lang));
+                    if self.use_saved_password {
+                        //  Masked placeholder. Clicking on it allows typing a new password.
+                        let response = ui
+                            .add(
+                                egui::TextEdit::singleline(&mut String::new())
+                                    .hint_text("••••••••")
                                     .password(true),
-                            );
-                        });
-                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            )
+                            .on_hover_text(t!("menu.use_saved_password", &state.params.lang));
+                        if response.gained_focus() || response.changed() {
+                            self.use_saved_password = false;
+                        }
+                    } else {
+                        let _ = ui.add(
+                            egui::TextEdit::singleline(&mut self.login_dialog_input.password)
+                                .password(true),
+                        );
+                        if self.has_saved_password
+                            && ui
+                                .small_button(t!("menu.use_saved_password", &state.params.lang))
+                                .clicked()
+                        {
+                            self.login_dialog_input.password.zeroize();
+                            self.use_saved_password = true;
+                        }
+                    }
+                });
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    match credential_store().problem() {
+                        None => {
                             ui.checkbox(
                                 &mut self.remember_password,
                                 t!("menu.remember", &state.params.lang),
                             );
-                        });
-                        ui.end_row();
-                        //  Destination region
-                        //  Dropdown menu. egui calls this a "combo box", but it doesn't have a text input area.
-                        ui.horizontal(|ui| {
-                            ui.label(t!("menu.destination_region", &state.params.lang));
-                            egui::ComboBox::from_label("")
-                                .selected_text(destination_name(
-                                    &self.login_dialog_input.destination,
-                                    &state.params.lang,
-                                ))
-                                ////format!("{:?}", self.login_dialog_input.destination)) // No translation - fix this
-                                .show_ui(ui, |ui| {
-                                    ui.selectable_value(
-                                        &mut self.login_dialog_input.destination,
-                                        LoginDestination::Last,
-                                        t!("menu.last_location", &state.params.lang),
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.login_dialog_input.destination,
-                                        LoginDestination::Home,
-                                        t!("menu.home", &state.params.lang),
-                                    );
-                                    ui.selectable_value(
-                                        &mut self.login_dialog_input.destination,
-                                        LoginDestination::Region("".to_string()),
-                                        t!("menu.region", &state.params.lang),
-                                    );
-                                })
-                        });
-                        //  If combo box is "region", allow input of region name
-                        if let LoginDestination::Region(ref mut region_name) =
-                            &mut self.login_dialog_input.destination
-                        {
-                            let _ = ui.add(egui::TextEdit::singleline(region_name));
-                        };
-                    });
-
-                ui.vertical_centered(|ui| {
-                    let filled_in = self.login_dialog_input.is_filled_in(); // if form filled in
-                    if ui
-                        .add_enabled(
-                            filled_in,
-                            egui::Button::new(t!("menu.login", &state.params.lang)),
-                        )
-                        .clicked()
-                    {
-                        let password_md5_opt = if self.login_dialog_input.password.is_empty() {
-                            None
-                        } else {
-                            Some(md5::compute(&self.login_dialog_input.password.trim()))
-                            // get MD5 of password
-                        };
-                        self.login_dialog_input.zeroize(); // erase text password in memory
-                                                           /*
-                                                           let mut login_params = LoginParams {
-                                                               grid: self.grid.clone(),
-                                                               destination: self.login_dialog_input.destination.clone(), // which region
-                                                               user_name: self.login_dialog_input.user_name.trim().to_string(),
-                                                               ..Default::default()
-                                                           };
-                                                           */
-                        let mut login_params = LoginParams::new(
-                            self.grid.clone(),
-                            self.login_dialog_input.destination.clone(), // which region
-                            self.login_dialog_input.user_name.trim().to_string(),
-                        );
-
-                        if password_md5_opt.is_some() {
-                            // if a new password was typed in
-                            login_params.set_password_md5(password_md5_opt);
-                            //  Save newly typed password ***TEMP*** move this to after successful login.
-                            if self.remember_password {
-                                login_params.save_password().unwrap(); // ***NEED ERROR HANDLING***
-                            }
-                        } else {
-                            // try to get one from storage
-                            if login_params.fetch_password().is_err() {
-                                // if no stored password
-                                println!("Still need password"); // ***TEMP*** need to make beep sound here.
-                                return;
-                            }
                         }
-                        accepted = true; // dismiss dialog
-                        println!(
-                            "Attempting login to {}",
-                            login_params.get_service(LoginParams::CRED_TYPE_PASS)
-                        );
-                        let _ = state
-                            .send_boxed_gui_event(Box::new(GuiEvent::LoginStart(login_params)));
-                        // tell main which grid has been selected.
+                        Some(problem) => {
+                            //  Nowhere to keep it. Say so, and why.
+                            ui.add_enabled(
+                                false,
+                                egui::Checkbox::new(
+                                    &mut false,
+                                    t!("menu.remember", &state.params.lang),
+                                ),
+                            )
+                            .on_disabled_hover_text(format!(
+                                "{}: {}",
+                                t!("message.cannot_remember_passwords", &state.params.lang),
+                                problem
+                            ));
+                        }
                     }
                 });
+                ui.end_row();
+                //  Destination region
+                //  Dropdown menu. egui calls this a "combo box", but it doesn't have a text input area.
+                ui.horizontal(|ui| {
+                    ui.label(t!("menu.destination_region", &state.params.lang));
+                    egui::ComboBox::from_label("")
+                        .selected_text(destination_name(
+                            &self.login_dialog_input.destination,
+                            &state.params.lang,
+                        ))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.login_dialog_input.destination,
+                                LoginDestination::Last,
+                                t!("menu.last_location", &state.params.lang),
+                            );
+                            ui.selectable_value(
+                                &mut self.login_dialog_input.destination,
+                                LoginDestination::Home,
+                                t!("menu.home", &state.params.lang),
+                            );
+                            ui.selectable_value(
+                                &mut self.login_dialog_input.destination,
+                                LoginDestination::Region("".to_string()),
+                                t!("menu.region", &state.params.lang),
+                            );
+                        })
+                });
+                //  If combo box is "region", allow input of region name
+                if let LoginDestination::Region(ref mut region_name) =
+                    &mut self.login_dialog_input.destination
+                {
+                    let _ = ui.add(egui::TextEdit::singleline(region_name));
+                };
             });
-            if accepted || !not_cancelled {
-                self.is_open = false;
-            } // do here to avoid borrow clash
-            if !not_cancelled {
-                // user clicked close box
-                let _ = state.send_boxed_gui_event(Box::new(GuiEvent::Startup));
-                // back to ground state
+    }
+
+    /// Login needs at least a user name.
+    fn can_accept(&self) -> bool {
+        self.login_dialog_input.is_filled_in()
+    }
+
+    /// Start the login, or go back to the start screen.
+    fn finish(&mut self, state: &mut CommonState, button: ModalButton) -> bool {
+        if button == ModalButton::Cancel {
+            self.login_dialog_input.zeroize();
+            let _ = state.send_boxed_gui_event(Box::new(GuiEvent::Startup));
+            // back to ground state
+            return true;
+        }
+        let typed_password_opt =
+            if self.use_saved_password || self.login_dialog_input.password.is_empty() {
+                None
+            } else {
+                Some(Zeroizing::new(self.login_dialog_input.password.clone()))
+            };
+        self.login_dialog_input.zeroize(); // erase text password in memory
+        let mut login_params = LoginParams::new(
+            self.grid.clone(),
+            self.login_dialog_input.destination.clone(), // which region
+            self.login_dialog_input.user_name.trim().to_string(),
+        );
+
+        //  Saved or forgotten when the login succeeds, not before.
+        login_params.remember_password = self.remember_password;
+        login_params.forget_password = self.has_saved_password && !self.remember_password;
+        if let Some(typed_password) = typed_password_opt {
+            // if a new password was typed in
+            if let Err(e) = login_params.set_password(&typed_password) {
+                let errmsg = format!("{}", e);
+                state.add_error_window(&self.title, &[errmsg.as_str()]);
+                return false;
             }
-            //  Successful fake login.
-            if accepted {
-                ////state.app_state.change_mode(SystemMode::Connected);
-                let _ = state.send_boxed_gui_event(Box::new(GuiEvent::Connected));
-                ////let connected_menu = MenuConnected::new_link(&data.ui_app_assets);
-                ////state.set_menu_group(connected_menu);
+        } else {
+            // try to get one from storage
+            if let Err(e) = login_params.fetch_password() {
+                //  Not usable. Ask for a typed one.
+                self.has_saved_password = false;
+                self.use_saved_password = false;
+                let errmsg = format!("{}", e);
+                state.add_error_window(&self.title, &[errmsg.as_str()]);
+                return false;
             }
         }
-    }
-
-    /// If this is in the dynamic widgets list, drop if retain is false.
-    fn retain(&self) -> bool {
-        self.is_open
+        //  Use a "trust this device" token from an earlier login, if we have one.
+        if let Err(e) = login_params.fetch_device_token() {
+            log::warn!("Unable to read device token: {:?}", e);
+        }
+        log::info!(
+            "Attempting login to {}",
+            login_params.get_service(LoginParams::CRED_TYPE_PASS)
+        );
+        let _ = state.send_boxed_gui_event(Box::new(GuiEvent::LoginStart(login_params)));
+        // tell main which grid has been selected.
+        true
     }
 
     //  Access ID
     fn get_id(&self) -> egui::Id {
         self.id
     }
+}
 
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
+#[test]
+fn test_saved_password_action() {
+    let data: super::super::uiinfo::GridSelectParamsData = serde_json::from_str(
+        r#"{"metaverse": "OSGrid", "grid": "osgrid", "picture_bar": "", "home_url": ""}"#,
+    )
+    .unwrap();
+    let grid = GridSelectParams {
+        data,
+        picture_bar: libui::ImageHandle::new(egui::TextureId::default()),
+        user_defined: false,
+    };
+    let mut params = LoginParams::new(grid, LoginDestination::Last, "joe".to_string());
+    //  Saved password used, "remember" left ticked. Nothing to do.
+    params.remember_password = true;
+    assert_eq!(params.saved_password_action(), SavedPasswordAction::Keep);
+    //  New password typed, "remember" ticked. Saved.
+    params.set_password("secret").unwrap();
+    assert_eq!(params.saved_password_action(), SavedPasswordAction::Save);
+    //  New password typed, "remember" not ticked. One saved earlier stays.
+    params.remember_password = false;
+    assert_eq!(params.saved_password_action(), SavedPasswordAction::Keep);
+    //  User unticked "remember" for the saved password. Deleted.
+    params.forget_password = true;
+    assert_eq!(params.saved_password_action(), SavedPasswordAction::Delete);
+}
 
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
+#[test]
+fn test_parse_device_token() {
+    let now = UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let parse = |stored: &str| {
+        LoginParams::parse_device_token(stored, now).map(|token| token.as_str().to_string())
+    };
+    //  Valid. Only the first colon separates.
+    assert_eq!(parse("1000001:abc"), Some("abc".to_string()));
+    assert_eq!(parse("2000000:abc:def"), Some("abc:def".to_string()));
+    //  Expired, including expiring now.
+    assert_eq!(parse("999999:abc"), None);
+    assert_eq!(parse("1000000:abc"), None);
+    //  Malformed.
+    assert_eq!(parse(""), None);
+    assert_eq!(parse("abc"), None);
+    assert_eq!(parse("2000000:"), None);
+    assert_eq!(parse(":abc"), None);
+    assert_eq!(parse("-1:abc"), None);
+    assert_eq!(parse("soon:abc"), None);
+    assert_eq!(parse("18446744073709551615:abc"), None); // past the end of time
 }
@@ -0,0 +1,174 @@
+//! #  dialogmfa.rs -- second login step, one-time code entry.
+//!
+//! Appears when the login backend asks for a one-time (TOTP) code.
+//
+//  Animats
+//  October 2026
+//
+use super::super::uiinfo::GuiEvent;
+use super::dialoglogin::LoginParams;
+use core::cell::RefCell;
+use libui::t;
+use libui::{CommonState, Dictionary, ModalButton, ModalDialog, ModalDialogLink};
+use std::rc::Rc;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+/// Number of digits in a TOTP code. RFC 6238 allows 6 to 8; 6 is what everybody uses.
+const TOTP_CODE_LENGTH: usize = 6;
+
+//  Dialog box input for the second login step.
+//  The code is zeroized when sent and on drop.
+#[derive(Default, ZeroizeOnDrop)]
+struct MfaDialogInput {
+    code: String,       // one-time code as typed
+    trust_device: bool, // ask for a "trust this device" token
+}
+
+impl MfaDialogInput {
+    /// The code with spaces and dashes removed, if it is a valid TOTP code.
+    /// Authenticator apps often display "123 456".
+    fn valid_code(&self) -> Option<Zeroizing<String>> {
+        let code: Zeroizing<String> = self
+            .code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .into();
+        if code.len() == TOTP_CODE_LENGTH && code.chars().all(|c| c.is_ascii_digit()) {
+            Some(code)
+        } else {
+            None
+        }
+    }
+}
+
+/// One-time code dialog. Modal.
+//  The persistent part.
+pub struct MfaDialogWindow {
+    title: String,              // title of window
+    id: egui::Id,               // unique ID
+    verify_label: &'static str, // translated button labels
+    cancel_label: &'static str,
+    login_params: LoginParams,        // the login in progress
+    mfa_dialog_input: MfaDialogInput, // user-provided data
+    bad_code: bool,                   // last attempt was not a valid code
+}
+
+impl MfaDialogWindow {
+    /// Create one-time code dialog for a login in progress.
+    pub fn new(id: egui::Id, login_params: LoginParams, lang: &Dictionary) -> Self {
+        let title = format!(
+            "{} -- {}",
+            login_params.grid.data.metaverse, login_params.grid.data.grid
+        );
+        MfaDialogWindow {
+            title,
+            id,
+            verify_label: t!("menu.mfa.verify", lang),
+            cancel_label: t!("menu.cancel", lang),
+            login_params,
+            mfa_dialog_input: Default::default(),
+            bad_code: false,
+        }
+    }
+
+    /// As link
+    pub fn new_link(id: egui::Id, login_params: LoginParams, lang: &Dictionary) -> ModalDialogLink {
+        Rc::new(RefCell::new(Self::new(id, login_params, lang)))
+    }
+}
+
+impl ModalDialog for MfaDialogWindow {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Verify, or cancel the login.
+    fn button_labels(&self) -> (&str, Option<&str>) {
+        (self.verify_label, Some(self.cancel_label))
+    }
+
+    /// Draw the one-time code form.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, state: &mut CommonState) {
+        ui.label(t!("menu.mfa.prompt", &state.params.lang));
+        ui.horizontal(|ui| {
+            ui.label(t!("menu.mfa.code", &state.params.lang));
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.mfa_dialog_input.code)
+                    .char_limit(TOTP_CODE_LENGTH + 2) // allow for a separator
+                    .desired_width(100.0),
+            );
+            if response.changed() {
+                self.bad_code = false;
+            }
+        });
+        ui.checkbox(
+            &mut self.mfa_dialog_input.trust_device,
+            t!("menu.mfa.trust_device", &state.params.lang),
+        );
+        if self.bad_code {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                t!("menu.mfa.invalid_code", &state.params.lang),
+            );
+        }
+    }
+
+    /// Continue the login with the code, or go back to the start screen.
+    fn finish(&mut self, state: &mut CommonState, button: ModalButton) -> bool {
+        if button == ModalButton::Cancel {
+            let _ = state.send_boxed_gui_event(Box::new(GuiEvent::Startup));
+            // back to ground state
+            return true;
+        }
+        let code_opt = self.mfa_dialog_input.valid_code();
+        self.mfa_dialog_input.code.zeroize(); // erase typed code either way
+        match code_opt {
+            Some(code) => {
+                self.login_params.auth_token = Some(code);
+                self.login_params.trust_device = self.mfa_dialog_input.trust_device;
+                //  Back into the login pipeline, now with a code.
+                let _ = state.send_boxed_gui_event(Box::new(GuiEvent::LoginStart(
+                    self.login_params.clone(),
+                )));
+                true
+            }
+            None => {
+                self.bad_code = true;
+                false
+            }
+        }
+    }
+
+    //  Access ID
+    fn get_id(&self) -> egui::Id {
+        self.id
+    }
+}
+
+#[test]
+fn test_valid_code() {
+    let valid = |code: &str| {
+        MfaDialogInput {
+            code: code.to_string(),
+            trust_device: false,
+        }
+        .valid_code()
+        .map(|code| code.as_str().to_string())
+    };
+    //  As authenticator apps show them.
+    assert_eq!(valid("123456"), Some("123456".to_string()));
+    assert_eq!(valid("123 456"), Some("123456".to_string()));
+    assert_eq!(valid("123-456"), Some("123456".to_string()));
+    assert_eq!(valid(" 123456\n"), Some("123456".to_string()));
+    //  Wrong length.
+    assert_eq!(valid("12345"), None);
+    assert_eq!(valid("1234567"), None);
+    //  Not digits.
+    assert_eq!(valid("12345a"), None);
+    assert_eq!(valid("abcdef"), None);
+    assert_eq!(valid("１２３４５６"), None); // full width digits
+                                             //  Nothing.
+    assert_eq!(valid(""), None);
+    assert_eq!(valid(" - "), None);
+}
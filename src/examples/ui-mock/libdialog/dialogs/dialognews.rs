@@ -0,0 +1,92 @@
+//! #  dialognews.rs  -- news panel on the start screen
+//!
+//! Headlines from the grids' news feeds, down the right side.
+//! Clicking a headline opens it in the browser.
+//
+//  Animats
+//  October 2026
+//
+use super::super::gridnews::{NewsItem, NewsMonitor};
+use core::cell::RefCell;
+use libui::{t, Dictionary};
+use std::rc::Rc;
+use std::time::Duration;
+
+/// How often to check for news arriving.
+const NEWS_POLL: Duration = Duration::from_millis(250);
+/// Width of the news panel.
+const NEWS_PANEL_WIDTH: f32 = 300.0;
+
+/// The news panel.
+//  The persistent part.
+pub struct NewsPanel {
+    news_monitor: Rc<RefCell<NewsMonitor>>, // the news, arriving in the background
+}
+
+impl NewsPanel {
+    /// Usual new
+    pub fn new(news_monitor: Rc<RefCell<NewsMonitor>>) -> Self {
+        NewsPanel { news_monitor }
+    }
+
+    /// One headline. Date, then title, linked if there is a link.
+    fn draw_item(ui: &mut egui::Ui, item: &NewsItem) {
+        ui.horizontal_wrapped(|ui| {
+            if let Some(date) = &item.date {
+                ui.weak(date);
+            }
+            match &item.link {
+                Some(link) => {
+                    if ui.link(&item.title).on_hover_text(link).clicked() {
+                        if let Err(e) = webbrowser::open(link) {
+                            log::error!("Trouble trying to open web page \"{}\": {:?}", link, e);
+                        }
+                    }
+                }
+                None => {
+                    ui.label(&item.title);
+                }
+            }
+        });
+    }
+
+    /// Draw the panel. Must be called before the central panel is drawn.
+    //  No panel at all if no grid has a news feed.
+    pub fn draw(&mut self, ctx: &egui::Context, lang: &Dictionary) {
+        let mut news_monitor = self.news_monitor.borrow_mut();
+        let fetching = news_monitor.update();
+        if fetching {
+            ctx.request_repaint_after(NEWS_POLL);
+        }
+        if !news_monitor.has_feeds() {
+            return;
+        }
+        egui::SidePanel::right("news panel")
+            .default_width(NEWS_PANEL_WIDTH)
+            .show(ctx, |ui| {
+                ui.heading(t!("menu.news.title", lang));
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for grid_news in news_monitor.get_news() {
+                        ui.separator();
+                        ui.strong(&grid_news.grid_name);
+                        match &grid_news.result {
+                            Ok(items) if items.is_empty() => {
+                                ui.weak(t!("menu.news.none", lang));
+                            }
+                            Ok(items) => {
+                                for item in items {
+                                    Self::draw_item(ui, item);
+                                }
+                            }
+                            Err(e) => {
+                                ui.weak(t!("menu.news.unavailable", lang)).on_hover_text(e);
+                            }
+                        }
+                    }
+                    if fetching {
+                        ui.spinner();
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,134 @@
+//! #  dialogpreferences.rs  -- user preferences
+//!
+//! Display preferences. Changes apply at once and are saved.
+//! The window layout can also be reset here.
+//
+//  Animats
+//  October 2026
+//
+use core::cell::RefCell;
+use libui::t;
+use libui::{CommonState, GuiWindow, GuiWindowLink, UiScaleSettings, UI_SCALE_MAX, UI_SCALE_MIN};
+use std::rc::Rc;
+
+/// The preferences window.
+/// The persistent part.
+#[derive(GuiWindow)]
+pub struct PreferencesWindow {
+    /// Title of window
+    #[gui(title)]
+    title: String,
+    /// Unique ID
+    #[gui(id)]
+    id: egui::Id,
+    /// True if open. Set to false to make it close.
+    #[gui(is_open)]
+    is_open: bool,
+    /// UI scale settings being edited
+    ui_scale_settings: UiScaleSettings,
+}
+
+impl PreferencesWindow {
+    /// Name, for remembering the window layout.
+    pub const WINDOW_NAME: &'static str = "preferences";
+
+    /// Open the preferences window.
+    pub fn open_window(state: &mut CommonState) {
+        let window = Self::create(state);
+        state.add_window(window);
+    }
+
+    /// Create the preferences window. Also used to reopen it next session.
+    pub fn create(state: &mut CommonState) -> GuiWindowLink {
+        Self::new_link(
+            Self::WINDOW_NAME,
+            t!("window.preferences", state.get_lang()),
+            state,
+        )
+    }
+
+    /// Usual new
+    fn new(id: &str, title: &str, state: &CommonState) -> Self {
+        PreferencesWindow {
+            id: egui::Id::new(id),
+            title: title.to_string(),
+            is_open: true,
+            ui_scale_settings: state.ui_scale.get_settings(),
+        }
+    }
+
+    /// As link
+    fn new_link(id: &str, title: &str, state: &CommonState) -> GuiWindowLink {
+        Rc::new(RefCell::new(Self::new(id, title, state)))
+    }
+
+    /// Apply and save the UI scale.
+    fn apply_ui_scale(&mut self, state: &mut CommonState) {
+        state.ui_scale.set_settings(self.ui_scale_settings);
+        if let Err(e) = state.ui_scale.save() {
+            log::error!("Unable to save UI scale settings: {:?}", e);
+            let errmsg = format!("{}", e);
+            state.add_error_window(
+                t!("window.preferences", state.get_lang()),
+                &[errmsg.as_str()],
+            );
+        }
+    }
+}
+
+impl GuiWindow for PreferencesWindow {
+    /// Usual draw function
+    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
+        if self.is_open {
+            let mut not_cancelled = true;
+            let mut apply = false;
+            let mut reset_layout = false;
+            let lang = state.get_lang();
+            let window = egui::containers::Window::new(self.title.as_str())
+                .id(self.id)
+                .collapsible(false)
+                .resizable(false)
+                .open(&mut not_cancelled);
+            window.show(ctx, |ui| {
+                egui::Grid::new("preferences grid").show(ui, |ui| {
+                    ui.label(t!("menu.preferences.ui_scale", lang));
+                    //  Applied when the drag ends. Rescaling under the mouse makes the slider jump.
+                    let response = ui.add(
+                        egui::Slider::new(
+                            &mut self.ui_scale_settings.user_scale,
+                            UI_SCALE_MIN..=UI_SCALE_MAX,
+                        )
+                        .step_by(0.05),
+                    );
+                    apply |= response.drag_stopped() || (response.changed() && !response.dragged());
+                    ui.end_row();
+                    ui.label("");
+                    apply |= ui
+                        .checkbox(
+                            &mut self.ui_scale_settings.fit_to_window,
+                            t!("menu.preferences.fit_to_window", lang),
+                        )
+                        .changed();
+                    ui.end_row();
+                });
+                ui.separator();
+                if ui
+                    .button(t!("menu.preferences.reset_layout", lang))
+                    .on_hover_text(t!("menu.preferences.reset_layout_hover", lang))
+                    .clicked()
+                {
+                    reset_layout = true;
+                }
+            });
+            if reset_layout {
+                state.window_layout.reset();
+            }
+            if apply {
+                self.apply_ui_scale(state);
+            }
+            if !not_cancelled {
+                self.is_open = false;
+            } // do here to avoid borrow clash
+        }
+    }
+}
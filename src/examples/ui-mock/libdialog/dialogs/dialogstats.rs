@@ -1,166 +1,185 @@
 //! #  dialogstats.rs  -- statistics dialog
 //!
-//! Displays useful performance graphs.
+//! Displays useful performance graphs, one for each metric in the registry.
 //
 //  Animats
 //  May 2023
 //
-use core::any::Any;
 use core::cell::RefCell;
 use std::rc::Rc;
 ////use crate::GuiAssets;
 use egui::Widget;
-use libui::{t, CommonState, GuiWindow, GuiWindowLink, SendAnyBoxed, StatGraph};
+use libui::{
+    t, CommonState, GuiWindow, GuiWindowLink, MetricKind, StatGraph, METRICS_HISTORY_LENGTH,
+    METRICS_INTERVAL,
+};
 
-/// Event sent once per second to statistics window to update statistics.
-/// These represent most of the potential bottlenecks.
-#[derive(Default, Clone, Copy, Debug)]
-pub struct StatisticsEvent {
-    /// Frames per second, last second
-    pub frame_time_average: f32,
-    /// Longest frame time, last second.            
-    pub frame_time_longest: f32,
-    /*
-        /// UDP queue length, current
-        pub udp_queue_len: u32,
-        /// Move task fell behind, count, cumulative
-        pub move_task_lagging: usize,
-        /// UDP round trip time (seconds)
-        pub udp_round_trip_time: f32,
-        /// UDP Packets sent (cumulative)
-        pub packets_sent: usize,
-        /// UDP packets received (cumulative)
-        pub packets_received: usize,
-        /// UDP Packets that had to be retransmitted (cumulative)
-        pub packets_retransmitted: usize,
-        /// Incoming UDP packet numbers that were never seen, and thus lost.
-        pub packets_lost: usize,
-        /// Asset queue length (current)
-        pub asset_queue_len: usize,
-        /// Assets bytes_loaded (cumulative)
-        pub asset_bytes_loaded: usize,
-    */
-}
-
-impl StatisticsEvent {
-    /// For downcasting
-    #[allow(dead_code)]
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
+/// Graph of one metric.
+struct MetricGraph {
+    /// Metric name, in the registry
+    name: String,
+    /// Metric title, translated
+    title: String,
+    /// True if shown
+    shown: bool,
+    /// The graph
+    graph: StatGraph,
 }
 
 /// The performance statistics window.
 /// The persistent part.
+//  There is a graph for each metric in the registry, made when first seen.
+#[derive(GuiWindow)]
 pub struct StatisticsWindow {
     /// Title of window
+    #[gui(title)]
     title: String,
     /// Unique ID
+    #[gui(id)]
     id: egui::Id,
     /// True if open. Set to false to make it close.
+    #[gui(is_open)]
     is_open: bool,
-    /// Graph: FPS
-    frame_time_average: StatGraph,
-    /// Graph: worst FPS
-    frame_time_longest: StatGraph,
+    /// Graphs, in registry order
+    graphs: Vec<MetricGraph>,
+    /// Registry samples already in the graphs
+    sample_count: u64,
 }
 
 impl StatisticsWindow {
-    /// Open the statistics window.
-    pub fn open_window(state: &mut CommonState) {
-        //  Add window if not already open
-        let length = 100; // save 100 seconds of data for now
-        let window = Self::new_link(
-            "stats",
+    /// Name, for remembering the window layout.
+    pub const WINDOW_NAME: &'static str = "stats";
+
+    /// Open the statistics window, or close it if open.
+    pub fn toggle_window(state: &mut CommonState) {
+        state.toggle_window(egui::Id::new(Self::WINDOW_NAME), Self::create);
+    }
+
+    /// Create the statistics window. Also used to reopen it next session.
+    pub fn create(state: &mut CommonState) -> GuiWindowLink {
+        Self::new_link(
+            Self::WINDOW_NAME,
             t!("Performance statistics", state.get_lang()),
-            length,
-            state,
-        );
-        state
-            .add_window(window);
+        )
     }
 
-    /// Create statistics window data areas.
-    fn new(id: &str, title: &str, length: usize, state: &mut CommonState) -> Self {
+    /// Usual new. Graphs are added as metrics are seen.
+    fn new(id: &str, title: &str) -> Self {
         StatisticsWindow {
             id: egui::Id::new(id),
             title: title.to_string(),
             is_open: true,
-            frame_time_average: StatGraph::new(
-                t!("dialog.statistics.frame_time", state.get_lang()),
-                [0.0, 0.1],
-                length,
-                "fta",
-            ),
-            frame_time_longest: StatGraph::new(
-                t!("dialog.statistics.frame_time_worst", state.get_lang()),
-                [0.0, 0.1],
-                length,
-                "ftl",
-            ),
+            graphs: Vec::new(),
+            sample_count: 0,
         }
     }
+
     /// As link
-    fn new_link(id: &str, title: &str, length: usize, state: &mut CommonState) -> GuiWindowLink {
-        Rc::new(RefCell::new(Self::new(id, title, length, state)))
+    fn new_link(id: &str, title: &str) -> GuiWindowLink {
+        Rc::new(RefCell::new(Self::new(id, title)))
+    }
+
+    /// Bring the graphs up to date with the registry.
+    //  New metrics get graphs, filled from their history.
+    //  Samples taken while the window was closed are caught up.
+    fn update_graphs(&mut self, state: &CommonState) {
+        let sample_count = state.metrics.sample_count();
+        let new_samples = sample_count - self.sample_count;
+        self.sample_count = sample_count;
+        //  Time of a sample, in seconds, by sample number.
+        let time = |index: u64| index as f64 * METRICS_INTERVAL.as_secs_f64();
+        for metric in state.metrics.metrics() {
+            let history = metric.history();
+            let info = metric.info();
+            //  The graph, and where in the history its new samples start.
+            let (graph, start) = match self.graphs.iter().position(|g| g.name == info.name) {
+                Some(pos) => {
+                    if new_samples == 0 {
+                        continue;
+                    }
+                    let start = history.len().saturating_sub(new_samples as usize);
+                    (&mut self.graphs[pos].graph, start)
+                }
+                None => {
+                    let title = match info.kind {
+                        MetricKind::Gauge => info.title.clone(),
+                        MetricKind::Counter => format!(
+                            "{} {}",
+                            info.title,
+                            t!("dialog.statistics.per_second", state.get_lang())
+                        ),
+                    };
+                    let graph = StatGraph::new(
+                        title.as_str(),
+                        info.y_range,
+                        METRICS_HISTORY_LENGTH,
+                        &info.name,
+                    )
+                    .show_summary(true);
+                    self.graphs.push(MetricGraph {
+                        name: info.name.clone(),
+                        title,
+                        shown: true,
+                        graph,
+                    });
+                    (&mut self.graphs.last_mut().unwrap().graph, 0) // all the history
+                }
+            };
+            let first = sample_count - (history.len() - start) as u64;
+            for (i, &v) in history.range(start..).enumerate() {
+                graph.push_at(0, time(first + i as u64), v);
+            }
+            graph.set_thresholds(&info.thresholds);
+        }
     }
 }
 
 impl GuiWindow for StatisticsWindow {
     /// Usual draw function
-    fn draw(&mut self, ctx: &egui::Context, _state: &mut CommonState) {
+    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
         if self.is_open {
             let mut not_cancelled = true;
             let window = egui::containers::Window::new(self.title.as_str())
                 .id(self.id)
                 .collapsible(true)
                 .open(&mut not_cancelled);
-            window.show(ctx, |ui| {
-                ui.vertical(|ui| {
-                    self.frame_time_average.ui(ui);
-                    ui.separator();
-                    ui.end_row();
-                    self.frame_time_longest.ui(ui);
-                    ui.end_row();
-                });
-            });
+            window.show(ctx, |ui| self.draw_contents(ui, state));
             if !not_cancelled {
                 self.is_open = false;
             } // do here to avoid borrow clash
         }
     }
 
-    /// Incoming message event.
-    /// We get all GUI events, but only care about one type.
-    fn pass_event(&mut self, _state: &mut CommonState, event: &SendAnyBoxed) {
-        //  Is this the event we care about, the statistics event?
-        if let Some(ev) = event.downcast_ref::<StatisticsEvent>() {
-            //  Push data into plot
-            self.frame_time_average.push(ev.frame_time_average);
-            self.frame_time_longest.push(ev.frame_time_longest);
-        }
-    }
-
-    /// If this is in the dynamic widgets list, drop if retain is false.
-    fn retain(&self) -> bool {
-        self.is_open
-    }
-
-    //  Access ID
-    fn get_id(&self) -> egui::Id {
-        self.id
-    }
-
-    /// For downcasting
-    #[allow(dead_code)]
-    fn as_any(&self) -> &dyn Any {
-        todo!(); // lifetime problem
-                 ////self
+    /// Metric selection, then the graphs. Used both in the window and when docked.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, state: &mut CommonState) {
+        self.update_graphs(state);
+        ui.collapsing(t!("dialog.statistics.metrics", state.get_lang()), |ui| {
+            for graph in &mut self.graphs {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut graph.shown, graph.title.as_str());
+                    let mut log_y = graph.graph.is_log_y();
+                    if ui
+                        .checkbox(
+                            &mut log_y,
+                            t!("dialog.statistics.log_scale", state.get_lang()),
+                        )
+                        .changed()
+                    {
+                        graph.graph.set_log_y(log_y);
+                    }
+                });
+            }
+        });
+        ui.vertical(|ui| {
+            for graph in self.graphs.iter_mut().filter(|g| g.shown) {
+                ui.separator();
+                graph.graph.ui(ui);
+            }
+        });
     }
 
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        todo!(); // lifetime problem
-                 ////self
+    /// Can be docked.
+    fn dock_title(&self) -> Option<&str> {
+        Some(&self.title)
     }
 }
@@ -6,22 +6,31 @@
 //
 //  These are called from the render thread. Do not spend much time here.
 //
-use crate::t;
+use super::dialogcredentials::CredentialManagerWindow;
+use super::dialogpreferences::PreferencesWindow;
 use crate::GuiCommonEvent;
 use egui::Ui;
-use libui::CommonState;
+use libui::{t, CommonState, ConfirmRequest};
 
 //
 /// Avatar->Preferences
 pub fn menu_preferences(_ui: &mut Ui, state: &mut CommonState) {
-    //  Unimplemented
-    state.add_error_window(
-        t!("menu.unimplemented", state.get_lang()),
-        &[t!("menu.unimplemented", state.get_lang())],
-    );
+    PreferencesWindow::open_window(state);
+}
+
+/// Avatar->Saved passwords
+pub fn menu_saved_passwords(_ui: &mut Ui, state: &mut CommonState) {
+    CredentialManagerWindow::open_window(state);
 }
 
 /// Avatar->Quit
+/// Asks first.
 pub fn menu_quit(_ui: &mut Ui, state: &mut CommonState) {
-    let _ = state.send_gui_event(GuiCommonEvent::Shutdown); // tell main loop to quit
+    let request = ConfirmRequest {
+        title: t!("menu.avatar.quit", state.get_lang()).to_string(),
+        question: t!("menu.quit_confirm", state.get_lang()).to_string(),
+        on_yes: Box::new(GuiCommonEvent::Shutdown), // tell main loop to quit
+        on_no: None,
+    };
+    let _ = state.send_gui_event(GuiCommonEvent::Confirm(request));
 }
@@ -9,13 +9,13 @@
 use super::dialogclick::ClickWindow;
 use super::dialogstats::StatisticsWindow;
 use super::menuavatar;
-use super::menuhelp::{menu_help_about, menu_help_manual}; // submenus
-use crate::UiAppAssets;
-use core::any::Any;
+use super::menuhelp::{menu_help_about, menu_help_diagnostics, menu_help_manual}; // submenus
 use core::cell::RefCell;
 use egui::{menu, Frame};
 use libui::t;
-use libui::{CommonState, MenuGroup, MenuGroupLink, NavAction, NavArrows};
+use libui::{
+    CommonState, Dictionary, IconRegistry, MenuGroup, MenuGroupLink, NavAction, NavArrows,
+};
 use log::LevelFilter;
 use std::rc::Rc;
 #[allow(clippy::blocks_in_if_conditions)] // allow excessive nesting, which is the style Egui uses.
@@ -38,36 +38,42 @@ const TRANSLUCENT_GREY_COLOR32: egui::Color32 = egui::Color32::from_rgba_premult
 //
 //  The overlay on the main screen. Menus disappear when not used.
 //  Cursor to top or bottom of window restores them.
-pub struct MenuConnected<'a> {
-    move_arrows: NavArrows<'a>,
-    rot_arrows: NavArrows<'a>,
+#[derive(MenuGroup)]
+#[gui(name = "Connected")]
+pub struct MenuConnected {
+    move_arrows: NavArrows,
+    rot_arrows: NavArrows,
+    new_log_module: String, // module name typed, to give its own log level
 }
 
-impl MenuConnected<'_> {
+impl MenuConnected {
     /// Create new, as trait object. Provide needed graphical assets.
-    pub fn new_link(assets: &UiAppAssets) -> MenuGroupLink {
+    pub fn new_link(icons: &IconRegistry) -> MenuGroupLink {
         //  Two four-way controls with a center reset button
         let button_size = egui::Vec2::splat(64.0);
+        let pressed_arrow = icons.get("arrow-pressed").texture;
+        let pressed_button = icons.get("center-pressed").texture;
         Rc::new(RefCell::new(MenuConnected {
             move_arrows: NavArrows::new(
-                (assets.move_arrows_icon, button_size),
-                (assets.pressed_arrow_icon, button_size),
-                (assets.pressed_button_icon, button_size),
+                (icons.get("move-arrows").texture, button_size),
+                (pressed_arrow, button_size),
+                (pressed_button, button_size),
                 8.0,
                 "Move camera",
             ),
             rot_arrows: NavArrows::new(
-                (assets.rot_arrows_icon, button_size),
-                (assets.pressed_arrow_icon, button_size),
-                (assets.pressed_button_icon, button_size),
+                (icons.get("rot-arrows").texture, button_size),
+                (pressed_arrow, button_size),
+                (pressed_button, button_size),
                 8.0,
                 "Aim camera",
             ),
+            new_log_module: String::new(),
         })) // create a trait object to dispatch
     }
 }
 
-impl MenuGroup for MenuConnected<'_> {
+impl MenuGroup for MenuConnected {
     /// Draws the menu set for Login state.
     //  Called on every frame. Do not delay here.
     fn draw(&mut self, state: &mut CommonState) -> bool {
@@ -88,6 +94,13 @@ impl MenuGroup for MenuConnected<'_> {
                             menuavatar::menu_preferences(ui, state);
                         }
 
+                        if ui
+                            .button(t!("menu.avatar.saved_passwords", state.get_lang()))
+                            .clicked()
+                        {
+                            menuavatar::menu_saved_passwords(ui, state);
+                        }
+
                         if ui
                             .button(t!("menu.avatar.quit", state.get_lang()))
                             .clicked()
@@ -125,6 +138,24 @@ impl MenuGroup for MenuConnected<'_> {
                             .clicked()
                         {}
                     });
+                    ui.menu_button(t!("menu.window", state.get_lang()), |ui| {
+                        // Window menu. Open windows, click to bring to front.
+                        let windows = state.list_windows();
+                        if windows.is_empty() {
+                            ui.label(t!("menu.window.none", state.get_lang()));
+                        }
+                        for (id, title) in windows {
+                            if ui.button(title).clicked() {
+                                state.bring_to_front(id);
+                                ui.close_menu();
+                            }
+                        }
+                        ui.separator();
+                        if ui.button(t!("window.messages", state.get_lang())).clicked() {
+                            // Message window can be closed, so it has to be reopenable.
+                            state.open_log_console();
+                        }
+                    });
                     ui.menu_button(t!("menu.help", state.get_lang()), |ui| {
                         // Help menu
                         if ui.button(t!("menu.help", state.get_lang())).clicked() {
@@ -134,52 +165,64 @@ impl MenuGroup for MenuConnected<'_> {
                             // About menu entry
                             menu_help_about(ui, state);
                         }
+                        if ui.button(t!("menu.help.diagnostics", state.get_lang())).clicked() {
+                            // Bundle logs and settings for a bug report
+                            menu_help_diagnostics(ui, state);
+                        }
                     });
                     ui.menu_button(t!("menu.developer", state.get_lang()), |ui| {
                         //  Log level setting submenu
-                        let lang = state.get_lang();
-                        let mut log_level = state.params.log_level; // avoid multiple partial borrow of same struct
-                        ui.menu_button(t!("menu.developer.log_level", lang), |ui| {
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Off,
-                                t!("menu.log_level.off", lang),
-                            );
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Error,
-                                t!("menu.log_level.error", lang),
-                            );
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Warn,
-                                t!("menu.log_level.warn", lang),
-                            );
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Info,
-                                t!("menu.log_level.info", lang),
-                            );
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Debug,
-                                t!("menu.log_level.debug", lang),
-                            );
-                            ui.radio_value(
-                                &mut log_level,
-                                LevelFilter::Trace,
-                                t!("menu.log_level.trace", lang),
-                            );
+                        ui.menu_button(t!("menu.developer.log_level", state.get_lang()), |ui| {
+                            let controller = &state.params.log_controller;
+                            let mut settings = controller.get_settings();
+                            let lang = state.get_lang();
+                            ui.label(t!("menu.developer.log_file", lang));
+                            log_level_radio_buttons(ui, &mut settings.file_level, lang);
+                            ui.separator();
+                            ui.label(t!("menu.developer.log_console", lang));
+                            log_level_radio_buttons(ui, &mut settings.gui_level, lang);
+                            //  Modules with their own level, for when one module needs watching.
+                            ui.separator();
+                            ui.label(t!("menu.developer.log_modules", lang));
+                            for (module, level) in settings.module_levels.clone() {
+                                ui.menu_button(module.as_str(), |ui| {
+                                    let mut level = level;
+                                    log_level_radio_buttons(ui, &mut level, lang);
+                                    ui.separator();
+                                    if ui
+                                        .button(t!("menu.developer.log_module_usual", lang))
+                                        .clicked()
+                                    {
+                                        settings.set_module_level(&module, None);
+                                        ui.close_menu();
+                                    } else {
+                                        settings.set_module_level(&module, Some(level));
+                                    }
+                                });
+                            }
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut self.new_log_module);
+                                let module = self.new_log_module.trim();
+                                if ui.button(t!("menu.developer.log_module_add", lang)).clicked()
+                                    && !module.is_empty()
+                                {
+                                    settings.set_module_level(module, Some(LevelFilter::Debug));
+                                    self.new_log_module.clear();
+                                }
+                            });
+                            if settings != controller.get_settings() {
+                                controller.set_settings(settings);
+                                if let Err(e) = controller.save() {
+                                    log::error!("Unable to save log levels: {:?}", e);
+                                }
+                            }
                         });
-                        //  ***MOVE LOG LEVEL TO STATE*** params is read-only.
-                        ////state.params.log_level = log_level;     // update log level
                         //  Statistics menu
                         if ui
                             .button(t!("menu.developer.statistics", state.get_lang()))
                             .clicked()
                         {
-                            ////println!("Statistics menu");
-                            StatisticsWindow::open_window(state);
+                            StatisticsWindow::toggle_window(state);
                         }
                         //  End statistics menu
                         #[cfg(feature = "replay")]
@@ -217,24 +260,18 @@ impl MenuGroup for MenuConnected<'_> {
                 });
         }
         //  Non-menu items
-        state.message_window.draw(&ctx, &state.params); // dummy test window
         state.draw(&ctx); // all the standard windows
                           //  Finish
         ctx.is_pointer_over_area() // True if GUI is in use
     }
+}
 
-    /// Ident for debug purposes
-    fn get_name(&self) -> &'static str {
-        "Connected"
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        todo!(); //// self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        todo!(); //// self
-    }
+/// One radio button for each log level.
+fn log_level_radio_buttons(ui: &mut egui::Ui, level: &mut LevelFilter, lang: &Dictionary) {
+    ui.radio_value(level, LevelFilter::Off, t!("menu.log_level.off", lang));
+    ui.radio_value(level, LevelFilter::Error, t!("menu.log_level.error", lang));
+    ui.radio_value(level, LevelFilter::Warn, t!("menu.log_level.warn", lang));
+    ui.radio_value(level, LevelFilter::Info, t!("menu.log_level.info", lang));
+    ui.radio_value(level, LevelFilter::Debug, t!("menu.log_level.debug", lang));
+    ui.radio_value(level, LevelFilter::Trace, t!("menu.log_level.trace", lang));
 }
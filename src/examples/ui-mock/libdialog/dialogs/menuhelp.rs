@@ -5,12 +5,17 @@
 //  Animats
 //  November 2022
 //
+use super::super::usergrids::{GRID_PREFERENCES_FILE_NAME, USER_GRID_FILE_NAME};
 use egui::Ui;
-use libui::{t, CommonState, TextWindow};
+use libui::{t, CommonState, GuiWindowLink, TextWindow};
 /// Configuration
 const HELP_PAGE: &str =
     "https://github.com/John-Nagle/ui-mock#ui-mock---mockup-of-a-game-type-user-interface";
 const COPYRIGHT: &str = "© 2022 Animats";
+/// Name of the About window, for remembering the window layout.
+pub const ABOUT_WINDOW_NAME: &str = "about window";
+/// Default name for the diagnostics bundle.
+const DIAGNOSTICS_FILE_NAME: &str = "diagnostics.zip";
 
 /// Help->Help
 pub fn menu_help_manual(_ui: &mut Ui, state: &mut CommonState) {
@@ -25,9 +30,35 @@ pub fn menu_help_manual(_ui: &mut Ui, state: &mut CommonState) {
         }
     }
 }
+/// Help->About
 pub fn menu_help_about(_ui: &mut Ui, state: &mut CommonState) {
-    //  Create window if necessary
-    //  Generate system information dump
+    let about_window = about_window(state);
+    state.add_window(about_window);
+}
+
+/// Help->Collect diagnostics
+//  Zips the recent logs, system information and preferences, for bug reports.
+pub fn menu_help_diagnostics(_ui: &mut Ui, state: &mut CommonState) {
+    let Some(log_files) = state.params.log_controller.log_files().cloned() else {
+        log::error!("No log files, so no diagnostics.");
+        return;
+    };
+    let info = system_info(state);
+    state.save_file_async(
+        t!("menu.help.diagnostics", state.get_lang()),
+        DIAGNOSTICS_FILE_NAME,
+        move |path| {
+            log_files.collect_diagnostics(
+                path,
+                &info,
+                &[USER_GRID_FILE_NAME, GRID_PREFERENCES_FILE_NAME],
+            )
+        },
+    );
+}
+
+/// System information, one item per line. For the About window and diagnostics.
+pub fn system_info(state: &CommonState) -> Vec<String> {
     let if_unknown = |x| {
         if let Some(v) = x {
             v
@@ -35,58 +66,58 @@ pub fn menu_help_about(_ui: &mut Ui, state: &mut CommonState) {
             "unknown".to_string()
         }
     }; // for Option
-       //  Need to create new window
     let mut msgs = Vec::new();
-    let version = format!(
+    msgs.push(format!(
         "{}: {}",
         t!("message.version", state.get_lang()),
         state.params.executable_version
-    );
-    msgs.push(version.as_str());
+    ));
     use sysinfo::SystemExt;
     let mut sys = sysinfo::System::new_all(); // get system information
                                               //  System info
     sys.refresh_all();
-    let os_info = format!(
+    msgs.push(format!(
         "{}: {}, {}",
         t!("message.os_version", state.get_lang()),
         if_unknown(sys.name()),
         if_unknown(sys.long_os_version())
-    );
-    msgs.push(os_info.as_str());
+    ));
     //  CPU info
-    let system_memory = format!(
+    msgs.push(format!(
         "{}: {:?}",
         t!("message.system_memory", state.get_lang()),
         sys.total_memory()
-    );
-    msgs.push(system_memory.as_str());
-    let cpu_count = format!(
+    ));
+    msgs.push(format!(
         "{}: {}",
         t!("message.cpu_count", state.get_lang()),
         sys.cpus().len()
-    );
-    msgs.push(cpu_count.as_str());
+    ));
     //  Graphics subsystem info
-    let gpu_name = format!(
+    msgs.push(format!(
         "{}: {:?}, {}",
         t!("message.gpu_name", state.get_lang()),
         state.params.gpu_info.device_type,
         state.params.gpu_info.name
-    );
-    msgs.push(gpu_name.as_str());
-    let graphics_system = format!(
+    ));
+    msgs.push(format!(
         "{}: {:?}",
         t!("message.graphics_system", state.get_lang()),
         state.params.gpu_info.backend
-    );
-    msgs.push(graphics_system.as_str());
+    ));
+    msgs
+}
+
+/// The About window. Also used to reopen it next session.
+pub fn about_window(state: &mut CommonState) -> GuiWindowLink {
+    //  Generate system information dump
+    let info = system_info(state);
+    let mut msgs: Vec<&str> = info.iter().map(|s| s.as_str()).collect();
     msgs.push(COPYRIGHT); // copyright notice
-    let about_window = TextWindow::new_link(
-        egui::Id::new("about window"),
+    TextWindow::new_link(
+        egui::Id::new(ABOUT_WINDOW_NAME),
         t!("menu.help.about", state.get_lang()),
         &msgs,
         Some(t!("menu.ok", state.get_lang())),
-    );
-    let _stat = state.add_window(about_window);
+    )
 }
@@ -7,7 +7,6 @@
 //  November 2022
 //
 use super::super::uiinfo::GuiEvent;
-use core::any::Any;
 use core::cell::RefCell;
 use libui::t;
 use libui::{CommonState, MenuGroup, MenuGroupLink};
@@ -19,6 +18,8 @@ use std::rc::Rc;
 //
 //  The start screen. A scrolling list of big image buttons, one
 //  for each metaverse.
+#[derive(MenuGroup)]
+#[gui(name = "Login")]
 pub struct MenuLogin {}
 
 impl MenuLogin {
@@ -46,6 +47,9 @@ impl MenuGroup for MenuLogin {
             }
         });
 
+        //  Docks, which must come before the central panel
+        state.draw_docks(&ctx);
+
         //  Central panel
         egui::CentralPanel::default().show(&ctx, |ui| {
             //  Login dialog
@@ -58,18 +62,9 @@ impl MenuGroup for MenuLogin {
         true
     }
 
-    /// Ident for debug purposes
-    fn get_name(&self) -> &'static str {
-        "Login"
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
+    /// Back to the start menu. Goes through the event handler, which tracks the mode.
+    fn on_back(&mut self, state: &mut CommonState) -> bool {
+        let _ = state.send_boxed_gui_event(Box::new(GuiEvent::Startup));
+        false
     }
 }
@@ -7,11 +7,12 @@
 //  Animats
 //  November 2022
 //
-use super::super::dialogs::dialoggrid::GridSelectWindow;
+use super::super::dialogs::dialoggrid::{GridSelectAction, GridSelectWindow};
+use super::super::dialogs::dialoggridedit::GridEditWindow;
+use super::super::dialogs::dialognews::NewsPanel;
 use super::super::uiinfo::GuiEvent;
-use core::any::Any;
 use core::cell::RefCell;
-use libui::{CommonState, MenuGroup, MenuGroupLink};
+use libui::{t, CommonState, ConfirmRequest, MenuGroup, MenuGroupLink};
 use std::rc::Rc;
 
 #[allow(clippy::blocks_in_if_conditions)] // allow excessive nesting, which is the style Egui uses.
@@ -21,14 +22,20 @@ use std::rc::Rc;
 //
 //  The start screen. A scrolling list of big image buttons, one
 //  for each metaverse.
+#[derive(MenuGroup)]
+#[gui(name = "Start")]
 pub struct MenuStart {
     grid_select_window: GridSelectWindow, // the window with the big buttons..
+    news_panel: NewsPanel,                // grid news, at the side
 }
 
 impl MenuStart {
     /// Create new, as trait object
-    pub fn new_link(grid_select_window: GridSelectWindow) -> MenuGroupLink {
-        Rc::new(RefCell::new(MenuStart { grid_select_window })) // create a trait object to dispatch
+    pub fn new_link(grid_select_window: GridSelectWindow, news_panel: NewsPanel) -> MenuGroupLink {
+        Rc::new(RefCell::new(MenuStart {
+            grid_select_window,
+            news_panel,
+        })) // create a trait object to dispatch
     }
 }
 
@@ -38,32 +45,50 @@ impl MenuGroup for MenuStart {
     fn draw(&mut self, state: &mut CommonState) -> bool {
         // Insert egui commands here to draw the menus for thie state.
         let ctx = state.context.clone();
+        //  Docks and news go at the sides, so are drawn first.
+        //  Docks are outermost, where dropping a window docks it.
+        state.draw_docks(&ctx);
+        self.news_panel.draw(&ctx, state.get_lang());
         //  Draw the splash screen with a big set of alternative metaverses.
         //
         egui::CentralPanel::default().show(&ctx, |_ui| {
-            if let Some(grid) = self.grid_select_window.draw(&ctx) {
-                // select desired grid
-                //  A grid has been selected
-                let _ = state.send_boxed_gui_event(Box::new(GuiEvent::LoginTo(grid)));
-                // tell main which grid has been selected.
+            let modal_open = state.is_modal_open();
+            match self
+                .grid_select_window
+                .draw(&ctx, state.get_lang(), modal_open)
+            {
+                Some(GridSelectAction::Select(grid)) => {
+                    //  A grid has been selected
+                    let _ = state.send_boxed_gui_event(Box::new(GuiEvent::LoginTo(grid)));
+                    // tell main which grid has been selected.
+                }
+                Some(GridSelectAction::Add) => GridEditWindow::open_window(state, None),
+                Some(GridSelectAction::Edit(grid)) => {
+                    GridEditWindow::open_window(state, Some(&grid.data))
+                }
+                Some(GridSelectAction::Delete(grid)) => {
+                    //  Can't be undone, so ask first.
+                    let lang = state.get_lang();
+                    let request = ConfirmRequest {
+                        title: t!("menu.grid_edit.delete", lang).to_string(),
+                        question: format!(
+                            "{}\n{} -- {}",
+                            t!("message.delete_grid_confirm", lang),
+                            grid.data.metaverse,
+                            grid.data.grid
+                        ),
+                        on_yes: Box::new(GuiEvent::DeleteUserGrid(
+                            grid.data.metaverse,
+                            grid.data.grid,
+                        )),
+                        on_no: None,
+                    };
+                    state.confirm(request);
+                }
+                None => {}
             }
             state.draw(&ctx); // all the standard windows
         });
         true // menus must stay visible, not time out
     }
-
-    /// Ident for debug purposes
-    fn get_name(&self) -> &'static str {
-        "Start"
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
 }
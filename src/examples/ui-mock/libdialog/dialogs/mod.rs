@@ -4,8 +4,13 @@
 //  Menus and dialogs. Usually one per file.
 //  Dialogs
 pub mod dialogclick;
+pub mod dialogcredentials;
 pub mod dialoggrid;
+pub mod dialoggridedit;
 pub mod dialoglogin;
+pub mod dialogmfa;
+pub mod dialognews;
+pub mod dialogpreferences;
 pub mod dialogstats;
 
 //  Menus and submenus
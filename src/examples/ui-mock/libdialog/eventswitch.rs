@@ -8,13 +8,84 @@
 //  November, 2022
 //
 use super::dialogs;
+use dialogs::dialogcredentials::CredentialManagerWindow;
 use dialogs::dialoggrid::GridSelectWindow;
-use dialogs::dialoglogin::LoginDialogWindow;
+use dialogs::dialoglogin::{LoginDialogWindow, LoginParams};
+use dialogs::dialogmfa::MfaDialogWindow;
+use dialogs::dialognews::NewsPanel;
+use dialogs::dialogpreferences::PreferencesWindow;
+use dialogs::dialogstats::StatisticsWindow;
 use dialogs::menuconnected::MenuConnected;
+use dialogs::menuhelp::{about_window, ABOUT_WINDOW_NAME};
 
 use super::uiinfo;
-use libui::t;
-use uiinfo::{pick_replay_file_async, GuiEvent, SystemMode, UiData};
+use libui::{t, CommonState, GuiState, MenuGroupLink, MenuTransition, StateMachine};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+use uiinfo::{pick_replay_file_async, GuiEvent, SystemMode, UiData, UiInfo};
+use zeroize::Zeroizing;
+
+/// How long a "trust this device" token lasts. Real grids decide this.
+const DEVICE_TOKEN_LIFETIME: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Register the windows whose layout is remembered between sessions.
+pub fn register_windows(state: &mut CommonState) {
+    state.register_window(StatisticsWindow::WINDOW_NAME, StatisticsWindow::create);
+    state.register_window(PreferencesWindow::WINDOW_NAME, PreferencesWindow::create);
+    state.register_window(
+        CredentialManagerWindow::WINDOW_NAME,
+        CredentialManagerWindow::create,
+    );
+    state.register_window(ABOUT_WINDOW_NAME, about_window);
+}
+
+/// The main system modes, what can follow what, and the menus for each.
+pub fn system_mode_machine() -> StateMachine<SystemMode, GuiState<UiInfo>> {
+    StateMachine::<SystemMode, GuiState<UiInfo>>::new("SystemMode", SystemMode::Startup)
+        .menu(SystemMode::Startup, start_menu)
+        .on_enter(SystemMode::Startup, |gui_state| {
+            gui_state.app_state.selected_grid = None // cancel grid selection
+        })
+        .push_menu(
+            SystemMode::Login,
+            |_| dialogs::menulogin::MenuLogin::new_link(),
+            MenuTransition::Slide,
+        )
+        .menu(SystemMode::Connected, |gui_state| {
+            MenuConnected::new_link(&gui_state.common_state.assets.icons)
+        })
+        .on_enter(SystemMode::Connected, |gui_state| {
+            gui_state.common_state.open_log_console(); // messages for the user
+            gui_state.common_state.restore_windows(); // windows open last session
+        })
+        //  Startup goes to itself to redisplay the grid list.
+        .allow(
+            SystemMode::Startup,
+            &[SystemMode::Startup, SystemMode::Login, SystemMode::Shutdown],
+        )
+        .allow(
+            SystemMode::Login,
+            &[
+                SystemMode::Startup,
+                SystemMode::Connected,
+                SystemMode::Shutdown,
+            ],
+        )
+        .allow(SystemMode::Connected, &[SystemMode::Shutdown])
+}
+
+/// The start menu, with the grids to choose from.
+fn start_menu(gui_state: &mut GuiState<UiInfo>) -> MenuGroupLink {
+    let grid_select_window = GridSelectWindow::new(
+        "Grid select",
+        t!("window.grid_select", &gui_state.common_state.params.lang),
+        &gui_state.common_state.assets,
+        gui_state.app_state.grid_select_params.clone(),
+        Rc::clone(&gui_state.app_state.grid_status),
+    );
+    let news_panel = NewsPanel::new(Rc::clone(&gui_state.app_state.grid_news));
+    dialogs::menustart::MenuStart::new_link(grid_select_window, news_panel)
+}
 
 ///  Handle GuiEvent
 pub fn handle_gui_event(data: &mut UiData, window: &winit::window::Window, event: &GuiEvent) {
@@ -25,29 +96,15 @@ pub fn handle_gui_event(data: &mut UiData, window: &winit::window::Window, event
     match event {
         //  Go to start state.
         GuiEvent::Startup => {
-            data.gui_state.app_state.selected_grid = None; // cancel grid selection
-            data.gui_state.app_state.change_mode(SystemMode::Startup); // back to starting state
-            let grid_select_window = GridSelectWindow::new(
-                "Grid select",
-                t!(
-                    "window.grid_select",
-                    &data.gui_state.common_state.params.lang
-                ),
-                &data.gui_state.common_state.assets,
-                data.gui_state.app_state.grid_select_params.clone(),
-            );
-            let start_menu = dialogs::menustart::MenuStart::new_link(grid_select_window);
-            data.gui_state.common_state.set_menu_group(start_menu);
+            data.change_mode(SystemMode::Startup); // back to starting state
         }
         GuiEvent::OpenReplay(path_buf_opt) => {
             // open a replay file
             match path_buf_opt {
                 Some(path_buf) => {
-                    println!("Open replay: {:?}", path_buf); // ***TEMP***
-                                                             //  ***NEED TO PASS path_buf and grid to startup and actually go*** This is the dummy version
-                    data.gui_state.app_state.change_mode(SystemMode::Connected);
-                    let connected_menu = MenuConnected::new_link(&data.ui_app_assets);
-                    data.gui_state.common_state.set_menu_group(connected_menu);
+                    log::info!("Open replay: {:?}", path_buf);
+                    //  ***NEED TO PASS path_buf and grid to startup and actually go*** This is the dummy version
+                    data.change_mode(SystemMode::Connected);
                 }
                 None => {
                     //  User cancelled replay. Back to ground state.
@@ -62,44 +119,109 @@ pub fn handle_gui_event(data: &mut UiData, window: &winit::window::Window, event
             //  Grid has been selected, now try to log in.
             //  Bring up a background with a top menu bar plus a login dialog.
 
-            match data.gui_state.app_state.get_mode() {
-                SystemMode::Startup => {
-                    data.gui_state.app_state.change_mode(SystemMode::Login); // advance to login state
-                    let login_menu = dialogs::menulogin::MenuLogin::new_link();
-                    data.gui_state.common_state.set_menu_group(login_menu);
-                    let is_file_pick = grid.data.login_url.is_none();
-                    data.gui_state.app_state.selected_grid = Some(grid.clone()); // set the selected grid
-                    if is_file_pick {
-                        //  No grid URL, so this is a replay file selection, not a login.
-                        //  File pick is done with the platform's native file picker, asynchronously.
-                        //  File pickers are special - they authorize the program to access the file at the system level.
-                        pick_replay_file_async(&mut data.gui_state.common_state, window);
-                    // use the file picker
-                    } else {
-                        //  This is a login to a grid. Bring up login dialog window.
-                        let id = data.gui_state.common_state.get_unique_id();
-                        data.gui_state
-                            .common_state
-                            .add_window(LoginDialogWindow::new_link(id, grid));
-                    }
-                }
-                _ => {
-                    log::error!(
-                        "Login request to {} while in state {:?}",
-                        grid.data.metaverse,
-                        data.gui_state.app_state.get_mode()
-                    );
+            if !data.change_mode(SystemMode::Login) {
+                log::error!("Login request to {} refused", grid.data.metaverse);
+                return;
+            }
+            let is_file_pick = grid.data.login_url.is_none();
+            data.gui_state.app_state.selected_grid = Some(grid.clone()); // set the selected grid
+            if is_file_pick {
+                //  No grid URL, so this is a replay file selection, not a login.
+                //  File pick is done with the platform's native file picker, asynchronously.
+                //  File pickers are special - they authorize the program to access the file at the system level.
+                pick_replay_file_async(&mut data.gui_state.common_state, window);
+            // use the file picker
+            } else {
+                //  This is a login to a grid. Bring up login dialog window.
+                let id = data.gui_state.common_state.get_unique_id();
+                let dialog =
+                    LoginDialogWindow::new_link(id, grid, data.gui_state.common_state.get_lang());
+                data.gui_state.common_state.add_modal(dialog);
+            }
+        }
+        GuiEvent::LoginStart(login_params) => {
+            //  This is where the login backend gets called. Dummy version.
+            if mock_login_needs_mfa(login_params) {
+                //  Second step. Ask for a one-time code, which comes back as another LoginStart.
+                data.gui_state
+                    .common_state
+                    .send_boxed_gui_event(Box::new(GuiEvent::MfaRequired(login_params.clone())))
+                    .unwrap();
+                return;
+            }
+            if login_params.auth_token.is_some() && login_params.trust_device {
+                //  Server would issue this. The mock makes one up.
+                let token = Zeroizing::new(format!(
+                    "{:x}",
+                    md5::compute(format!("{}{:?}", login_params.user_name, Instant::now()))
+                ));
+                if let Err(e) = login_params
+                    .save_device_token(&token, SystemTime::now() + DEVICE_TOKEN_LIFETIME)
+                {
+                    log::error!("Unable to save device token: {:?}", e);
                 }
             }
+            //  Login accepted. Now it's safe to remember the account and password.
+            if let Err(e) = login_params.login_succeeded() {
+                log::error!("Unable to save login info: {:?}", e);
+            }
+            data.gui_state
+                .common_state
+                .send_boxed_gui_event(Box::new(GuiEvent::Connected))
+                .unwrap();
+        }
+        GuiEvent::MfaRequired(login_params) => {
+            let id = data.gui_state.common_state.get_unique_id();
+            let dialog = MfaDialogWindow::new_link(
+                id,
+                login_params.clone(),
+                data.gui_state.common_state.get_lang(),
+            );
+            data.gui_state.common_state.add_modal(dialog);
+        }
+        GuiEvent::SaveUserGrid(old_key, grid_data) => {
+            let old_opt = old_key
+                .as_ref()
+                .map(|(metaverse, grid)| (metaverse.as_str(), grid.as_str()));
+            let result = data
+                .gui_state
+                .app_state
+                .upsert_user_grid(old_opt, grid_data.clone());
+            update_user_grids(data, result);
         }
-        GuiEvent::LoginStart(_login_params) => {
-            ////data.gui_state.common_state.unimplemented_msg(); // ***TEMP***
+        GuiEvent::DeleteUserGrid(metaverse, grid) => {
+            data.gui_state.app_state.user_grids.remove(metaverse, grid);
+            update_user_grids(data, Ok(()));
         }
         GuiEvent::Connected => {
-            println!("Connected, show 3D world");
-            data.gui_state.app_state.change_mode(SystemMode::Connected);
-            let connected_menu = MenuConnected::new_link(&data.ui_app_assets);
-            data.gui_state.common_state.set_menu_group(connected_menu);
+            log::info!("Connected, show 3D world");
+            data.change_mode(SystemMode::Connected);
         }
     }
 }
+
+/// Dummy login backend. Does this login need a one-time code?
+//  Grids marked "mfa" ask for one unless we already have a code or a trusted device token.
+fn mock_login_needs_mfa(login_params: &LoginParams) -> bool {
+    login_params.grid.data.mfa.unwrap_or(false)
+        && login_params.auth_token.is_none()
+        && login_params.device_token.is_none()
+}
+
+/// After a change to the user grid list, save it and redisplay the grid list.
+fn update_user_grids(data: &mut UiData, result: Result<(), anyhow::Error>) {
+    let result = result.and_then(|_| data.gui_state.app_state.user_grids.save());
+    if let Err(e) = result {
+        log::error!("Unable to update user grids: {:?}", e);
+        let errmsg = format!("{}", e);
+        data.gui_state.common_state.add_error_window(
+            t!("window.grid_edit", &data.gui_state.common_state.params.lang),
+            &[errmsg.as_str()],
+        );
+    }
+    data.gui_state.app_state.rebuild_grid_list();
+    data.gui_state
+        .common_state
+        .send_boxed_gui_event(Box::new(GuiEvent::Startup))
+        .unwrap(); // Redraw start screen with new list.
+}
@@ -0,0 +1,153 @@
+//! # gridnews.rs  --  news feeds published by grids.
+//!
+//! Grids can list an RSS or Atom feed as "news_url" in the grid file.
+//! Feeds are fetched in the background through the caching fetcher,
+//! so the last copy is still shown when offline.
+//
+//  Animats
+//  October 2026
+//
+use super::uiinfo::GridSelectParams;
+use anyhow::{Context, Error};
+use crossbeam_channel::{Receiver, Sender};
+use libui::CachedFetcher;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Most items shown per grid.
+const MAX_NEWS_ITEMS: usize = 5;
+
+/// One news item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewsItem {
+    pub title: String,        // headline
+    pub link: Option<String>, // web page for the item
+    pub date: Option<String>, // when published, as YYYY-MM-DD
+}
+
+/// News from one grid.
+#[derive(Debug)]
+pub struct GridNews {
+    pub grid_name: String,                     // for display
+    pub result: Result<Vec<NewsItem>, String>, // news, or why not
+}
+
+/// Parse an RSS or Atom feed. Newest items first, at most MAX_NEWS_ITEMS.
+pub fn parse_feed(content: &[u8]) -> Result<Vec<NewsItem>, Error> {
+    let feed = feed_rs::parser::parse(content).context("Not an RSS or Atom feed")?;
+    let mut entries = feed.entries;
+    //  Feeds are usually newest first, but not always.
+    entries.sort_by(|a, b| b.published.or(b.updated).cmp(&a.published.or(a.updated)));
+    Ok(entries
+        .into_iter()
+        .take(MAX_NEWS_ITEMS)
+        .map(|entry| NewsItem {
+            title: entry
+                .title
+                .map(|t| t.content.trim().to_string())
+                .unwrap_or_default(),
+            link: entry.links.first().map(|l| l.href.clone()),
+            date: entry
+                .published
+                .or(entry.updated)
+                .map(|d| d.format("%Y-%m-%d").to_string()),
+        })
+        .collect())
+}
+
+/// News from all grids which have feeds, fetched in the background.
+//  Kept for the whole run, so each feed is fetched once, not every time the grid list is shown.
+#[derive(Debug)]
+pub struct NewsMonitor {
+    send: Sender<GridNews>,   // for the worker threads
+    recv: Receiver<GridNews>, // answers from the worker threads
+    news: Vec<GridNews>,      // answers so far
+    pending: usize,           // fetches not yet answered
+    fetched: HashSet<String>, // feed URLs fetched or being fetched
+}
+
+impl NewsMonitor {
+    /// Start fetching news from every grid with a feed.
+    pub fn new(grids: &[GridSelectParams], fetcher: &Arc<CachedFetcher>) -> Self {
+        let (send, recv) = crossbeam_channel::unbounded();
+        let mut monitor = NewsMonitor {
+            send,
+            recv,
+            news: Vec::new(),
+            pending: 0,
+            fetched: HashSet::new(),
+        };
+        monitor.fetch_new(grids, fetcher);
+        monitor
+    }
+
+    /// Start fetching news from grids with feeds not yet fetched, such as grids just added.
+    pub fn fetch_new(&mut self, grids: &[GridSelectParams], fetcher: &Arc<CachedFetcher>) {
+        for grid in grids {
+            if let Some(news_url) = &grid.data.news_url {
+                if !self.fetched.insert(news_url.clone()) {
+                    continue; // already have it
+                }
+                let grid_name = format!("{} -- {}", grid.data.metaverse, grid.data.grid);
+                let news_url = news_url.clone();
+                let fetcher = Arc::clone(fetcher);
+                let send = self.send.clone();
+                self.pending += 1;
+                std::thread::spawn(move || {
+                    let result = fetcher
+                        .fetch(&news_url)
+                        .and_then(|content| parse_feed(&content))
+                        .map_err(|e| {
+                            log::warn!("No news from {}: {:?}", news_url, e);
+                            format!("{:#}", e)
+                        });
+                    let _ = send.send(GridNews { grid_name, result }); // monitor may be gone
+                });
+            }
+        }
+    }
+
+    /// Pick up any news that has come in. Returns true if still fetching.
+    pub fn update(&mut self) -> bool {
+        while let Ok(news) = self.recv.try_recv() {
+            self.pending -= 1;
+            self.news.push(news);
+        }
+        self.pending > 0
+    }
+
+    /// True if any grid has a news feed.
+    pub fn has_feeds(&self) -> bool {
+        self.pending > 0 || !self.news.is_empty()
+    }
+
+    /// News so far, in the order it arrived.
+    pub fn get_news(&self) -> &[GridNews] {
+        &self.news
+    }
+}
+
+#[test]
+fn test_parse_feed() {
+    const RSS: &str = r#"<?xml version="1.0"?>
+        <rss version="2.0"><channel><title>Grid news</title>
+        <item><title>Older</title><link>https://example.com/1</link>
+            <pubDate>Mon, 05 Oct 2026 10:00:00 GMT</pubDate></item>
+        <item><title>Newer</title><link>https://example.com/2</link>
+            <pubDate>Tue, 06 Oct 2026 10:00:00 GMT</pubDate></item>
+        </channel></rss>"#;
+    let items = parse_feed(RSS.as_bytes()).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0].title, "Newer");
+    assert_eq!(items[0].link.as_deref(), Some("https://example.com/2"));
+    assert_eq!(items[0].date.as_deref(), Some("2026-10-06"));
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+        <feed xmlns="http://www.w3.org/2005/Atom"><title>Grid news</title>
+        <id>urn:example</id><updated>2026-10-06T10:00:00Z</updated>
+        <entry><title>Maintenance</title><id>urn:example:1</id>
+            <link href="https://example.com/m"/><updated>2026-10-06T10:00:00Z</updated></entry>
+        </feed>"#;
+    let items = parse_feed(ATOM.as_bytes()).unwrap();
+    assert_eq!(items[0].title, "Maintenance");
+    assert!(parse_feed(b"<html></html>").is_err());
+}
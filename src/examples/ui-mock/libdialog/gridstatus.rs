@@ -0,0 +1,120 @@
+//! # gridstatus.rs  --  is a grid up?
+//!
+//! Grids are checked in the background, one thread per grid,
+//! and the answers are picked up by the grid select screen
+//! on later frames.
+//
+//  Animats
+//  October 2026
+//
+use super::uiinfo::GridSelectParamsData;
+use crossbeam_channel::{Receiver, Sender};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long to wait for a grid before calling it offline.
+const GRID_STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Grid status, for display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GridStatus {
+    Checking, // no answer yet
+    Online,   // login server answered
+    Offline,  // login server did not answer
+}
+
+/// Checks whether a grid is up.
+//  Network in real use, canned answers in tests.
+pub trait GridStatusChecker: Send + Sync {
+    /// Check one grid. Called on a worker thread, so may block.
+    fn check(&self, login_url: &str) -> GridStatus;
+}
+
+/// Grid status by HTTP.
+//  Any HTTP answer at all, even an error status, means the login server is up.
+pub struct HttpGridStatusChecker {}
+
+impl GridStatusChecker for HttpGridStatusChecker {
+    fn check(&self, login_url: &str) -> GridStatus {
+        match ureq::get(login_url.trim())
+            .timeout(GRID_STATUS_TIMEOUT)
+            .call()
+        {
+            Ok(_) | Err(ureq::Error::Status(_, _)) => GridStatus::Online,
+            Err(e) => {
+                log::info!("Grid at {} is not answering: {:?}", login_url, e);
+                GridStatus::Offline
+            }
+        }
+    }
+}
+
+/// (metaverse, grid), which identifies a grid.
+type GridKey = (String, String);
+
+/// Status of all the grids, updated in the background.
+//  Kept for the whole run, so grids are checked once, not every time the grid list is shown.
+pub struct GridStatusMonitor {
+    checker: Arc<dyn GridStatusChecker>,   // how to check
+    status: HashMap<GridKey, GridStatus>,  // latest known status
+    send: Sender<(GridKey, GridStatus)>,   // for the worker threads
+    recv: Receiver<(GridKey, GridStatus)>, // answers from the worker threads
+}
+
+impl std::fmt::Debug for GridStatusMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("GridStatusMonitor")
+            .field("status", &self.status)
+            .finish()
+    }
+}
+
+impl GridStatusMonitor {
+    /// Usual new
+    pub fn new(checker: Arc<dyn GridStatusChecker>) -> Self {
+        let (send, recv) = crossbeam_channel::unbounded();
+        GridStatusMonitor {
+            checker,
+            status: HashMap::new(),
+            send,
+            recv,
+        }
+    }
+
+    /// Start checking a grid. Grids without a login URL are replays, and have no status.
+    pub fn start_check(&mut self, grid: &GridSelectParamsData) {
+        if let Some(login_url) = &grid.login_url {
+            let key = (grid.metaverse.clone(), grid.grid.clone());
+            self.status.insert(key.clone(), GridStatus::Checking);
+            let checker = Arc::clone(&self.checker);
+            let send = self.send.clone();
+            let login_url = login_url.clone();
+            std::thread::spawn(move || {
+                let _ = send.send((key, checker.check(&login_url))); // monitor may be gone
+            });
+        }
+    }
+
+    /// Start checking a grid, unless it has been checked already.
+    pub fn start_check_if_new(&mut self, grid: &GridSelectParamsData) {
+        if self.get_status(grid).is_none() {
+            self.start_check(grid);
+        }
+    }
+
+    /// Pick up any answers that have come in. Returns true if still checking.
+    pub fn update(&mut self) -> bool {
+        while let Ok((key, status)) = self.recv.try_recv() {
+            self.status.insert(key, status);
+        }
+        self.status.values().any(|s| *s == GridStatus::Checking)
+    }
+
+    /// Status of a grid, if it has one.
+    pub fn get_status(&self, grid: &GridSelectParamsData) -> Option<GridStatus> {
+        self.status
+            .get(&(grid.metaverse.clone(), grid.grid.clone()))
+            .copied()
+    }
+}
@@ -0,0 +1,110 @@
+//! # gridvalidate.rs  --  check a grid file.
+//!
+//! grids.json is hand-edited. Rather than stopping at the
+//! first mistake, every entry is checked and all the
+//! problems are reported together. Good entries are kept.
+//
+//  Animats
+//  October 2026
+//
+use super::uiinfo::{is_web_url, GridSelectParamsData};
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Top level of the grid file. Entries are parsed one at a time, so
+/// that one bad entry does not lose the others.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GridFileJson {
+    grids: Vec<serde_json::Value>,
+}
+
+/// Parse a grid file. Returns the good entries and a list of problems.
+/// Fails only if the file is not a grid file at all.
+pub fn parse_grid_file(content: &str) -> Result<(Vec<GridSelectParamsData>, Vec<String>), Error> {
+    let grid_file: GridFileJson = serde_json::from_str(content)
+        .map_err(|e| anyhow!("Grid file is not in grid file format: {}", e))?;
+    let mut grids = Vec::new();
+    let mut problems = Vec::new();
+    for (n, value) in grid_file.grids.into_iter().enumerate() {
+        //  Name the entry as well as possible for the report.
+        let name = value
+            .get("metaverse")
+            .and_then(|v| v.as_str())
+            .unwrap_or("?")
+            .to_string();
+        match serde_json::from_value::<GridSelectParamsData>(value) {
+            Ok(data) => grids.push(data),
+            Err(e) => problems.push(format!("Grid entry {} ({}): {}", n + 1, name, e)),
+        }
+    }
+    Ok((grids, problems))
+}
+
+/// Check a URL. Only web URLs are allowed.
+fn check_url(what: &str, url_str: &str) -> Result<(), String> {
+    match url::Url::parse(url_str.trim()) {
+        Ok(url) if (url.scheme() == "http" || url.scheme() == "https") && url.has_host() => Ok(()),
+        Ok(_) => Err(format!("{} \"{}\" is not a web address", what, url_str)),
+        Err(e) => Err(format!("{} \"{}\" is not valid: {}", what, url_str, e)),
+    }
+}
+
+/// Check the grid entries for problems a parser can't catch.
+/// Returns one message per problem. Picture bars are looked for in asset_dir.
+pub fn validate_grids(grids: &[GridSelectParamsData], asset_dir: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for grid in grids {
+        let mut problem =
+            |msg: String| problems.push(format!("{} -- {}: {}", grid.metaverse, grid.grid, msg));
+        if grid.metaverse.trim().is_empty() || grid.grid.trim().is_empty() {
+            problem("Metaverse and grid names must not be empty".to_string());
+        }
+        if !seen.insert((grid.metaverse.as_str(), grid.grid.as_str())) {
+            problem("Duplicate entry".to_string());
+        }
+        let urls = [
+            ("Home page", Some(&grid.home_url)),
+            ("Login URL", grid.login_url.as_ref()),
+            ("Sign up URL", grid.signup_url.as_ref()),
+            ("News feed", grid.news_url.as_ref()),
+        ];
+        for (what, url_opt) in urls {
+            if let Some(url) = url_opt {
+                if let Err(msg) = check_url(what, url) {
+                    problem(msg);
+                }
+            }
+        }
+        if is_web_url(&grid.picture_bar) {
+            if let Err(msg) = check_url("Picture bar", &grid.picture_bar) {
+                problem(msg);
+            }
+        } else if !grid.picture_bar.is_empty() && !asset_dir.join(&grid.picture_bar).is_file() {
+            problem(format!("Picture bar image {} is missing", grid.picture_bar));
+        }
+    }
+    problems
+}
+
+#[test]
+fn test_grid_validation() {
+    const GRID_FILE: &str = r#"{ "grids": [
+        { "metaverse": "A", "grid": "a", "picture_bar": "", "home_url": "https://a.example.com" },
+        { "metaverse": "A", "grid": "a", "picture_bar": "", "home_url": "https://a.example.com" },
+        { "metaverse": "B", "grid": "b", "picture_bar": "", "home_url": "ftp://b.example.com",
+          "login_url": "not a url" },
+        { "metaverse": "C", "grid": "c", "picture_bar": "images/nosuch.png", "home_url": "http://c.example.com" },
+        { "metaverse": "D", "grid": "d", "picture_bar": "", "home_url": "http://d.example.com", "colour": "red" }
+    ]}"#;
+    let (grids, problems) = parse_grid_file(GRID_FILE).unwrap();
+    assert_eq!(grids.len(), 4);
+    assert_eq!(problems.len(), 1); // unknown field
+    assert!(problems[0].contains("colour"));
+    let problems = validate_grids(&grids, Path::new("/nonexistent"));
+    assert_eq!(problems.len(), 4); // duplicate, two bad URLs, missing image
+    assert!(parse_grid_file(r#"{ "grid": [] }"#).is_err());
+}
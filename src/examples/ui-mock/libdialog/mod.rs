@@ -1,9 +1,15 @@
 // #! libdialog - the application-specific dialogs
 
+mod credentials;
 mod dialogs;
 mod eventswitch;
+mod gridnews;
+mod gridstatus;
+mod gridvalidate;
+mod passwordscheme;
 mod uiinfo;
+mod usergrids;
 
-pub use dialogs::dialogstats::StatisticsEvent;
-pub use eventswitch::handle_gui_event;
-pub use uiinfo::{GridSelectParams, GuiEvent, SystemMode, UiAppAssets, UiData, UiInfo};
+pub use eventswitch::{handle_gui_event, register_windows, system_mode_machine};
+pub use uiinfo::{FrameMetrics, GridSelectParams, GuiEvent, SystemMode, UiData, UiInfo};
+pub use usergrids::UserGridList;
@@ -0,0 +1,103 @@
+//! # passwordscheme.rs  --  how a password is prepared before it is sent to a grid.
+//!
+//! Each grid declares its scheme in grids.json as "password_scheme".
+//! The prepared form is what goes to the server and into credential storage.
+//! Secrets are kept in zeroizing buffers throughout.
+//
+//  Animats
+//  October 2026
+//
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+/// A way of preparing a password for login.
+pub trait PasswordScheme: Sync {
+    /// Convert password as typed to the form sent to the server.
+    fn prepare(&self, password: &str) -> Zeroizing<String>;
+    /// Check that this scheme is safe to use with this login URL.
+    fn check_login_url(&self, _login_url: &str) -> Result<(), Error> {
+        Ok(())
+    }
+    /// Name for debug and logging purposes only
+    fn get_name(&self) -> &'static str;
+}
+
+/// Second Life convention. "$1$" followed by the MD5 of the password in hex.
+pub struct Md5Scheme {}
+
+impl Md5Scheme {
+    const PASSWORD_PREFIX: &'static str = "$1$"; // precedes password MD5 in hex. SL convention.
+}
+
+impl PasswordScheme for Md5Scheme {
+    fn prepare(&self, password: &str) -> Zeroizing<String> {
+        let mut digest = md5::compute(password.trim());
+        let prepared = Zeroizing::new(format!("{}{:032x}", Self::PASSWORD_PREFIX, digest));
+        digest.0.zeroize();
+        prepared
+    }
+
+    fn get_name(&self) -> &'static str {
+        "md5"
+    }
+}
+
+/// Password sent as typed. Only allowed over an encrypted connection.
+pub struct PlainTlsScheme {}
+
+impl PasswordScheme for PlainTlsScheme {
+    fn prepare(&self, password: &str) -> Zeroizing<String> {
+        Zeroizing::new(password.trim().to_string())
+    }
+
+    fn check_login_url(&self, login_url: &str) -> Result<(), Error> {
+        if login_url.trim().to_lowercase().starts_with("https://") {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Plain text passwords require an https login URL, not \"{}\"",
+                login_url
+            ))
+        }
+    }
+
+    fn get_name(&self) -> &'static str {
+        "plain_tls"
+    }
+}
+
+/// Scheme names, as they appear in grids.json.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordSchemeId {
+    #[default]
+    Md5,
+    PlainTls,
+}
+
+impl PasswordSchemeId {
+    /// The scheme itself
+    pub fn scheme(&self) -> &'static dyn PasswordScheme {
+        match self {
+            PasswordSchemeId::Md5 => &Md5Scheme {},
+            PasswordSchemeId::PlainTls => &PlainTlsScheme {},
+        }
+    }
+}
+
+#[test]
+fn test_password_schemes() {
+    let prepared = PasswordSchemeId::Md5.scheme().prepare(" password ");
+    assert_eq!(prepared.as_str(), "$1$5f4dcc3b5aa765d61d8327deb882cf99");
+    let prepared = PasswordSchemeId::PlainTls.scheme().prepare("password");
+    assert_eq!(prepared.as_str(), "password");
+    assert!(PasswordSchemeId::PlainTls
+        .scheme()
+        .check_login_url("http://login.example.com")
+        .is_err());
+    assert!(PasswordSchemeId::PlainTls
+        .scheme()
+        .check_login_url("https://login.example.com")
+        .is_ok());
+}
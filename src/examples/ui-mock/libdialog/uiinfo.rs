@@ -4,12 +4,18 @@
 //  November 2022
 //
 use super::dialogs::dialoglogin::LoginParams;
+use super::gridnews::NewsMonitor;
+use super::gridstatus::{GridStatusMonitor, HttpGridStatusChecker};
+use super::gridvalidate::{parse_grid_file, validate_grids};
+use super::passwordscheme::PasswordSchemeId;
+use super::usergrids::UserGridList;
 use anyhow::{anyhow, Context, Error};
-use libui::load_image;
-use libui::{t, AppState, CommonState, GuiState};
-use rend3::Renderer;
-use rend3_egui::EguiRenderRoutine;
-use serde::Deserialize;
+use core::cell::RefCell;
+use libui::{
+    t, AppState, CachedFetcher, CommonState, CounterHandle, GaugeHandle, GuiState, ImageHandle,
+    ImageLoader, StateMachine,
+};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -26,31 +32,24 @@ pub enum GuiEvent {
     OpenReplay(Option<PathBuf>), // open a replay file
     LoginTo(GridSelectParams),   // ask for login params
     LoginStart(LoginParams),     // start the login process
+    MfaRequired(LoginParams),    // login backend wants a one-time code
     Connected,                   // dummy connected state
-                                 ////Login(ConnectInfo),                         // login dialog result
+    ////Login(ConnectInfo),                         // login dialog result
+    /// Add a user grid, or replace the (metaverse, grid) one
+    SaveUserGrid(Option<(String, String)>, GridSelectParamsData),
+    /// Delete the (metaverse, grid) user grid
+    DeleteUserGrid(String, String),
 }
 
 /// GUI states.
 //  The main states of the system.
-//  This is a state machine
+//  The allowed changes are in system_mode_machine.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum SystemMode {
-    Startup, // idle, waiting for grid selection
-    // -> Login, Replay. Exit
-    Login, // login dialog is up.
-    // -> Connected, Start
+    Startup,   // idle, waiting for grid selection
+    Login,     // login dialog is up.
     Connected, // Fully connected, all menus live
-    // -> Shutdown
-    Shutdown, // shutting down and cleaning up
-              // -> Exit, Start
-}
-
-/// AppAssets -- compile time assets of the UI.
-pub struct UiAppAssets {
-    pub move_arrows_icon: egui::TextureId,
-    pub rot_arrows_icon: egui::TextureId,
-    pub pressed_arrow_icon: egui::TextureId,
-    pub pressed_button_icon: egui::TextureId,
+    Shutdown,  // shutting down and cleaning up
 }
 
 /// Part of AppUi
@@ -61,17 +60,37 @@ pub struct UiData {
     pub _directional_handle: rend3::types::DirectionalLightHandle,
 
     pub egui_routine: rend3_egui::EguiRenderRoutine,
+    pub pixels_per_point: f32, // scale egui_routine was last set to, including UI scale
+    pub image_loader: ImageLoader, // background image loading
+    pub picture_bars_checked: bool, // picture bar problems have been reported
     pub start_time: Instant,
-    pub quit: bool, // global quit flag
+    pub frame_metrics: FrameMetrics, // frame statistics, for the statistics window
+    pub quit: bool,                  // global quit flag
 
     //  The 2D GUI
     pub gui_state: GuiState<UiInfo>, // state of the GUI
-
-    //  Assets of the application.
-    pub ui_app_assets: UiAppAssets,
+    /// Primary system mode
+    pub mode: StateMachine<SystemMode, GuiState<UiInfo>>,
 }
 
-impl UiData {}
+impl UiData {
+    /// Change main system mode. Login, select grid, run, etc.
+    /// Changes the mode machine doesn't allow are logged and not made. Returns true if made.
+    pub fn change_mode(&mut self, new_mode: SystemMode) -> bool {
+        match self.mode.transition(&mut self.gui_state, new_mode) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("{:?}", e);
+                false
+            }
+        }
+    }
+
+    /// Access
+    pub fn get_mode(&self) -> SystemMode {
+        self.mode.current()
+    }
+}
 
 /// Useful statistical info
 #[derive(Debug)]
@@ -125,47 +144,101 @@ impl FrameStatistics {
     }
 }
 
+/// Frame statistics, as metrics, for the statistics window.
+pub struct FrameMetrics {
+    /// Average frame time, seconds
+    pub frame_time_average: GaugeHandle,
+    /// Longest frame time, seconds
+    pub frame_time_longest: GaugeHandle,
+    /// Frames drawn
+    pub frames: CounterHandle,
+}
+
 /// Generic parameter to GuiState, containing app-specific info.
 //  Data passed through GuiState, but not interpreted by it.
 #[derive(Debug)]
 pub struct UiInfo {
-    ///  Primary system mode
-    system_mode: SystemMode, // primary operating mode
     /// Selected grid
     pub selected_grid: Option<GridSelectParams>, // params of selected grid, if any
     ///  All the grids, read-only and shareable
     pub grid_select_params: Rc<Vec<GridSelectParams>>,
+    ///  The grids from the bundled grid file, before user grids are added
+    bundled_grids: Vec<GridSelectParams>,
+    ///  The grids the user added
+    pub user_grids: UserGridList,
+    ///  Picture bar for user grids, which don't have their own
+    user_grid_picture_bar: ImageHandle,
+    ///  Web content, cached
+    pub fetcher: Arc<CachedFetcher>,
+    ///  Which grids are up, checked once per grid
+    pub grid_status: Rc<RefCell<GridStatusMonitor>>,
+    ///  Grid news, fetched once per feed
+    pub grid_news: Rc<RefCell<NewsMonitor>>,
     /// Useful satistical info
     pub frame_statistics: FrameStatistics,
 }
 
 impl UiInfo {
     /// Usual new
-    pub fn new(grid_select_params: Vec<GridSelectParams>) -> Self {
-        Self {
-            system_mode: SystemMode::Startup, // start in Startup mode
-            selected_grid: None,              // with no grid
-            grid_select_params: Rc::new(grid_select_params), // all possible grids
+    pub fn new(
+        bundled_grids: Vec<GridSelectParams>,
+        user_grids: UserGridList,
+        user_grid_picture_bar: ImageHandle,
+        fetcher: Arc<CachedFetcher>,
+    ) -> Self {
+        let grid_status = GridStatusMonitor::new(Arc::new(HttpGridStatusChecker {}));
+        let grid_news = NewsMonitor::new(&[], &fetcher);
+        let mut ui_info = Self {
+            selected_grid: None,                     // with no grid
+            grid_select_params: Rc::new(Vec::new()), // all possible grids, filled in below
+            bundled_grids,
+            user_grids,
+            user_grid_picture_bar,
+            fetcher,
+            grid_status: Rc::new(RefCell::new(grid_status)),
+            grid_news: Rc::new(RefCell::new(grid_news)),
             frame_statistics: FrameStatistics::new(),
-        }
+        };
+        ui_info.rebuild_grid_list();
+        ui_info
     }
-    /// Change main system mode. Login, select grid, run, etc.
-    pub fn change_mode(&mut self, new_mode: SystemMode) {
-        log::info!(
-            "System state change: {:?} -> {:?}",
-            self.system_mode,
-            new_mode
-        );
-        println!(
-            "System state change: {:?} -> {:?}",
-            self.system_mode, new_mode
-        ); // ***TEMP***
-        self.system_mode = new_mode; // do state change
+
+    /// Add a user grid, or replace the one it was edited from.
+    /// Fails if that would duplicate a bundled grid or another user grid.
+    pub fn upsert_user_grid(
+        &mut self,
+        old_opt: Option<(&str, &str)>,
+        data: GridSelectParamsData,
+    ) -> Result<(), Error> {
+        if self
+            .bundled_grids
+            .iter()
+            .any(|g| g.data.metaverse == data.metaverse && g.data.grid == data.grid)
+        {
+            return Err(anyhow!(
+                "There is already a grid {} -- {}",
+                data.metaverse,
+                data.grid
+            ));
+        }
+        self.user_grids.upsert(old_opt, data)
     }
 
-    /// Access
-    pub fn get_mode(&self) -> SystemMode {
-        self.system_mode
+    /// Rebuild the grid list from the bundled grids plus the user's grids.
+    /// Grids not seen before are checked, and their news fetched, in the background.
+    //  Call after changing user_grids.
+    pub fn rebuild_grid_list(&mut self) {
+        let mut grids = self.bundled_grids.clone();
+        grids.extend(self.user_grids.grids.iter().map(|data| GridSelectParams {
+            data: data.clone(),
+            picture_bar: self.user_grid_picture_bar.clone(),
+            user_defined: true,
+        }));
+        for grid in &grids {
+            self.grid_status.borrow_mut().start_check_if_new(&grid.data);
+        }
+        self.grid_news.borrow_mut().fetch_new(&grids, &self.fetcher);
+        self.grid_select_params = Rc::new(grids);
     }
 }
 
@@ -192,8 +265,6 @@ pub fn pick_replay_file_async(state: &mut CommonState, window: &winit::window::W
         let file = task.await; // wait for dialog completion
         let replay_path_opt = if let Some(file) = file {
             // If you are on native platform you can just get the path
-            println!("{:?}", file.path());
-
             // If you care about wasm support you just read() the file
             ////file.read().await;
             log::warn!("File picked: {:?}", file.path());
@@ -210,37 +281,45 @@ pub fn pick_replay_file_async(state: &mut CommonState, window: &winit::window::W
 }
 
 /// GridSelectParams file contents.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
 pub struct GridSelectParamsData {
     pub metaverse: String,         // Second Life, OsGrid, etc.
     pub grid: String,              // agni, etc.
-    pub picture_bar: String,       // local file name in images directory
+    pub picture_bar: String,       // file name in assets directory, or URL. Empty for user grids.
     pub home_url: String,          // home page for site
-    pub join_url: Option<String>,  // How to join
     pub login_url: Option<String>, // if none, this is a replay
+    pub news_url: Option<String>,  // RSS or Atom news feed
+    pub mfa: Option<bool>,         // grid asks for a one-time code at login
     pub comment: Option<String>,   // to allow a comment in the source JSON file
-}
-
-/// This describes the format of the grids.json file for serde deserialization.
-#[derive(Debug, Clone, Deserialize)]
-struct GridSelectParamsDataJson {
-    pub grids: Vec<GridSelectParamsData>,
+    /// How to join. Older user grid files call this join_url.
+    #[serde(alias = "join_url")]
+    pub signup_url: Option<String>,
+    /// How passwords are prepared for this grid
+    #[serde(default)]
+    pub password_scheme: PasswordSchemeId,
 }
 
 #[derive(Debug, Clone)]
 pub struct GridSelectParams {
-    pub data: GridSelectParamsData,   // as read from JSON
-    pub picture_bar: egui::TextureId, // texture has been loaded and is ready to go
+    pub data: GridSelectParamsData, // as read from JSON
+    pub picture_bar: ImageHandle,   // texture, which may still be loading
+    pub user_defined: bool,         // from the user's grid list, so the user can edit it
 }
 
 impl GridSelectParams {
     /// Read the JSON grid select params file tnto a GridSelectParams structure.
+    //  Returns the usable grids, and a list of problems found in the file.
+    //  Picture bars load in the background, showing the placeholder until loaded.
+    //  A grid whose picture bar won't load keeps the placeholder.
+    //  Only an unreadable file is an error.
     pub fn read_grid_select_params(
         filename: &PathBuf,
         asset_dir: &Path,
-        egui_routine: &mut EguiRenderRoutine,
-        renderer: &Arc<Renderer>,
-    ) -> Result<Vec<GridSelectParams>, Error> {
+        image_loader: &mut ImageLoader,
+        fetcher: &Arc<CachedFetcher>,
+        placeholder: egui::TextureId,
+    ) -> Result<(Vec<GridSelectParams>, Vec<String>), Error> {
         //  Read grid_select file
         let mut grid_file = asset_dir.to_path_buf();
         grid_file.push(filename);
@@ -255,34 +334,90 @@ impl GridSelectParams {
         reader
             .read_to_string(&mut content)
             .context("Failed to read the grid select params config.")?;
-        let grids_data: GridSelectParamsDataJson = serde_json::from_str(&content)
-            .context("Failed to parse grid select params config file.")?;
+        let (grids_data, mut problems) = parse_grid_file(&content).with_context(|| {
+            format!(
+                "Failed to parse grid select params config file {:?}",
+                filename
+            )
+        })?;
+        problems.extend(validate_grids(&grids_data, asset_dir));
         let mut params = Vec::new();
-        for data in grids_data.grids {
-            let mut image_file_name = asset_dir.to_path_buf(); // build file name of image
-            image_file_name.push(&data.picture_bar);
-            println!(
+        for data in grids_data {
+            log::debug!(
                 "Metaverse: {} Grid: {} Picture bar image file: {:?}",
-                data.metaverse, data.grid, image_file_name
-            ); // ***TEMP***
-            let image = image::io::Reader::open(&image_file_name)
-                .with_context(|| {
-                    format!(
-                        "Unable to open image file {:?} for grid menu",
-                        image_file_name
-                    )
-                })?
-                .decode()
-                .with_context(|| {
-                    format!(
-                        "Unable to decode image file {:?} for grid menu",
-                        image_file_name
-                    )
-                })?;
-            ////let rgba = image.to_rgba8();
-            let picture_bar = load_image(image, egui_routine, renderer);
-            params.push(GridSelectParams { picture_bar, data });
+                data.metaverse,
+                data.grid,
+                data.picture_bar
+            );
+            let picture_bar = Self::load_picture_bar(
+                &data.picture_bar,
+                asset_dir,
+                image_loader,
+                fetcher,
+                placeholder,
+            );
+            params.push(GridSelectParams {
+                picture_bar,
+                data,
+                user_defined: false,
+            });
+        }
+        for problem in &problems {
+            log::warn!("Grid file {:?}: {}", filename, problem);
+        }
+        Ok((params, problems))
+    }
+
+    /// Start loading a picture bar image, from the web or the asset directory.
+    pub fn load_picture_bar(
+        picture_bar: &str,
+        asset_dir: &Path,
+        image_loader: &mut ImageLoader,
+        fetcher: &Arc<CachedFetcher>,
+        placeholder: egui::TextureId,
+    ) -> ImageHandle {
+        if is_web_url(picture_bar) {
+            image_loader.load_url(picture_bar, fetcher, placeholder)
+        } else {
+            image_loader.load_file(&asset_dir.join(picture_bar), placeholder)
         }
-        Ok(params)
     }
+
+    /// Picture bars of bundled grids which wouldn't load, for the grid problem report.
+    /// Call once they have all finished loading.
+    //  Missing files were already reported by validation.
+    pub fn picture_bar_problems(grids: &[GridSelectParams], asset_dir: &Path) -> Vec<String> {
+        grids
+            .iter()
+            .filter(|grid| !grid.user_defined)
+            .filter(|grid| {
+                is_web_url(&grid.data.picture_bar)
+                    || asset_dir.join(&grid.data.picture_bar).is_file()
+            })
+            .filter_map(|grid| {
+                grid.picture_bar
+                    .error()
+                    .map(|e| format!("{} -- {}: {}", grid.data.metaverse, grid.data.grid, e))
+            })
+            .collect()
+    }
+
+    /// Stand-in for a picture bar that is loading, or won't load. Gray diagonal stripes.
+    pub fn placeholder_picture_bar() -> image::DynamicImage {
+        const STRIPE_WIDTH: u32 = 32;
+        let image = image::RgbaImage::from_fn(1024, 128, |x, y| {
+            if ((x + y) / STRIPE_WIDTH) % 2 == 0 {
+                image::Rgba([96, 96, 96, 255])
+            } else {
+                image::Rgba([128, 128, 128, 255])
+            }
+        });
+        image::DynamicImage::ImageRgba8(image)
+    }
+}
+
+/// True if this is a web address rather than a file name.
+pub fn is_web_url(s: &str) -> bool {
+    let s = s.trim().to_lowercase();
+    s.starts_with("http://") || s.starts_with("https://")
 }
@@ -0,0 +1,374 @@
+//! # usergrids.rs  --  grids added by the user.
+//!
+//! The bundled grids.json is read-only. Grids the user adds
+//! are kept in the config dir in the same format, and merged
+//! with the bundled list at startup.
+//
+//  Animats
+//  October 2026
+//
+use super::uiinfo::GridSelectParamsData;
+use anyhow::{anyhow, Context, Error};
+use libui::{get_config_dir, move_aside, write_atomic};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// User grid file, in the config dir.
+pub const USER_GRID_FILE_NAME: &str = "user_grids.json";
+/// Grid select screen preferences file, in the config dir.
+pub const GRID_PREFERENCES_FILE_NAME: &str = "grid_preferences.json";
+/// Metaverse name for grids added by login URL. OpenSim grids are the ones people add.
+pub const DEFAULT_USER_METAVERSE: &str = "Open Simulator";
+/// How long to wait for a grid to answer.
+const GRID_INFO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a grid says about itself.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridInfo {
+    pub grid_name: String,          // "gridname"
+    pub login_url: String,          // "login", else the URL asked
+    pub home_url: Option<String>,   // "about", else "welcome"
+    pub signup_url: Option<String>, // "register"
+}
+
+/// Where grid info comes from.
+//  Network in real use, canned data in tests.
+pub trait GridInfoSource: Send + Sync {
+    /// Ask a grid, by login URL, for its info.
+    fn get_grid_info(&self, login_url: &str) -> Result<GridInfo, Error>;
+}
+
+/// Grid info by HTTP. OpenSim convention: LOGINURL/get_grid_info returns XML.
+pub struct HttpGridInfoSource {}
+
+impl GridInfoSource for HttpGridInfoSource {
+    fn get_grid_info(&self, login_url: &str) -> Result<GridInfo, Error> {
+        let url = format!("{}/get_grid_info", login_url.trim().trim_end_matches('/'));
+        log::info!("Fetching grid info from {}", url);
+        let xml = ureq::get(&url)
+            .timeout(GRID_INFO_TIMEOUT)
+            .call()
+            .with_context(|| format!("No answer from {}", url))?
+            .into_string()
+            .with_context(|| format!("Unreadable answer from {}", url))?;
+        parse_grid_info(&xml, login_url)
+    }
+}
+
+/// Parse the get_grid_info XML document.
+pub fn parse_grid_info(xml: &str, login_url: &str) -> Result<GridInfo, Error> {
+    if xml_tag(xml, "gridinfo").is_none() {
+        return Err(anyhow!("Not a grid info document"));
+    }
+    let grid_name = xml_tag(xml, "gridname")
+        .or_else(|| xml_tag(xml, "gridnick"))
+        .ok_or_else(|| anyhow!("Grid info has no grid name"))?;
+    Ok(GridInfo {
+        grid_name,
+        login_url: xml_tag(xml, "login").unwrap_or_else(|| login_url.trim().to_string()),
+        home_url: xml_tag(xml, "about").or_else(|| xml_tag(xml, "welcome")),
+        signup_url: xml_tag(xml, "register"),
+    })
+}
+
+/// Contents of a simple XML element, trimmed, with the standard entities decoded.
+//  Grid info is flat and small, so a real XML parser is not needed.
+fn xml_tag(xml: &str, tag: &str) -> Option<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let len = xml[start..].find(&close)?;
+    let text = xml[start..start + len]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// The user's own grids.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserGridList {
+    pub grids: Vec<GridSelectParamsData>,
+    #[serde(skip)]
+    path: Option<PathBuf>, // where it came from
+}
+
+impl UserGridList {
+    /// Read the user grid file from the config dir. Missing file is an empty list.
+    /// An unusable file is moved aside, and the list starts empty.
+    /// Returns the list, and what went wrong, if anything.
+    pub fn load() -> (Self, Option<Error>) {
+        match get_config_dir() {
+            Ok(dir) => Self::load_or_move_aside(&dir.join(USER_GRID_FILE_NAME)),
+            Err(e) => (Default::default(), Some(e)),
+        }
+    }
+
+    /// Read a user grid file. If it can't be used, move it aside, so that
+    /// saving the list doesn't lose it, and start with an empty list.
+    pub fn load_or_move_aside(path: &Path) -> (Self, Option<Error>) {
+        match Self::load_from(path) {
+            Ok(list) => (list, None),
+            Err(e) => match move_aside(path) {
+                Ok(bad_path) => {
+                    let list = UserGridList {
+                        grids: Vec::new(),
+                        path: Some(path.to_path_buf()),
+                    };
+                    (list, Some(e.context(format!("Moved to {:?}", bad_path))))
+                }
+                //  Still there, so don't overwrite it.
+                Err(move_error) => (Default::default(), Some(e.context(move_error))),
+            },
+        }
+    }
+
+    /// Read a user grid file. Missing file is an empty list.
+    pub fn load_from(path: &Path) -> Result<Self, Error> {
+        let mut list: UserGridList = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Unable to read user grid file {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Unable to parse user grid file {:?}", path))?
+        } else {
+            Default::default()
+        };
+        list.path = Some(path.to_path_buf());
+        Ok(list)
+    }
+
+    /// Write the list back where it came from.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("User grid list has no file"))?;
+        write_atomic(path, serde_json::to_string_pretty(self)?.as_bytes())
+            .with_context(|| format!("Unable to write user grid file {:?}", path))
+    }
+
+    /// Position of a grid in the list
+    fn find(&self, metaverse: &str, grid: &str) -> Option<usize> {
+        self.grids
+            .iter()
+            .position(|g| g.metaverse == metaverse && g.grid == grid)
+    }
+
+    /// Add a grid, or replace the one it was edited from.
+    /// Fails if that would duplicate another entry.
+    pub fn upsert(
+        &mut self,
+        old_opt: Option<(&str, &str)>,
+        data: GridSelectParamsData,
+    ) -> Result<(), Error> {
+        let old_pos = old_opt.and_then(|(metaverse, grid)| self.find(metaverse, grid));
+        if let Some(pos) = self.find(&data.metaverse, &data.grid) {
+            if Some(pos) != old_pos {
+                return Err(anyhow!(
+                    "There is already a grid {} -- {}",
+                    data.metaverse,
+                    data.grid
+                ));
+            }
+        }
+        match old_pos {
+            Some(pos) => self.grids[pos] = data,
+            None => self.grids.push(data),
+        }
+        Ok(())
+    }
+
+    /// Remove a grid. Not an error if not present.
+    pub fn remove(&mut self, metaverse: &str, grid: &str) {
+        self.grids
+            .retain(|g| !(g.metaverse == metaverse && g.grid == grid));
+    }
+}
+
+/// Order of the grid select screen, after the favourites.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GridSortOrder {
+    #[default]
+    Listed, // bundled grids, then user grids, in file order
+    Name, // by metaverse and grid name
+}
+
+/// The user's grid select screen settings.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GridPreferences {
+    /// Favourite grids as (metaverse, grid), in the order shown, at the top
+    #[serde(default)]
+    pub favourites: Vec<(String, String)>,
+    /// Order of the other grids
+    #[serde(default)]
+    pub sort_order: GridSortOrder,
+    #[serde(skip)]
+    path: Option<PathBuf>, // where it came from
+}
+
+impl GridPreferences {
+    /// Read the preferences from the config dir. Missing file means defaults.
+    pub fn load() -> Result<Self, Error> {
+        let path = get_config_dir()?.join(GRID_PREFERENCES_FILE_NAME);
+        let mut prefs: GridPreferences = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Unable to read grid preferences file {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Unable to parse grid preferences file {:?}", path))?
+        } else {
+            Default::default()
+        };
+        prefs.path = Some(path);
+        Ok(prefs)
+    }
+
+    /// Write the preferences back where they came from.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Grid preferences have no file"))?;
+        write_atomic(path, serde_json::to_string_pretty(self)?.as_bytes())
+            .with_context(|| format!("Unable to write grid preferences file {:?}", path))
+    }
+
+    /// Position in the favourites list, if a favourite.
+    pub fn favourite_position(&self, metaverse: &str, grid: &str) -> Option<usize> {
+        self.favourites
+            .iter()
+            .position(|(m, g)| m == metaverse && g == grid)
+    }
+
+    /// Add to end of favourites, or remove.
+    pub fn toggle_favourite(&mut self, metaverse: &str, grid: &str) {
+        match self.favourite_position(metaverse, grid) {
+            Some(pos) => {
+                self.favourites.remove(pos);
+            }
+            None => self
+                .favourites
+                .push((metaverse.to_string(), grid.to_string())),
+        }
+    }
+
+    /// Move a favourite one place up the list.
+    pub fn raise_favourite(&mut self, metaverse: &str, grid: &str) {
+        if let Some(pos) = self.favourite_position(metaverse, grid) {
+            if pos > 0 {
+                self.favourites.swap(pos - 1, pos);
+            }
+        }
+    }
+
+    /// Display order of grids: favourites in favourite order, then the rest in sort order.
+    /// Returns indices into grids.
+    pub fn display_order(&self, grids: &[&GridSelectParamsData]) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..grids.len()).collect();
+        let name = |n: usize| {
+            (
+                grids[n].metaverse.to_lowercase(),
+                grids[n].grid.to_lowercase(),
+            )
+        };
+        //  Stable sort, so Listed keeps file order.
+        order.sort_by(|&a, &b| {
+            let fav_a = self.favourite_position(&grids[a].metaverse, &grids[a].grid);
+            let fav_b = self.favourite_position(&grids[b].metaverse, &grids[b].grid);
+            match (fav_a, fav_b) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => match self.sort_order {
+                    GridSortOrder::Listed => std::cmp::Ordering::Equal,
+                    GridSortOrder::Name => name(a).cmp(&name(b)),
+                },
+            }
+        });
+        order
+    }
+}
+
+/// Does a grid match the search text? Empty search text matches everything.
+pub fn grid_matches_search(grid: &GridSelectParamsData, search: &str) -> bool {
+    let search = search.trim().to_lowercase();
+    search.is_empty()
+        || grid.metaverse.to_lowercase().contains(&search)
+        || grid.grid.to_lowercase().contains(&search)
+        || grid.home_url.to_lowercase().contains(&search)
+}
+
+#[test]
+fn test_parse_grid_info() {
+    const GRID_INFO: &str = "<gridinfo>
+        <login>http://login.osgrid.org/</login>
+        <gridname>OSGrid</gridname>
+        <gridnick>osgrid</gridnick>
+        <welcome>http://www.osgrid.org/splash/</welcome>
+        <about>http://www.osgrid.org/?q=about&amp;x=1</about>
+        <register>http://www.osgrid.org/accounts</register>
+    </gridinfo>";
+    let info = parse_grid_info(GRID_INFO, "http://login.osgrid.org").unwrap();
+    assert_eq!(info.grid_name, "OSGrid");
+    assert_eq!(info.login_url, "http://login.osgrid.org/");
+    assert_eq!(
+        info.home_url.as_deref(),
+        Some("http://www.osgrid.org/?q=about&x=1")
+    );
+    assert_eq!(
+        info.signup_url.as_deref(),
+        Some("http://www.osgrid.org/accounts")
+    );
+    assert!(parse_grid_info("<html></html>", "http://example.com").is_err());
+}
+
+#[test]
+fn test_grid_display_order() {
+    fn grid(metaverse: &str, grid: &str) -> GridSelectParamsData {
+        serde_json::from_str(&format!(
+            r#"{{"metaverse": "{}", "grid": "{}", "picture_bar": "", "home_url": ""}}"#,
+            metaverse, grid
+        ))
+        .unwrap()
+    }
+    let grids_data = [grid("Zeta", "a"), grid("Alpha", "b"), grid("Mu", "c")];
+    let grids: Vec<&GridSelectParamsData> = grids_data.iter().collect();
+    let mut prefs = GridPreferences::default();
+    assert_eq!(prefs.display_order(&grids), vec![0, 1, 2]);
+    prefs.sort_order = GridSortOrder::Name;
+    assert_eq!(prefs.display_order(&grids), vec![1, 2, 0]);
+    prefs.toggle_favourite("Zeta", "a");
+    prefs.toggle_favourite("Mu", "c");
+    assert_eq!(prefs.display_order(&grids), vec![0, 2, 1]);
+    prefs.raise_favourite("Mu", "c");
+    assert_eq!(prefs.display_order(&grids), vec![2, 0, 1]);
+    assert!(grid_matches_search(grids[1], " alp"));
+    assert!(!grid_matches_search(grids[1], "zeta"));
+}
+
+#[test]
+fn test_bad_user_grid_file() {
+    let path = std::env::temp_dir().join(format!("uimock-test-grids-{}.json", std::process::id()));
+    std::fs::write(&path, "{ not json").unwrap();
+    let (list, error) = UserGridList::load_or_move_aside(&path);
+    assert!(error.is_some());
+    assert!(list.grids.is_empty());
+    //  The bad file is kept, and the list can still be saved.
+    let mut bad_path = path.clone().into_os_string();
+    bad_path.push(".bad");
+    assert_eq!(std::fs::read_to_string(&bad_path).unwrap(), "{ not json");
+    list.save().unwrap();
+    let (list, error) = UserGridList::load_or_move_aside(&path);
+    assert!(error.is_none());
+    assert!(list.grids.is_empty());
+    let _ = std::fs::remove_file(&path);
+    let _ = std::fs::remove_file(&bad_path);
+}
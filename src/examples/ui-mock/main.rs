@@ -15,17 +15,18 @@ mod examplesupport;
 mod libdialog;
 
 use anyhow::Error;
-use libdialog::handle_gui_event;
+use libdialog::{handle_gui_event, register_windows, system_mode_machine};
 use libdialog::{
-    GridSelectParams, GuiEvent, StatisticsEvent, SystemMode, UiAppAssets, UiData, UiInfo,
+    FrameMetrics, GridSelectParams, GuiEvent, SystemMode, UiData, UiInfo, UserGridList,
 };
-use libui::{get_executable_name, get_log_file_name, panic_dialog, t};
+use libui::{get_executable_name, get_log_dir, install_crash_handler, panic_dialog, t};
 use libui::{
-    Dictionary, ExecutableVersion, GuiAssets, GuiCommonEvent, GuiParams, GuiState, MessageLogger,
-    SendAnyBoxed,
+    CachedFetcher, CommonState, CrashReportDialog, Dictionary, ExecutableVersion, GuiAssets,
+    GuiCommonEvent, GuiParams, GuiState, IconRegistry, ImageLoader, LogController, LogFiles,
+    SendAnyBoxed, UreqFetcher,
 };
-use log::LevelFilter;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::fmt::Debug;
 
@@ -37,6 +38,14 @@ use winit::event::StartCause;
 const MENU_DISPLAY_SECS: u64 = 3; 
 /// Statistics this often
 const STATISTICS_INTERVAL: Duration = Duration::new(1, 0); 
+/// Metric: average frame time, seconds
+const METRIC_FRAME_TIME_AVERAGE: &str = "frame_time.average";
+/// Metric: longest frame time, seconds
+const METRIC_FRAME_TIME_LONGEST: &str = "frame_time.longest";
+/// Metric: frames drawn, graphed as frames per second
+const METRIC_FRAMES: &str = "frames";
+/// Frame time for 60 frames per second, shown on frame time graphs
+const FRAME_BUDGET: f32 = 1.0 / 60.0;
 /// Turn on for verbose event printing.
 const PRINT_ALL_EVENTS: bool = false;
 /// Formatting for timestamps in debug print
@@ -52,6 +61,8 @@ pub struct AppUi {
     //  We have to do this at the outer level so the logger can access it early.
     event_send_channel: crossbeam_channel::Sender<SendAnyBoxed>,
     event_recv_channel: Option<crossbeam_channel::Receiver<SendAnyBoxed>>,
+    //  Log levels. Set up with the logger, before the GUI.
+    log_controller: LogController,
 }
 
 impl AppUi {
@@ -66,6 +77,7 @@ impl AppUi {
             data: None,
             event_recv_channel: Some(event_recv_channel), // because it will be taken
             event_send_channel: event_send_channel.clone(),
+            log_controller: LogController::default(), // until logging starts
         }
     }
 
@@ -80,31 +92,39 @@ impl AppUi {
         if let Some(event) = raw_event.downcast_ref::<GuiEvent>() {
             log::warn!("GuiEvent: {:?}", event);
             handle_gui_event(data, window, event); // main GUI event handler switch
-        } else if let Some(event) = raw_event.downcast_ref::<GuiCommonEvent>() {
+        } else {
             //  Handle standard utility-type events.
-            match event {
-                GuiCommonEvent::ErrorMessage((title, messages)) => {
-                    // display message
-                    let msgs: Vec<&str> = messages.iter().map(|m| m.as_str()).collect();
-                    data.gui_state.common_state.add_error_window(title, &msgs);
-                }
-                GuiCommonEvent::LogMessage(s) => data.gui_state.common_state.add_msg(s.to_string()),
-                GuiCommonEvent::ErrorShutdown(s) => {
-                    log::error!("Error shutdown: {}",s); // in real programs do a popup.
-                    data.gui_state.app_state.change_mode(SystemMode::Shutdown); // shutdown starts
-                    data.quit = true; // force quit
-                }
+            //  Taken by value, because a confirmation carries the event to send on yes.
+            match raw_event.downcast::<GuiCommonEvent>() {
+                Ok(event) => match *event {
+                    GuiCommonEvent::ErrorMessage((title, messages)) => {
+                        // display message
+                        let msgs: Vec<&str> = messages.iter().map(|m| m.as_str()).collect();
+                        data.gui_state.common_state.add_error_window(&title, &msgs);
+                    }
+                    GuiCommonEvent::LogMessage(record) => {
+                        data.gui_state.common_state.add_log_record(record)
+                    }
+                    GuiCommonEvent::Confirm(request) => data.gui_state.common_state.confirm(request),
+                    GuiCommonEvent::ErrorShutdown(s) => {
+                        log::error!("Error shutdown: {}",s); // in real programs do a popup.
+                        data.change_mode(SystemMode::Shutdown); // shutdown starts
+                        data.quit = true; // force quit
+                    }
 
-                GuiCommonEvent::Shutdown => {
-                    data.gui_state.app_state.change_mode(SystemMode::Shutdown); // shutdown starts
-                    data.quit = true; // force quit
-                } // shut down and exit
+                    GuiCommonEvent::Shutdown => {
+                        data.change_mode(SystemMode::Shutdown); // shutdown starts
+                        Self::save_layout(data);
+                        data.quit = true; // force quit
+                    } // shut down and exit
+                },
+                Err(raw_event) => {
+                    log::error!(
+                        "Invalid non GuiEvent/GuiCommonEvent in handle_user_event: {:?}",
+                        raw_event
+                    );
+                }
             }
-        } else {
-            log::error!(
-                "Invalid non GuiEvent/GuiCommonEvent in handle_user_event: {:?}",
-                raw_event
-            );
         }
     }
     /// Setup of the graphics environment. Returns error.
@@ -211,39 +231,6 @@ impl AppUi {
         } else {
             None
         };
-        //  Icon loading (obsolete)
-        let web_icon_bytes = include_bytes!("../../assets/images/iconweb.png");
-        let assets = GuiAssets {
-            web_icon: libui::load_canned_icon(web_icon_bytes, &mut egui_routine, context.renderer),
-        };
-        //  Icon loading (current). Example content only. Real programs do this at run time, so we can have themes.
-        let move_arrows_icon_bytes = include_bytes!("../../assets/images/move-arrows-128.png");
-        let rot_arrows_icon_bytes = include_bytes!("../../assets/images/rot-arrows-128.png");
-        let pressed_arrow_icon_bytes =
-            include_bytes!("../../assets/images/arrow-pressed-right-64.png");
-        let pressed_button_icon_bytes = include_bytes!("../../assets/images/center-pressed-64.png");
-        let ui_app_assets = UiAppAssets {
-            move_arrows_icon: libui::load_canned_icon(
-                move_arrows_icon_bytes,
-                &mut egui_routine,
-                context.renderer,
-            ),
-            rot_arrows_icon: libui::load_canned_icon(
-                rot_arrows_icon_bytes,
-                &mut egui_routine,
-                context.renderer,
-            ),
-            pressed_arrow_icon: libui::load_canned_icon(
-                pressed_arrow_icon_bytes,
-                &mut egui_routine,
-                context.renderer,
-            ),
-            pressed_button_icon: libui::load_canned_icon(
-                pressed_button_icon_bytes,
-                &mut egui_routine,
-                context.renderer,
-            ),
-        };
         let start_time = Instant::now();
         let _version = env!("CARGO_PKG_VERSION").to_string(); // Version of main, not libraries
         let asset_dir =
@@ -258,18 +245,64 @@ impl AppUi {
         //// Detection turned off due to https://github.com/frewsxcv/rust-dark-light/issues/17
         ////let dark_mode = dark_light::detect() == dark_light::Mode::Dark; // True if dark mode
         let dark_mode = false; // ***TEMP*** force dark mode as default
-        let log_level = LevelFilter::Warn; // warn is default logging level
-        println!("Dark mode: {:?} -> {}", dark_light::detect(), dark_mode); // ***TEMP***
+        log::debug!("Dark mode: {:?} -> {}", dark_light::detect(), dark_mode);
         let adapter_info: rend3::ExtendedAdapterInfo = context.renderer.adapter_info.clone(); // adapter info for About box
                                                                                               ////println!("Adapter info: {:?}", adapter_info);   // ***TEMP***
+        //  Icons, by name. A theme's icons override the standard ones.
+        const ICON_DIR: &str = "icons";
+        const DARK_THEME_ICON_DIR: &str = "icons/themes/dark";
+        let mut icons =
+            IconRegistry::new(context.scale_factor, &mut egui_routine, context.renderer);
+        let mut icon_problems = icons.load_dir(
+            &asset_dir.join(ICON_DIR),
+            &mut egui_routine,
+            context.renderer,
+        );
+        let dark_theme_icon_dir = asset_dir.join(DARK_THEME_ICON_DIR);
+        if dark_mode && dark_theme_icon_dir.is_dir() {
+            icon_problems.extend(icons.load_dir(
+                &dark_theme_icon_dir,
+                &mut egui_routine,
+                context.renderer,
+            ));
+        }
+        for problem in icon_problems {
+            log::error!("{}", problem); // missing icons show as placeholders
+        }
+        let assets = GuiAssets { icons };
         const GRID_FILE: &str = "grids.json";
-        // Read in the grid select params, which requires reading some images.
-        let grid_select_params = GridSelectParams::read_grid_select_params(
-            &std::path::PathBuf::from_str(GRID_FILE)?,
-            &asset_dir,
+        // Read in the grid select params. Their images load in the background.
+        //  Web content is cached, so it is still there when offline.
+        let fetcher = Arc::new(CachedFetcher::new(Arc::new(UreqFetcher::default()))?);
+        let mut image_loader = ImageLoader::new();
+        let picture_bar_placeholder = libui::load_image(
+            GridSelectParams::placeholder_picture_bar(),
             &mut egui_routine,
             context.renderer,
-        )?;
+        );
+        let (grid_select_params, mut grid_file_problems) =
+            GridSelectParams::read_grid_select_params(
+                &std::path::PathBuf::from_str(GRID_FILE)?,
+                &asset_dir,
+                &mut image_loader,
+                &fetcher,
+                picture_bar_placeholder,
+            )?;
+        //  The user's own grids. All of them share one picture bar.
+        const USER_GRID_PICTURE_BAR: &str = "images/placeholderb.png";
+        let user_grid_picture_bar = GridSelectParams::load_picture_bar(
+            USER_GRID_PICTURE_BAR,
+            &asset_dir,
+            &mut image_loader,
+            &fetcher,
+            picture_bar_placeholder,
+        );
+        //  A bad user grid file is reported with the grid file problems.
+        let (user_grids, user_grids_error) = UserGridList::load();
+        if let Some(e) = user_grids_error {
+            log::error!("Unable to load user grid list, ignoring it: {:?}", e);
+            grid_file_problems.push(format!("{:#}", e));
+        }
 
         //  Info about the executable program.
         const BUILD_ID: &str = git_version::git_version!(); // build info, computed at compile time
@@ -287,7 +320,7 @@ impl AppUi {
             executable_version, // because we need version of main program, not libs
             asset_dir,
             dark_mode,
-            log_level,
+            log_controller: self.log_controller.clone(),
             menu_display_secs: MENU_DISPLAY_SECS,
             gpu_info: adapter_info, // GPU info
                                     ////grid_select_params,
@@ -295,9 +328,14 @@ impl AppUi {
         let event_send_channel = self.event_send_channel.clone();
         let event_recv_channel = self.event_recv_channel.take().unwrap();
         //  Set initial state of app-level UI info
-        let app_state = UiInfo::new(grid_select_params);
+        let app_state = UiInfo::new(
+            grid_select_params,
+            user_grids,
+            user_grid_picture_bar,
+            fetcher,
+        );
         //  Set up main state of the GUI
-        let gui_state = GuiState::new(
+        let mut gui_state = GuiState::new(
             params,
             assets,
             platform.expect("No platform"),
@@ -306,15 +344,33 @@ impl AppUi {
             event_recv_channel,
             app_state,
         );
+        register_windows(&mut gui_state.common_state);
+        let frame_metrics = Self::register_metrics(&gui_state.common_state);
+        //  If the last session crashed, offer its crash report.
+        if let Ok(log_dir) = get_log_dir() {
+            CrashReportDialog::offer(&mut gui_state.common_state, &log_dir);
+        }
+        //  Problems in the grid file are reported, all at once, but are not fatal.
+        if !grid_file_problems.is_empty() {
+            let messages: Vec<&str> = grid_file_problems.iter().map(|s| s.as_str()).collect();
+            gui_state.common_state.add_error_window(
+                t!("window.grid_file_problems", &gui_state.common_state.params.lang),
+                &messages,
+            );
+        }
         self.data = Some(UiData {
             _object_handle,
             _material_handle,
             _directional_handle,
             egui_routine,
+            pixels_per_point: context.scale_factor,
+            image_loader,
+            picture_bars_checked: false,
             start_time,
+            frame_metrics,
             gui_state,
+            mode: system_mode_machine(),
             quit: false,
-            ui_app_assets,
         });
         self.data
             .as_mut()
@@ -326,6 +382,40 @@ impl AppUi {
         Ok(())
     }
 
+    /// Save the window layout for next session. Trouble is logged, not fatal.
+    fn save_layout(data: &UiData) {
+        if let Err(e) = data.gui_state.common_state.save_layout() {
+            log::error!("Unable to save window layout: {:?}", e);
+        }
+    }
+
+    /// Register the frame statistics, for the statistics window.
+    fn register_metrics(state: &CommonState) -> FrameMetrics {
+        let metrics = state.metrics.sender();
+        let lang = state.get_lang();
+        let frame_metrics = FrameMetrics {
+            frame_time_average: metrics.register_gauge(
+                METRIC_FRAME_TIME_AVERAGE,
+                t!("dialog.statistics.frame_time", lang),
+                [0.0, 0.1],
+            ),
+            frame_time_longest: metrics.register_gauge(
+                METRIC_FRAME_TIME_LONGEST,
+                t!("dialog.statistics.frame_time_worst", lang),
+                [0.0, 0.1],
+            ),
+            frames: metrics.register_counter(
+                METRIC_FRAMES,
+                t!("dialog.statistics.frames", lang),
+                [0.0, 60.0],
+            ),
+        };
+        for name in [METRIC_FRAME_TIME_AVERAGE, METRIC_FRAME_TIME_LONGEST] {
+            metrics.add_threshold(name, t!("dialog.statistics.frame_budget", lang), FRAME_BUDGET);
+        }
+        frame_metrics
+    }
+
     /// Frame statistics update, called once per frame.
     /// Only does something once per second.
     fn frame_statistics_update(data: &mut UiData) {
@@ -337,15 +427,13 @@ impl AppUi {
           //  Only once per second past this point.
         let (frame_count, statistics_time, worst_frame_time) =
             data.gui_state.app_state.frame_statistics.reset();
-        //  ***MORE***
-        //  Send 1 second statistics to GUI
-        let stats_msg = StatisticsEvent {
-            frame_time_average: statistics_time.as_secs_f32() / (frame_count.max(1) as f32),
-            frame_time_longest: worst_frame_time.as_secs_f32(),
-            ..Default::default()
-        };
-        //  Pass directly to GUI, without going through a queue.
-        data.gui_state.common_state.pass_event(Box::new(stats_msg));
+        //  Send 1 second statistics to the metrics registry
+        let metrics = &data.frame_metrics;
+        metrics
+            .frame_time_average
+            .set(statistics_time.as_secs_f32() / (frame_count.max(1) as f32));
+        metrics.frame_time_longest.set(worst_frame_time.as_secs_f32());
+        metrics.frames.add(frame_count as u64);
     }
 }
 
@@ -375,18 +463,14 @@ impl rend3_framework::App for AppUi {
     //  One logger goes to a file.
     //  One logger goes to a window in the GUI
     fn register_logger(&mut self) {
-        let log_file_name =
-            get_log_file_name().expect("Unable to figure out where to put log files."); // get appropriate name for platform
-        let _ = simplelog::CombinedLogger::init(vec![
-            ////simplelog::TermLogger::new(LevelFilter::Warn, simplelog::Config::default(), simplelog::TerminalMode::Mixed, simplelog::ColorChoice::Auto),
-            simplelog::WriteLogger::new(
-                LevelFilter::Warn,
-                simplelog::Config::default(),
-                std::fs::File::create(*log_file_name.clone()).expect("Unable to create log file"),
-            ),
-            MessageLogger::new_logger(LevelFilter::Error, self.event_send_channel.clone()),
-        ]);
-        log::warn!("Logging to {:?}", log_file_name); // where the log is going
+        let log_dir = get_log_dir().expect("Unable to figure out where to put log files."); // get appropriate directory for platform
+        //  Levels are as saved last session, and can be changed from the Developer menu.
+        match LogController::init(LogFiles::new(&log_dir), self.event_send_channel.clone()) {
+            Ok(log_controller) => self.log_controller = log_controller,
+            Err(e) => eprintln!("Unable to start logging: {:?}", e),
+        }
+        log::warn!("Logging to {:?}", log_dir); // where the log is going
+        install_crash_handler(&log_dir); // crash reports go with the logs
     }
 
     /// Setup of the graphics enviornment, popping up a panic dialog on error.
@@ -406,11 +490,42 @@ impl rend3_framework::App for AppUi {
         //  Calculate frame statistics
         let data = self.data.as_mut().unwrap();
         Self::frame_statistics_update(data);
+        //  Textures for images finished loading in the background.
+        if data
+            .image_loader
+            .update(&mut data.egui_routine, context.renderer)
+        {
+            data.gui_state.common_state.context.request_repaint(); // keep checking
+        } else if !data.picture_bars_checked {
+            //  All loaded, or not. Picture bars which failed are grid file problems.
+            data.picture_bars_checked = true;
+            let problems = GridSelectParams::picture_bar_problems(
+                &data.gui_state.app_state.grid_select_params,
+                &data.gui_state.common_state.params.asset_dir,
+            );
+            if !problems.is_empty() {
+                let messages: Vec<&str> = problems.iter().map(|s| s.as_str()).collect();
+                data.gui_state.common_state.add_error_window(
+                    t!("window.grid_file_problems", &data.gui_state.common_state.params.lang),
+                    &messages,
+                );
+            }
+        }
         //  Build the 2D GUI
         let (paint_jobs, textures_delta) = data
             .gui_state
             .common_state
             .draw_all(context.window.as_ref().unwrap()); // build the 2D GUI
+        //  The UI scale or the monitor may have changed. The renderer must match egui.
+        let pixels_per_point = data.gui_state.common_state.context.pixels_per_point();
+        if pixels_per_point != data.pixels_per_point {
+            data.pixels_per_point = pixels_per_point;
+            data.egui_routine.resize(
+                context.resolution.x,
+                context.resolution.y,
+                pixels_per_point,
+            );
+        }
         let input = rend3_egui::Input {
             clipped_meshes: &paint_jobs,
             textures_delta,
@@ -502,7 +617,7 @@ impl rend3_framework::App for AppUi {
                     .try_iter()
                     .collect();
                 for ev in events {
-                    println!("User event: {:?}", ev); // ***TEMP***
+                    log::debug!("User event: {:?}", ev);
                     self.handle_user_event(context.window.as_ref().unwrap(), ev);
                 }
             }
@@ -523,14 +638,25 @@ impl rend3_framework::App for AppUi {
                 {
                     return; // 2D UI consumed this event.
                 }
+                //  While a modal dialog is open, the world gets no user input.
+                if data.gui_state.common_state.is_modal_open()
+                    && matches!(
+                        event,
+                        winit::event::WindowEvent::KeyboardInput { .. }
+                            | winit::event::WindowEvent::MouseInput { .. }
+                            | winit::event::WindowEvent::MouseWheel { .. }
+                            | winit::event::WindowEvent::CursorMoved { .. }
+                    )
+                {
+                    return;
+                }
 
                 match event {
                     winit::event::WindowEvent::Resized(size) => {
-                        data.egui_routine.resize(
-                            size.width,
-                            size.height,
-                            context.window.as_ref().unwrap().scale_factor() as f32,
-                        );
+                        data.egui_routine.resize(size.width, size.height, data.pixels_per_point);
+                    }
+                    winit::event::WindowEvent::CloseRequested => {
+                        Self::save_layout(data); // closing the window quits
                     }
                     winit::event::WindowEvent::Focused(gained) => {
                         if gained {
@@ -9,10 +9,19 @@
 //
 //  Animats
 //  June 2022
+use super::dialogbuilder::{confirm_dialog, ConfirmRequest};
+use super::docklayer;
 use super::guiutil;
+use super::logconsole::{LogConsole, LogRecord};
+use super::logcontrol::LogController;
 use super::menunone::MenuNone;
+use super::metrics::MetricsRegistry;
+use super::menustack::{self, MenuStack, MenuTransition};
+use super::modalstack::{self, MessageDialog, ModalDialogLink, ModalStack};
 use crate::t;
-use crate::{GuiWindow, GuiWindowLink, MenuGroupLink};
+use crate::{
+    GuiWindow, GuiWindowLink, IconRegistry, MenuGroupLink, UiScale, WindowFactory, WindowLayout,
+};
 use anyhow::{anyhow, Error};
 use core::cell::RefCell;
 use rend3::ExtendedAdapterInfo;
@@ -21,12 +30,9 @@ use simplelog::SharedLogger;
 use std::any::Any;
 
 use crate::Dictionary;
-use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::Instant;
-/// Configuration
-const MESSAGE_SCROLLBACK_LIMIT: usize = 200; // max scrollback for message window
 /// Useful types
 pub type SendAny = dyn Any + Send; // Can send anything across a channel. Must be boxed, though.
 pub type SendAnyBoxed = Box<SendAny>; // the boxed version
@@ -80,8 +86,8 @@ pub struct GuiParams {
     pub lang: Dictionary, // translation dictionary for chosen language
     /// True if in dark mode.
     pub dark_mode: bool,
-    /// Logging level, which needs to move elsewhere because this is not a constant.
-    pub log_level: LevelFilter,
+    /// Log levels. Shared with the loggers, so changes apply at once.
+    pub log_controller: LogController,
     /// Display menus for this long (secs)
     pub menu_display_secs: u64,
     /// GPU info
@@ -93,15 +99,15 @@ pub struct GuiParams {
 #[derive(Debug)]
 pub enum GuiCommonEvent {
     ErrorMessage((String, Vec<String>)), // pops up an warning dialog (title, [text])
-    LogMessage(String),                  // log to GUI
+    LogMessage(LogRecord),               // log to GUI
+    Confirm(ConfirmRequest),             // asks first, sends an event if the answer is yes
     ErrorShutdown(String),               // Shutdown with a pop-up error dialog.
     Shutdown,                            // eventually, everything shuts down
 }
 
 /// Assets used in displaying the GUI.
-#[derive(Default)]
 pub struct GuiAssets {
-    pub web_icon: egui::TextureId,
+    pub icons: IconRegistry, // icons, by name
 }
 
 ///  User interface info specific to the app
@@ -119,14 +125,22 @@ pub struct CommonState {
     pub platform: egui_winit::State,
     /// Context data
     pub context: egui::Context,
-    /// Miscellaneous message window
-    pub message_window: MessageWindow, // miscellaneous messages ***TEMP***
-    /// Currently active menu group
-    pub menu_group: MenuGroupLink,     // currently active menu group
+    /// Log console, with messages for the user
+    pub log_console: Rc<RefCell<LogConsole>>,
+    /// Menu groups, the top one active
+    pub(crate) menu_stack: MenuStack,
     ///  Disposable dynamic windows
     pub temporary_windows: Vec<GuiWindowLink>,
     /// Windows about to be created
     pending_windows: Vec<(GuiWindowLink, bool)>,
+    /// Modal dialogs, above all windows
+    pub(crate) modal_stack: ModalStack,
+    /// Windows to close at the end of the frame
+    windows_to_close: Vec<egui::Id>,
+    /// Window which was on top last frame
+    focused_window: Option<egui::Id>,
+    /// Docks already drawn this frame
+    docks_drawn: bool,
     /// Translated "OK" for dialogs.
     pub msg_ok: String,             // translated OK message
     /// Unique ID serial number
@@ -141,6 +155,14 @@ pub struct CommonState {
     pub light_mode_visuals: egui::Visuals,
     /// Dark mode colors
     pub dark_mode_visuals: egui::Visuals,
+    /// UI scale, on top of the OS scale factor
+    pub ui_scale: UiScale,
+    /// Window layout, remembered between sessions
+    pub window_layout: WindowLayout,
+    /// Performance metrics, from anywhere
+    pub metrics: MetricsRegistry,
+    /// System clipboard, opened when first used
+    clipboard: Option<arboard::Clipboard>,
 }
 
 impl CommonState {
@@ -154,11 +176,10 @@ impl CommonState {
         event_recv_channel: crossbeam_channel::Receiver<SendAnyBoxed>,
     ) -> Self {
         //  Set up base windows.
-        let message_window = MessageWindow::new(
-            "Messages",
+        let log_console = Rc::new(RefCell::new(LogConsole::new(
+            LogConsole::WINDOW_NAME,
             t!("window.messages", &params.lang),
-            MESSAGE_SCROLLBACK_LIMIT,
-        );
+        )));
         ////let grid_select_window = GridSelectWindow::new("Grid select", t!("window.grid_select", &params.lang), &assets, params.grid_select_params.clone());
         //  Set up defaults
         guiutil::set_default_styles(&context); // set up color and text defaults.
@@ -168,18 +189,35 @@ impl CommonState {
             visuals.override_text_color = Some(egui::Color32::WHITE); // whiter text for dark mode. Usual default is too dim
             visuals
         };
+        //  Our zoom factor is the UI scale, not egui's keyboard zoom.
+        context.options_mut(|options| options.zoom_with_keyboard = false);
+        let ui_scale = UiScale::load().unwrap_or_else(|e| {
+            log::error!("Unable to load UI scale settings, using defaults: {:?}", e);
+            Default::default()
+        });
+        //  Window positions from last session. Must follow the styles, which it preserves.
+        let (mut window_layout, layout_error) = WindowLayout::load(&context);
+        if let Some(e) = layout_error {
+            log::error!("Unable to load window layout, using defaults: {:?}", e);
+        }
+        window_layout.register(LogConsole::WINDOW_NAME, LogConsole::create);
         //  Some common words need translations handy
         let msg_ok = t!("menu.ok", &params.lang).to_string();
         ////let (event_send_channel, event_recv_channel) = crossbeam_channel::unbounded(); // message channel
         Self {
             platform,
             context,
-            message_window,
+            log_console,
+            clipboard: None,
             params: Rc::new(params),
             assets,
             temporary_windows: Vec::new(),
             pending_windows: Vec::new(),
-            menu_group: MenuNone::new_link(),
+            modal_stack: Default::default(),
+            windows_to_close: Vec::new(),
+            focused_window: None,
+            docks_drawn: false,
+            menu_stack: MenuStack::new(MenuNone::new_link()),
             msg_ok,
             unique_id: 0,
             last_interaction_time: Instant::now(),
@@ -187,14 +225,32 @@ impl CommonState {
             event_recv_channel,
             light_mode_visuals,
             dark_mode_visuals,
+            ui_scale,
+            window_layout,
+            metrics: Default::default(),
         }
     }
 
     /// Set the currently active menu group. Consumes menu group
     //  So, on a state change, we have to build a new menu group.
+    //  Any groups pushed beneath it are dropped.
     pub fn set_menu_group(&mut self, menu_group: MenuGroupLink) {
-        log::info!("Displaying menu group {}", menu_group.borrow().get_name());
-        self.menu_group = menu_group;
+        self.menu_stack.set(menu_group);
+    }
+
+    /// Push a menu group over the current one, which is kept for going back.
+    pub fn push_menu_group(&mut self, menu_group: MenuGroupLink, transition: MenuTransition) {
+        self.menu_stack.push(menu_group, transition);
+    }
+
+    /// Go back to the menu group beneath the current one.
+    pub fn pop_menu_group(&mut self) {
+        self.menu_stack.pop();
+    }
+
+    /// Number of menu groups on the stack. More than one means there is a way back.
+    pub fn menu_depth(&self) -> usize {
+        self.menu_stack.depth()
     }
 
     /// Draw all of GUI. Called at beginning of redraw event
@@ -203,17 +259,29 @@ impl CommonState {
         window: &winit::window::Window,
     ) -> (Vec<egui::ClippedPrimitive>, egui::TexturesDelta) {
         ////self.platform.update_time(data.start_time.elapsed().as_secs_f64());
+        self.window_layout.update(&self.context); // any layout reset happens between frames
         self.context
             .begin_pass(self.platform.take_egui_input(window));
+        self.ui_scale.update(&self.context);
+        self.metrics.update(Instant::now()); // collect samples, even with the statistics window closed
 
         // egui commands run here
         ////let show_menus = self.if_gui_awake();
         ////let mut inuse = guimenus::draw(self, show_menus); // draws the GUI (BECOMING OBSOLETE)
         //  Draw the active menus.
-        let menu_group = Rc::clone(&self.menu_group);
+        menustack::apply_menu_changes(self);
+        let ctx = self.context.clone();
+        modalstack::take_modal_keys(&ctx, self); // before any menu can take Enter
+        menustack::handle_back(&ctx, self);
+        let menu_group = self.menu_stack.top();
+        self.docks_drawn = false;
         let mut inuse = menu_group.borrow_mut().draw(self);
+        inuse |= menustack::draw_transition(&ctx, self);
+        //  Modal dialogs go on top of whatever the menu group drew.
+        modalstack::draw_modals(&ctx, self);
+        inuse |= self.is_modal_open(); // a modal dialog keeps the GUI awake
 
-        inuse |= is_at_fullscreen_window_top_bottom(window, &self.context); // check if need to escape from full screen
+        inuse |= is_at_fullscreen_window_top_bottom(&self.context); // check if need to escape from full screen
         if inuse {
             self.wake_up_gui();
         }
@@ -221,6 +289,7 @@ impl CommonState {
             shapes,
             textures_delta,
             platform_output,
+            pixels_per_point,
             ..
         } = self.context.end_pass();
         if !platform_output.events.is_empty() {
@@ -232,24 +301,64 @@ impl CommonState {
             );
         }
         //  Tesselate and return paint jobs.
+        //  Pixels per point includes the UI scale, so it is not the window's scale factor.
         (
-            self.context
-                .tessellate(shapes, window.scale_factor() as f32),
+            self.context.tessellate(shapes, pixels_per_point),
             textures_delta,
         )
     }
 
+    /// Draw the docks, with their docked windows. Once per frame.
+    /// Docks are panels, so a menu group with a central panel must call this before drawing it,
+    /// or the docks will cover the central panel instead of shrinking it.
+    /// Otherwise, draw does it.
+    pub fn draw_docks(&mut self, ctx: &egui::Context) {
+        if self.docks_drawn {
+            return;
+        }
+        self.docks_drawn = true;
+        let windows: Vec<GuiWindowLink> = self.temporary_windows.iter().map(Rc::clone).collect();
+        docklayer::draw_docks(ctx, self, &windows);
+    }
+
     /// Draw all live windows
     pub fn draw(&mut self, ctx: &egui::Context) {
+        //  Docked windows are drawn in their docks, unless the menu group already drew them.
+        self.draw_docks(ctx);
         //  Temporary windows
         //  We have to make a list of the windows to do outside "state" to avoid a double mutable borrow.
         let todo_list: Vec<GuiWindowLink> = self.temporary_windows.iter().map(Rc::clone).collect();
+        //  The rest float.
         for w in &todo_list {
-            w.borrow_mut().draw(ctx, self)
+            let docked = {
+                let window = w.borrow();
+                window.dock_title().is_some() && self.window_layout.docks.is_docked(window.get_id())
+            };
+            if !docked {
+                w.borrow_mut().draw(ctx, self)
+            }
         } // draw all temporaries
         //  Now unlocked.
-        //  Remove any non-live windows.
-        self.temporary_windows.retain(|w| w.borrow().retain());
+        //  Tell the window on top if it just got there.
+        let top_id = ctx.memory(|memory| memory.areas().top_layer_id(egui::Order::Middle)).map(|layer| layer.id);
+        if top_id != self.focused_window {
+            self.focused_window = top_id;
+            if let Some(window) = top_id.and_then(|id| self.find_window(id)) {
+                window.borrow_mut().on_focus(self);
+            }
+        }
+        //  Remove any non-live windows, and any closed by close_window.
+        let to_close = std::mem::take(&mut self.windows_to_close);
+        let (closed, open): (Vec<GuiWindowLink>, Vec<GuiWindowLink>) = std::mem::take(&mut self.temporary_windows)
+            .into_iter()
+            .partition(|w| {
+                let window = w.borrow();
+                !window.retain() || to_close.contains(&window.get_id())
+            });
+        self.temporary_windows = open;
+        for w in closed {
+            w.borrow_mut().on_close(self);
+        }
         //  Add any new windows.
         //  Windows with duplicate IDs are ignored.
         while let Some(w) = self.pending_windows.pop() {
@@ -264,14 +373,18 @@ impl CommonState {
         //  Check for duplicate window
         if replace {
             //  If replacing, drop the old one.
-            self.temporary_windows.retain(|w| w.borrow().get_id() != new_id)
+            if let Some(pos) = self.temporary_windows.iter().position(|w| w.borrow().get_id() == new_id) {
+                let old = self.temporary_windows.remove(pos);
+                old.borrow_mut().on_close(self);
+            }
         } else {
             //  If adding, drop the new one.
             if self.temporary_windows.iter().find(|w| w.borrow().get_id() == new_id).is_some() {
                 return;
             }
         }
-        self.temporary_windows.push(window);    // add
+        self.temporary_windows.push(Rc::clone(&window));    // add
+        window.borrow_mut().on_open(self);
     }
     
     //  Add a new window, ignore if dup.  
@@ -284,6 +397,128 @@ impl CommonState {
         self.pending_windows.push((window, true));
     }
 
+    /// Find an open window by ID. Includes windows opened this frame.
+    //  A window can't find itself while it is drawing, because it is borrowed.
+    pub fn find_window(&self, id: egui::Id) -> Option<GuiWindowLink> {
+        self.all_windows()
+            .find(|w| w.try_borrow().is_ok_and(|w| w.get_id() == id))
+            .map(Rc::clone)
+    }
+
+    /// Find an open window by type. The first one, if there are several.
+    pub fn find_window_of_type<T: Any>(&self) -> Option<GuiWindowLink> {
+        self.all_windows()
+            .find(|w| {
+                w.try_borrow().is_ok_and(|w| {
+                    let window: &dyn GuiWindow = &*w; // downcast the window, not the Ref
+                    window.as_any().is::<T>()
+                })
+            })
+            .map(Rc::clone)
+    }
+
+    /// Is this window open?
+    pub fn is_window_open(&self, id: egui::Id) -> bool {
+        self.find_window(id).is_some()
+    }
+
+    /// IDs and titles of the open windows which have titles, for the Window menu.
+    pub fn list_windows(&self) -> Vec<(egui::Id, String)> {
+        self.temporary_windows
+            .iter()
+            .filter_map(|w| {
+                let window = w.try_borrow().ok()?;
+                Some((window.get_id(), window.get_title()?.to_string()))
+            })
+            .collect()
+    }
+
+    /// Open windows, including ones opened this frame.
+    fn all_windows(&self) -> impl Iterator<Item = &GuiWindowLink> {
+        self.temporary_windows
+            .iter()
+            .chain(self.pending_windows.iter().map(|(w, _)| w))
+    }
+
+    /// Close a window. It goes away at the end of the frame.
+    /// OK to call from within a draw.
+    pub fn close_window(&mut self, id: egui::Id) {
+        self.pending_windows
+            .retain(|(w, _)| !w.try_borrow().is_ok_and(|w| w.get_id() == id));
+        self.windows_to_close.push(id);
+    }
+
+    /// Close a window if it is open, otherwise create it and open it. For menu toggles.
+    /// Returns true if the window is now open.
+    pub fn toggle_window(
+        &mut self,
+        id: egui::Id,
+        create: impl FnOnce(&mut CommonState) -> GuiWindowLink,
+    ) -> bool {
+        if self.is_window_open(id) {
+            self.close_window(id);
+            false
+        } else {
+            let window = create(self);
+            self.add_window(window);
+            true
+        }
+    }
+
+    /// Bring a window to the front, expanded. A docked window becomes the dock's selected tab.
+    pub fn bring_to_front(&mut self, id: egui::Id) {
+        if self.window_layout.docks.is_docked(id) {
+            self.window_layout.docks.show_tab(id);
+        } else {
+            self.context
+                .move_to_top(egui::LayerId::new(egui::Order::Middle, id));
+            self.set_window_collapsed(id, false);
+        }
+    }
+
+    /// Minimize a window, by collapsing it to its title bar.
+    /// Windows which can't be collapsed are not affected.
+    pub fn minimize_window(&mut self, id: egui::Id) {
+        self.set_window_collapsed(id, true);
+    }
+
+    /// Collapse or expand a window.
+    //  egui keeps a window's collapsed state under its ID plus "collapsing".
+    fn set_window_collapsed(&self, id: egui::Id, collapsed: bool) {
+        let mut collapsing = egui::collapsing_header::CollapsingState::load_with_default_open(
+            &self.context,
+            id.with("collapsing"),
+            true,
+        );
+        collapsing.set_open(!collapsed);
+        collapsing.store(&self.context);
+    }
+
+    /// Register a window which can be reopened next session.
+    /// The window's Id must be egui::Id::new(name).
+    pub fn register_window(&mut self, name: &str, factory: WindowFactory) {
+        self.window_layout.register(name, factory);
+    }
+
+    /// Reopen the registered windows which were open at the end of the last session.
+    /// Only the first call does anything.
+    pub fn restore_windows(&mut self) {
+        for factory in self.window_layout.take_saved_windows() {
+            let window = factory(self);
+            self.add_window(window);
+        }
+    }
+
+    /// Save the window layout. Call between frames, usually at shutdown.
+    pub fn save_layout(&self) -> Result<(), Error> {
+        let open_ids: Vec<egui::Id> = self
+            .temporary_windows
+            .iter()
+            .map(|w| w.borrow().get_id())
+            .collect();
+        self.window_layout.save(&self.context, &open_ids)
+    }
+
     /// Get a unique ID, starting from 1.
     pub fn get_unique_id(&mut self) -> egui::Id {
         self.unique_id += 1; // serial number increment
@@ -293,7 +528,9 @@ impl CommonState {
     /// Don't overdo this, because it is a broadcast.
     /// Windows must ignore messages they don't need.
     pub fn pass_event(&mut self, event: SendAnyBoxed) {
-        Rc::clone(&self.menu_group)
+        menustack::apply_menu_changes(self);
+        self.menu_stack
+            .top()
             .borrow_mut()
             .pass_event(self, &event); // pass to menu group, if it wants events.
                                        //  Send to all. Ones that don't need it will ignore it.
@@ -313,15 +550,34 @@ impl CommonState {
     /// Handle common errors.
     /// OK to call from within a draw.
     pub fn add_error_window(&mut self, title: &str, message: &[&str]) {
-        //  Create a window with text and an "OK" button.
-        let w = TextWindow::new_link(
+        //  Create a modal dialog with text and an "OK" button.
+        let dialog = MessageDialog::new_link(
             self.get_unique_id(),
             title,
             message,
-            Some(self.msg_ok.as_str()),
+            self.msg_ok.as_str(),
         );
         //  And add it.
-        self.add_window(w);
+        self.add_modal(dialog);
+    }
+
+    /// Open a modal dialog, on top of everything else.
+    /// OK to call from within a draw.
+    pub fn add_modal(&mut self, dialog: ModalDialogLink) {
+        self.modal_stack.push(dialog);
+    }
+
+    /// Ask the user a yes/no question. The answer is sent as an event.
+    /// OK to call from within a draw.
+    pub fn confirm(&mut self, request: ConfirmRequest) {
+        let id = self.get_unique_id();
+        let dialog = confirm_dialog(id, request, self.get_lang());
+        self.add_modal(dialog);
+    }
+
+    /// Is a modal dialog open? If so, nothing else should get user input.
+    pub fn is_modal_open(&self) -> bool {
+        self.modal_stack.is_open()
     }
 
     /// Pop up "unimplemented" message.
@@ -365,9 +621,62 @@ impl CommonState {
             Ok(())
         }
     }
-    /// Display message in message window
+    /// Display message in the log console
     pub fn add_msg(&mut self, s: String) {
-        self.message_window.add_line(s)
+        self.add_log_record(LogRecord::new(log::Level::Info, "gui", s))
+    }
+
+    /// Add a log record to the log console.
+    pub fn add_log_record(&mut self, record: LogRecord) {
+        self.log_console.borrow_mut().add_record(record)
+    }
+
+    /// Open the log console, if not already open.
+    pub fn open_log_console(&mut self) {
+        let window = LogConsole::create(self);
+        self.add_window(window);
+    }
+
+    /// Ask the user where to save a file, then write it.
+    //  The file dialog and the writing run on their own thread, so the GUI keeps going.
+    //  Trouble is reported in an error window.
+    pub fn save_file_async(
+        &self,
+        title: &str,
+        file_name: &str,
+        write: impl FnOnce(&std::path::Path) -> Result<(), Error> + Send + 'static,
+    ) {
+        let channel = self.get_send_channel().clone();
+        let task = rfd::AsyncFileDialog::new()
+            .set_title(title)
+            .set_file_name(file_name)
+            .save_file();
+        let title = title.to_string();
+        std::thread::spawn(move || {
+            futures::executor::block_on(async move {
+                if let Some(file) = task.await {
+                    if let Err(e) = write(file.path()) {
+                        let event = GuiCommonEvent::ErrorMessage((
+                            title,
+                            vec![format!("{:?}: {:?}", file.path(), e)],
+                        ));
+                        let _ = Self::send_gui_event_on_channel(&channel, Box::new(event)); // if we can't send, we must be shutting down
+                    }
+                }
+            })
+        });
+    }
+
+    /// Put text on the system clipboard.
+    pub fn copy_to_clipboard(&mut self, text: &str) -> Result<(), Error> {
+        //  Kept, because on some platforms the text goes away with the clipboard.
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new()?);
+        }
+        if let Some(clipboard) = &mut self.clipboard {
+            clipboard.set_text(text)?;
+        }
+        Ok(())
     }
 }
 /*
@@ -411,9 +720,13 @@ impl<T: AppState> GuiState<T> {
 
 /// Text window, with noninteractive content.
 //  The persistent part
+#[derive(GuiWindow)]
 pub struct TextWindow {
+    #[gui(title)]
     title: String,                  // title of window
+    #[gui(id)]
     id: egui::Id,                   // unique ID
+    #[gui(is_open)]
     is_open: bool,                  // true if open
     message: Vec<String>,           // window text
     dismiss_button: Option<String>, // for "OK" button if desired
@@ -503,73 +816,6 @@ impl GuiWindow for TextWindow {
             } // do here to avoid borrow clash
         }
     }
-    /// If this is in the dynamic widgets list, drop if retain is false.
-    fn retain(&self) -> bool {
-        self.is_open
-    }
-
-    //  Access ID
-    fn get_id(&self) -> egui::Id {
-        self.id
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
-}
-
-/// A scrolling text message window.
-//  The persistent part
-pub struct MessageWindow {
-    title: String,           // title of window
-    id: egui::Id,            // unique ID
-    lines: VecDeque<String>, // the text
-}
-
-impl MessageWindow {
-    /// Create scrollable message window
-    pub fn new(id: &str, title: &str, scrollback_limit: usize) -> Self {
-        MessageWindow {
-            id: egui::Id::new(id),
-            title: title.to_string(),
-            lines: VecDeque::with_capacity(scrollback_limit),
-        }
-    }
-
-    /// Add a line of text. Consumes string
-    pub fn add_line(&mut self, text: String) {
-        self.lines.push_back(text);
-    }
-
-    /// Draw window of text
-    pub fn draw(&self, ctx: &egui::Context, _params: &Rc<GuiParams>) {
-        let window = egui::containers::Window::new(self.title.as_str()).id(self.id);
-        window.show(ctx, |ui| {
-            //  Ref: https://docs.rs/egui/latest/egui/containers/struct.ScrollArea.html#method.show_rows
-            let text_style = egui::TextStyle::Body;
-            let row_height = ui.text_style_height(&text_style);
-            egui::ScrollArea::both().stick_to_bottom(true).show_rows(
-                ui,
-                row_height,
-                self.lines.len(),
-                |ui, row_range| {
-                    for row in row_range {
-                        if row >= self.lines.len() {
-                            break;
-                        }
-                        let text = &self.lines[row];
-                        ui.add(egui::Label::new(text).truncate());
-                    }
-                },
-            );
-        });
-    }
 }
 
 /// We're in real trouble and the main GUI isn't running. Modal dialog.
@@ -585,32 +831,19 @@ pub fn panic_dialog(title: &str, message: &str) {
 
 /// True if cursor is at the top or bottom of the screen in full screen mode.
 //  This is how you get the menus back from a totally clean window.
-pub fn is_at_fullscreen_window_top_bottom(
-    window: &winit::window::Window,
-    ctx: &egui::Context,
-) -> bool {
-    const NEAR_EDGE: f32 = 5.0; // if within this many pixels of top or bottom
+//  Everything here is in points, so the edges scale with the UI.
+pub fn is_at_fullscreen_window_top_bottom(ctx: &egui::Context) -> bool {
+    const NEAR_EDGE: f32 = 5.0; // if within this many points of top or bottom
                                 ////if !window.fullscreen().is_some() { return false; }               // only meaningful for full screen
-    let inner_size = window.inner_size(); // sizes of window
-                                          ////let ctx = data.gui_state.platform.context();
+    let screen_rect = ctx.screen_rect(); // size of window, in points
     if let Some(pos) = ctx.pointer_interact_pos() {
         // check for pointer at top or bottom of window
-        ////println!("pos: {:?}, height: {}", pos, inner_size.height);
-        pos.y < NEAR_EDGE || pos.y + NEAR_EDGE > (inner_size.height as f32)
+        pos.y < screen_rect.top() + NEAR_EDGE || pos.y + NEAR_EDGE > screen_rect.bottom()
     } else {
         false
     }
 }
 
-/// First N chars of a string.  UTF-8 aware, but not grapheme aware. For log entries.
-fn first_n_chars(s: &str, n: usize) -> &str {
-    if let Some((x, _)) = s.char_indices().nth(n) {
-        &s[..x]
-    } else {
-        s
-    }
-}
-
 /// Logging to GUI
 //  This is complicated by its having to outlive
 //  almost everything else. Even the GUI to which
@@ -650,16 +883,8 @@ impl log::Log for MessageLogger {
         if record.level() > self.level_filter {
             return;
         } // filter out messages below threshold
-          // Format for display.
-        const MAX_LOG_MSG_LENGTH: usize = 100; // trim msg to this length
-        let s = format!(
-            "[{}] ({}): {}",
-            record.level(),
-            record.target(),
-            record.args()
-        );
-        let s = first_n_chars(&s, MAX_LOG_MSG_LENGTH).to_string(); // trim string
-        let event = GuiCommonEvent::LogMessage(s);
+          // Sent whole. The log console decides how to show it.
+        let event = GuiCommonEvent::LogMessage(LogRecord::from_record(record));
         if let Err(e) = CommonState::send_gui_event_on_channel(&self.send_channel, Box::new(event))
         {
             println!(
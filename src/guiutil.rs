@@ -7,15 +7,17 @@ use egui::FontFamily::Proportional;
 use egui::FontId;
 use egui::TextStyle::{Body, Button, Heading, /*Name,*/ Monospace, Small};
 use egui::TextureId;
-use image::{DynamicImage, GenericImageView};
+use image::DynamicImage;
 use rend3::Renderer;
 use rend3_egui::EguiRenderRoutine;
 use std::str::FromStr;
 use rend3::types::TextureFormat;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 
 const DEVELOPER: &str = "animats"; // used for directory generation - lower case
-const LOG_FILE_NAME: &str = "log.txt"; // name of log file
+/// Suffix of files being written by write_atomic.
+pub(crate) const TEMP_FILE_SUFFIX: &str = "tmp";
 
 /// Load an icon at compile time. Image is built into executable.
 pub fn load_canned_icon(
@@ -24,18 +26,14 @@ pub fn load_canned_icon(
     renderer: &Arc<Renderer>,
 ) -> TextureId {
     //  Images
-    let image_image = image::load_from_memory(image_bytes).unwrap();
-    let image_rgba = image_image.as_rgba8().unwrap().clone().into_raw();
-    let dimensions = image_image.dimensions();
-    let format = TextureFormat::Rgba8UnormSrgb;
-    //  Create and return texture
-    rend3_egui::EguiRenderRoutine::create_egui_texture(
-        &mut egui_routine.internal,
+    let image_image = image::load_from_memory(image_bytes).unwrap(); // built in, so must be valid
+    let image_rgba = image_image.to_rgba8(); // any format
+    create_texture(
+        image_rgba.as_raw(),
+        image_rgba.dimensions(),
+        "Canned icon",
+        egui_routine,
         renderer,
-        format,
-        &image_rgba,
-        dimensions,
-        Some("Canned icon"),
     )
 }
 
@@ -46,17 +44,33 @@ pub fn load_image(
     renderer: &Arc<Renderer>,
 ) -> TextureId {
     //Images
-    let image_rgba = image_image.as_rgba8().unwrap().clone().into_raw();
-    let dimensions = image_image.dimensions();
+    let image_rgba = image_image.to_rgba8(); // any format
+    create_texture(
+        image_rgba.as_raw(),
+        image_rgba.dimensions(),
+        "Built-in image",
+        egui_routine,
+        renderer,
+    )
+}
+
+/// Create a texture from RGBA bytes. Must be called from the main thread.
+pub(crate) fn create_texture(
+    image_rgba: &[u8],
+    dimensions: (u32, u32),
+    label: &str,
+    egui_routine: &mut EguiRenderRoutine,
+    renderer: &Arc<Renderer>,
+) -> TextureId {
     let format = TextureFormat::Rgba8UnormSrgb;
     //  Create and return texture
     rend3_egui::EguiRenderRoutine::create_egui_texture(
         &mut egui_routine.internal,
         renderer,
         format,
-        &image_rgba,
+        image_rgba,
         dimensions,
-        Some("Built-in image"),
+        Some(label),
     )
 }
 
@@ -93,17 +107,14 @@ pub fn get_executable_name() -> String {
         .to_lowercase() // just to get program name
 }
 
-/// Get log path -- get file name for log
-pub fn get_log_file_name() -> Result<Box<std::path::PathBuf>, Error> {
+/// Get log directory. Log files for each session go here.
+pub fn get_log_dir() -> Result<Box<std::path::PathBuf>, Error> {
     let executable = get_executable_name(); // name of program
     if let Some(proj_dirs) = directories::ProjectDirs::from("com", DEVELOPER, &executable) {
         let local_dir = proj_dirs.data_local_dir(); // directory into which logs will go
-        println!("Proj dirs data local dir: {:?}", local_dir); // ***TEMP***
         std::fs::create_dir_all(local_dir)
             .with_context(|| format!("Trouble creating logging directory: {:?}", local_dir))?; // create any needed directories
-        let path = local_dir.join(LOG_FILE_NAME);
-        println!("Log path: {:?}", path); // ***TEMP***
-        Ok(Box::new(path))
+        Ok(Box::new(local_dir.to_path_buf()))
     } else {
         Err(anyhow!("Unable to determine project directories"))
     }
@@ -114,7 +125,6 @@ pub fn get_cache_dir() -> Result<Box<std::path::PathBuf>, Error> {
     let executable = get_executable_name(); // name of program
     if let Some(proj_dirs) = directories::ProjectDirs::from("com", DEVELOPER, &executable) {
         let cache_dir = proj_dirs.cache_dir(); // directory into which cached files will go
-        println!("Cache directory: {:?}", cache_dir); // ***TEMP***
         std::fs::create_dir_all(cache_dir)
             .with_context(|| format!("Trouble creating cache directory: {:?}", cache_dir))?; // create any needed directories
         Ok(Box::new(cache_dir.to_path_buf()))
@@ -123,6 +133,50 @@ pub fn get_cache_dir() -> Result<Box<std::path::PathBuf>, Error> {
     }
 }
 
+/// Get configuration directory, for preferences and other user settings.
+pub fn get_config_dir() -> Result<Box<std::path::PathBuf>, Error> {
+    let executable = get_executable_name(); // name of program
+    if let Some(proj_dirs) = directories::ProjectDirs::from("com", DEVELOPER, &executable) {
+        let config_dir = proj_dirs.config_dir(); // directory for settings files
+        std::fs::create_dir_all(config_dir)
+            .with_context(|| format!("Trouble creating config directory: {:?}", config_dir))?; // create any needed directories
+        Ok(Box::new(config_dir.to_path_buf()))
+    } else {
+        Err(anyhow!("Unable to determine directories"))
+    }
+}
+
+/// Move a settings file which can't be used out of the way, as NAME.bad,
+/// so saving the settings again doesn't lose it. Returns where it went.
+pub fn move_aside(path: &std::path::Path) -> Result<std::path::PathBuf, Error> {
+    let mut bad_name = path.as_os_str().to_owned();
+    bad_name.push(".bad");
+    let bad_path = std::path::PathBuf::from(bad_name);
+    std::fs::rename(path, &bad_path)
+        .with_context(|| format!("Unable to move {:?} aside to {:?}", path, bad_path))?;
+    Ok(bad_path)
+}
+
+/// Write a whole file, or nothing. Written under a temporary name, NAME.PID-N.tmp, then renamed,
+/// so a crash or a second writer never leaves a partial file.
+pub fn write_atomic(path: &std::path::Path, contents: &[u8]) -> Result<(), Error> {
+    //  Unique per write, so two threads writing the same file don't collide.
+    static TEMP_COUNT: AtomicU64 = AtomicU64::new(0);
+    let temp_path = path.with_extension(format!(
+        "{}-{}.{}",
+        std::process::id(),
+        TEMP_COUNT.fetch_add(1, Ordering::Relaxed),
+        TEMP_FILE_SUFFIX
+    ));
+    let result = std::fs::write(&temp_path, contents)
+        .and_then(|_| std::fs::rename(&temp_path, path))
+        .with_context(|| format!("Unable to write {:?}", path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
 /// Get asset directory.
 ///
 /// - First choice: EXECUTABLEDIR/ASSETFOLDERNAME
@@ -0,0 +1,240 @@
+//! httpfetch.rs -- fetch web content, with a disk cache.
+//
+//  Fetching is behind a trait, so tests and offline demos can
+//  use local files instead of the network.
+//
+//  The cache keeps the last good copy of each URL, with its ETag.
+//  Fetches revalidate with If-None-Match. If the network is down,
+//  the cached copy is used. Cache files are written whole, then renamed,
+//  so a crash or a second fetcher never leaves a partial copy.
+//  Anything too big is an error, not cached. Everything here blocks, so call it
+//  from a worker thread, never the GUI thread.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::{get_cache_dir, write_atomic};
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Subdirectory of the cache directory for fetched content.
+const FETCH_CACHE_DIR_NAME: &str = "web";
+/// How long to wait for a web server.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+/// Largest thing we will fetch. Splash images and feeds are much smaller.
+const FETCH_MAX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Result of a fetch.
+#[derive(Debug)]
+pub enum FetchResult {
+    /// New content, with its ETag if the server gave one.
+    Modified { body: Vec<u8>, etag: Option<String> },
+    /// The ETag we sent still matches.
+    NotModified,
+}
+
+/// Something that can GET a URL.
+pub trait HttpFetcher: Send + Sync {
+    /// GET a URL. If an ETag is given, the answer may be NotModified.
+    fn get(&self, url: &str, etag: Option<&str>) -> Result<FetchResult, Error>;
+}
+
+/// Fetch over the network.
+#[derive(Default)]
+pub struct UreqFetcher {}
+
+impl HttpFetcher for UreqFetcher {
+    fn get(&self, url: &str, etag: Option<&str>) -> Result<FetchResult, Error> {
+        let mut request = ureq::get(url).timeout(FETCH_TIMEOUT);
+        if let Some(etag) = etag {
+            request = request.set("If-None-Match", etag);
+        }
+        let response = request
+            .call()
+            .with_context(|| format!("Unable to fetch {}", url))?;
+        if response.status() == 304 {
+            return Ok(FetchResult::NotModified);
+        }
+        let etag = response.header("ETag").map(|s| s.to_string());
+        let mut body = Vec::new();
+        //  One byte more than allowed, to tell too big from just big enough.
+        response
+            .into_reader()
+            .take(FETCH_MAX_SIZE + 1)
+            .read_to_end(&mut body)
+            .with_context(|| format!("Unable to read {}", url))?;
+        if body.len() as u64 > FETCH_MAX_SIZE {
+            return Err(anyhow!(
+                "{} is larger than the limit of {} bytes",
+                url,
+                FETCH_MAX_SIZE
+            ));
+        }
+        Ok(FetchResult::Modified { body, etag })
+    }
+}
+
+/// Fetch from local files, for tests and offline use.
+/// The URL's host and path name a file under the root directory.
+/// The ETag is the MD5 of the file.
+pub struct LocalFileFetcher {
+    root: PathBuf,
+}
+
+impl LocalFileFetcher {
+    /// Usual new
+    pub fn new(root: &Path) -> Self {
+        LocalFileFetcher {
+            root: root.to_path_buf(),
+        }
+    }
+}
+
+impl HttpFetcher for LocalFileFetcher {
+    fn get(&self, url: &str, etag: Option<&str>) -> Result<FetchResult, Error> {
+        let url = url::Url::parse(url)?;
+        let mut path = self.root.join(url.host_str().unwrap_or(""));
+        path.extend(url.path_segments().into_iter().flatten());
+        let body = std::fs::read(&path)
+            .with_context(|| format!("No local file {:?} for {}", path, url))?;
+        let file_etag = format!("\"{:x}\"", md5::compute(&body));
+        if etag == Some(file_etag.as_str()) {
+            Ok(FetchResult::NotModified)
+        } else {
+            Ok(FetchResult::Modified {
+                body,
+                etag: Some(file_etag),
+            })
+        }
+    }
+}
+
+/// What the cache knows about a URL.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheMeta {
+    url: String,          // for debugging the cache
+    etag: Option<String>, // as the server gave it
+}
+
+/// A fetcher with a disk cache in front.
+pub struct CachedFetcher {
+    fetcher: Arc<dyn HttpFetcher>, // where content comes from
+    cache_dir: PathBuf,            // where it is kept
+}
+
+impl CachedFetcher {
+    /// Cache in the standard cache directory.
+    pub fn new(fetcher: Arc<dyn HttpFetcher>) -> Result<Self, Error> {
+        Ok(Self::new_in(
+            fetcher,
+            &get_cache_dir()?.join(FETCH_CACHE_DIR_NAME),
+        ))
+    }
+
+    /// Cache in the given directory.
+    pub fn new_in(fetcher: Arc<dyn HttpFetcher>, cache_dir: &Path) -> Self {
+        CachedFetcher {
+            fetcher,
+            cache_dir: cache_dir.to_path_buf(),
+        }
+    }
+
+    /// Cache file names for a URL: (body, meta)
+    fn cache_files(&self, url: &str) -> (PathBuf, PathBuf) {
+        let key = format!("{:x}", md5::compute(url));
+        (
+            self.cache_dir.join(format!("{}.body", key)),
+            self.cache_dir.join(format!("{}.json", key)),
+        )
+    }
+
+    /// Fetch a URL. Uses the cached copy if it is still current, or if the fetch fails.
+    /// Fails only if the fetch fails and nothing is cached.
+    pub fn fetch(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let (body_file, meta_file) = self.cache_files(url);
+        let cached_opt = std::fs::read(&body_file).ok();
+        let etag_opt = if cached_opt.is_some() {
+            std::fs::read_to_string(&meta_file)
+                .ok()
+                .and_then(|s| serde_json::from_str::<CacheMeta>(&s).ok())
+                .and_then(|meta| meta.etag)
+        } else {
+            None
+        };
+        match self.fetcher.get(url, etag_opt.as_deref()) {
+            Ok(FetchResult::Modified { body, etag }) => {
+                if let Err(e) = self.store(url, &body, etag, &body_file, &meta_file) {
+                    log::warn!("Unable to cache {}: {:?}", url, e);
+                }
+                Ok(body)
+            }
+            Ok(FetchResult::NotModified) => {
+                cached_opt.ok_or_else(|| anyhow!("Not modified, but not in cache: {}", url))
+            }
+            Err(e) => match cached_opt {
+                Some(cached) => {
+                    log::warn!("Using cached copy of {}: {:?}", url, e);
+                    Ok(cached)
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Save in cache.
+    //  The old metadata goes first and the new metadata last, so a body
+    //  is never paired with some other body's ETag.
+    fn store(
+        &self,
+        url: &str,
+        body: &[u8],
+        etag: Option<String>,
+        body_file: &Path,
+        meta_file: &Path,
+    ) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        match std::fs::remove_file(meta_file) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        write_atomic(body_file, body)?;
+        let meta = CacheMeta {
+            url: url.to_string(),
+            etag,
+        };
+        write_atomic(meta_file, serde_json::to_string(&meta)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_cached_fetcher() {
+    let dir = std::env::temp_dir().join(format!("httpfetch-test-{}", std::process::id()));
+    let root = dir.join("web");
+    std::fs::create_dir_all(root.join("example.com").join("news")).unwrap();
+    let feed_file = root.join("example.com").join("news").join("feed.xml");
+    std::fs::write(&feed_file, "first").unwrap();
+    let local = Arc::new(LocalFileFetcher::new(&root));
+    let fetcher = CachedFetcher::new_in(local, &dir.join("cache"));
+    let url = "https://example.com/news/feed.xml";
+    assert_eq!(fetcher.fetch(url).unwrap(), b"first");
+    assert_eq!(fetcher.fetch(url).unwrap(), b"first"); // not modified, from cache
+    std::fs::write(&feed_file, "second").unwrap();
+    assert_eq!(fetcher.fetch(url).unwrap(), b"second"); // modified
+    std::fs::remove_file(&feed_file).unwrap();
+    assert_eq!(fetcher.fetch(url).unwrap(), b"second"); // "offline", from cache
+    assert!(fetcher.fetch("https://example.com/nothing").is_err());
+    //  Nothing left half written.
+    assert!(std::fs::read_dir(dir.join("cache"))
+        .unwrap()
+        .all(|entry| !entry
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+            .ends_with(".tmp")));
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,364 @@
+//! iconregistry.rs -- icons, looked up by name.
+//
+//  Icons are loaded at run time from a directory, not compiled in,
+//  so adding an icon or a theme means adding files, not fields.
+//
+//  A directory can hold:
+//  - PNG files. "name.png", with an optional "name@2x.png" for HiDPI screens.
+//  - SVG files. "name.svg", rendered at the screen's scale.
+//  - A packed atlas. "atlas.json", naming an atlas image and the
+//    rectangle of each icon in it. The atlas is cut up into one texture per icon.
+//
+//  Loading a second directory overrides icons of the same name,
+//  which is how themes work. Missing icons draw as a placeholder
+//  checkerboard, so they are obvious but not fatal.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::create_texture;
+use anyhow::{anyhow, Context, Error};
+use core::cell::RefCell;
+use egui::TextureId;
+use image::{GenericImageView, RgbaImage};
+use rend3::Renderer;
+use rend3_egui::EguiRenderRoutine;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Name of the atlas description file in an icon directory.
+const ATLAS_FILE_NAME: &str = "atlas.json";
+/// Suffix on the file name of HiDPI variants.
+const HIDPI_SUFFIX: &str = "@2x";
+/// Use HiDPI variants at or above this many pixels per point.
+const HIDPI_THRESHOLD: f32 = 1.5;
+/// Size of the placeholder, in pixels and points.
+const PLACEHOLDER_SIZE: u32 = 16;
+/// Size of a placeholder checkerboard square, in pixels.
+const PLACEHOLDER_SQUARE: u32 = 4;
+
+/// An icon, ready to draw.
+#[derive(Debug, Clone, Copy)]
+pub struct Icon {
+    pub texture: TextureId, // the texture
+    pub size: egui::Vec2,   // natural size, in points
+}
+
+/// Atlas description file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AtlasJson {
+    image: String,                    // atlas image file
+    image_2x: Option<String>,         // HiDPI atlas image file, same layout at twice the size
+    icons: HashMap<String, [u32; 4]>, // name -> [x, y, width, height], in pixels of image
+}
+
+/// An icon file found in a directory.
+#[derive(Debug, Clone, PartialEq)]
+struct IconFile {
+    path: PathBuf, // the file
+    scale: f32,    // pixels per point the file was drawn for
+    svg: bool,     // SVG, not PNG
+}
+
+/// Icon name and details from a file name. None if not an icon file.
+fn parse_icon_file_name(path: &Path) -> Option<(String, IconFile)> {
+    let svg = match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "png" => false,
+        "svg" => true,
+        _ => return None,
+    };
+    let stem = path.file_stem()?.to_str()?;
+    let (name, scale) = match stem.strip_suffix(HIDPI_SUFFIX) {
+        Some(name) => (name, 2.0),
+        None => (stem, 1.0),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some((
+        name.to_string(),
+        IconFile {
+            path: path.to_path_buf(),
+            scale,
+            svg,
+        },
+    ))
+}
+
+/// Pick the best file for the screen. SVG first, since it renders at any scale,
+/// then the PNG drawn for this screen, then whatever there is.
+fn pick_icon_file(files: &[IconFile], pixels_per_point: f32) -> Option<&IconFile> {
+    let wanted_scale = if pixels_per_point >= HIDPI_THRESHOLD {
+        2.0
+    } else {
+        1.0
+    };
+    files.iter().min_by_key(|file| {
+        if file.svg {
+            0
+        } else if file.scale == wanted_scale {
+            1
+        } else {
+            2
+        }
+    })
+}
+
+/// True if an atlas entry, from start for length, scaled, fits in the image.
+//  Numbers come from a file, so overflow means a bad entry, not a panic.
+fn fits_in(start: u32, length: u32, scale: u32, image_length: u32) -> bool {
+    start
+        .checked_add(length)
+        .and_then(|end| end.checked_mul(scale))
+        .is_some_and(|end| end <= image_length)
+}
+
+/// Render an SVG file at the given scale. Returns the image and its size in points.
+fn render_svg(bytes: &[u8], pixels_per_point: f32) -> Result<(RgbaImage, egui::Vec2), Error> {
+    let tree = resvg::usvg::Tree::from_data(bytes, &resvg::usvg::Options::default())?;
+    let size = egui::vec2(tree.size().width(), tree.size().height());
+    let width = (size.x * pixels_per_point).ceil().max(1.0) as u32;
+    let height = (size.y * pixels_per_point).ceil().max(1.0) as u32;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| anyhow!("Bad SVG size {} x {}", width, height))?;
+    resvg::render(
+        &tree,
+        resvg::tiny_skia::Transform::from_scale(pixels_per_point, pixels_per_point),
+        &mut pixmap.as_mut(),
+    );
+    //  Pixmaps are premultiplied. Textures are not.
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    let image = RgbaImage::from_raw(width, height, rgba).expect("Pixmap size mismatch");
+    Ok((image, size))
+}
+
+/// Magenta and black checkerboard, for missing icons.
+fn placeholder_image() -> RgbaImage {
+    RgbaImage::from_fn(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE, |x, y| {
+        if (x / PLACEHOLDER_SQUARE + y / PLACEHOLDER_SQUARE) % 2 == 0 {
+            image::Rgba([255, 0, 255, 255])
+        } else {
+            image::Rgba([0, 0, 0, 255])
+        }
+    })
+}
+
+/// All the icons, by name.
+pub struct IconRegistry {
+    icons: HashMap<String, Icon>,      // loaded icons
+    placeholder: Icon,                 // drawn for missing icons
+    pixels_per_point: f32,             // screen scale, for picking variants
+    missing: RefCell<HashSet<String>>, // missing icons already reported
+}
+
+impl IconRegistry {
+    /// Usual new. No icons yet.
+    pub fn new(
+        pixels_per_point: f32,
+        egui_routine: &mut EguiRenderRoutine,
+        renderer: &Arc<Renderer>,
+    ) -> Self {
+        let placeholder_image = placeholder_image();
+        let placeholder = Icon {
+            texture: create_texture(
+                placeholder_image.as_raw(),
+                placeholder_image.dimensions(),
+                "Missing icon",
+                egui_routine,
+                renderer,
+            ),
+            size: egui::Vec2::splat(PLACEHOLDER_SIZE as f32),
+        };
+        IconRegistry {
+            icons: HashMap::new(),
+            placeholder,
+            pixels_per_point,
+            missing: RefCell::new(HashSet::new()),
+        }
+    }
+
+    /// Load all the icons in a directory. Icons already loaded with the same name are replaced.
+    /// Returns a list of problems. Icons with problems are skipped.
+    pub fn load_dir(
+        &mut self,
+        dir: &Path,
+        egui_routine: &mut EguiRenderRoutine,
+        renderer: &Arc<Renderer>,
+    ) -> Vec<String> {
+        let mut problems = Vec::new();
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                problems.push(format!("Icon directory {:?}: {}", dir, e));
+                return problems;
+            }
+        };
+        //  Atlas first, so that separate files override it.
+        let atlas_file = dir.join(ATLAS_FILE_NAME);
+        if atlas_file.is_file() {
+            if let Err(e) = self.load_atlas(&atlas_file, egui_routine, renderer) {
+                problems.push(format!("Icon atlas {:?}: {:#}", atlas_file, e));
+            }
+        }
+        //  Group the variants of each icon.
+        let mut files: HashMap<String, Vec<IconFile>> = HashMap::new();
+        for entry in entries.flatten() {
+            if let Some((name, file)) = parse_icon_file_name(&entry.path()) {
+                files.entry(name).or_default().push(file);
+            }
+        }
+        for (name, variants) in files {
+            if let Some(file) = pick_icon_file(&variants, self.pixels_per_point) {
+                match self.load_icon_file(file, egui_routine, renderer) {
+                    Ok(icon) => {
+                        self.icons.insert(name, icon);
+                    }
+                    Err(e) => problems.push(format!("Icon {:?}: {:#}", file.path, e)),
+                }
+            }
+        }
+        problems
+    }
+
+    /// Load one icon file.
+    fn load_icon_file(
+        &self,
+        file: &IconFile,
+        egui_routine: &mut EguiRenderRoutine,
+        renderer: &Arc<Renderer>,
+    ) -> Result<Icon, Error> {
+        let bytes = std::fs::read(&file.path)?;
+        let (image, size) = if file.svg {
+            render_svg(&bytes, self.pixels_per_point)?
+        } else {
+            let image = image::load_from_memory(&bytes)?.to_rgba8();
+            let (width, height) = image.dimensions();
+            let size = egui::vec2(width as f32, height as f32) / file.scale;
+            (image, size)
+        };
+        let label = file.path.to_string_lossy();
+        Ok(Icon {
+            texture: create_texture(
+                image.as_raw(),
+                image.dimensions(),
+                &label,
+                egui_routine,
+                renderer,
+            ),
+            size,
+        })
+    }
+
+    /// Load a packed atlas. Each icon gets its own texture, so all icons draw the same way.
+    fn load_atlas(
+        &mut self,
+        atlas_file: &Path,
+        egui_routine: &mut EguiRenderRoutine,
+        renderer: &Arc<Renderer>,
+    ) -> Result<(), Error> {
+        let atlas: AtlasJson = serde_json::from_str(&std::fs::read_to_string(atlas_file)?)?;
+        let dir = atlas_file.parent().unwrap_or(Path::new("."));
+        let (image_name, scale) = match &atlas.image_2x {
+            Some(image_2x) if self.pixels_per_point >= HIDPI_THRESHOLD => (image_2x, 2),
+            _ => (&atlas.image, 1),
+        };
+        let image = image::open(dir.join(image_name))
+            .with_context(|| format!("Atlas image {}", image_name))?
+            .to_rgba8();
+        //  Check everything before creating any textures.
+        for (name, &[x, y, width, height]) in &atlas.icons {
+            if width == 0 || height == 0 {
+                return Err(anyhow!("Icon {} is empty", name));
+            }
+            if !fits_in(x, width, scale, image.width())
+                || !fits_in(y, height, scale, image.height())
+            {
+                return Err(anyhow!("Icon {} is outside the atlas image", name));
+            }
+        }
+        for (name, [x, y, width, height]) in atlas.icons {
+            let icon_image = image
+                .view(x * scale, y * scale, width * scale, height * scale)
+                .to_image();
+            let icon = Icon {
+                texture: create_texture(
+                    icon_image.as_raw(),
+                    icon_image.dimensions(),
+                    &name,
+                    egui_routine,
+                    renderer,
+                ),
+                size: egui::vec2(width as f32, height as f32),
+            };
+            self.icons.insert(name, icon);
+        }
+        Ok(())
+    }
+
+    /// Look up an icon. Missing icons get the placeholder, and are reported once.
+    pub fn get(&self, name: &str) -> Icon {
+        match self.icons.get(name) {
+            Some(icon) => *icon,
+            None => {
+                if self.missing.borrow_mut().insert(name.to_string()) {
+                    log::error!("No icon named \"{}\"", name);
+                }
+                self.placeholder
+            }
+        }
+    }
+
+    /// True if the icon has been loaded.
+    pub fn contains(&self, name: &str) -> bool {
+        self.icons.contains_key(name)
+    }
+}
+
+#[test]
+fn test_icon_file_names() {
+    let (name, file) = parse_icon_file_name(Path::new("icons/web.png")).unwrap();
+    assert_eq!((name.as_str(), file.scale, file.svg), ("web", 1.0, false));
+    let (name, file) = parse_icon_file_name(Path::new("icons/web@2x.PNG")).unwrap();
+    assert_eq!((name.as_str(), file.scale, file.svg), ("web", 2.0, false));
+    let (name, file) = parse_icon_file_name(Path::new("icons/move-arrows.svg")).unwrap();
+    assert_eq!((name.as_str(), file.svg), ("move-arrows", true));
+    assert!(parse_icon_file_name(Path::new("icons/atlas.json")).is_none());
+    assert!(parse_icon_file_name(Path::new("icons/@2x.png")).is_none());
+    //  Pick the variant for the screen.
+    let png_1x = parse_icon_file_name(Path::new("web.png")).unwrap().1;
+    let png_2x = parse_icon_file_name(Path::new("web@2x.png")).unwrap().1;
+    let svg = parse_icon_file_name(Path::new("web.svg")).unwrap().1;
+    let pngs = [png_1x.clone(), png_2x.clone()];
+    assert_eq!(pick_icon_file(&pngs, 1.0), Some(&png_1x));
+    assert_eq!(pick_icon_file(&pngs, 2.0), Some(&png_2x));
+    assert_eq!(pick_icon_file(&pngs[..1], 2.0), Some(&png_1x));
+    assert_eq!(pick_icon_file(&[png_2x, svg.clone()], 1.0), Some(&svg));
+    //  The placeholder is visible on both light and dark backgrounds.
+    let placeholder = placeholder_image();
+    assert_eq!(
+        placeholder.get_pixel(0, 0),
+        &image::Rgba([255, 0, 255, 255])
+    );
+    assert_eq!(
+        placeholder.get_pixel(PLACEHOLDER_SQUARE, 0),
+        &image::Rgba([0, 0, 0, 255])
+    );
+}
+
+#[test]
+fn test_atlas_bounds() {
+    assert!(fits_in(0, 32, 2, 64));
+    assert!(!fits_in(1, 32, 2, 64));
+    assert!(!fits_in(u32::MAX, 1, 1, 64)); // would overflow
+    assert!(!fits_in(u32::MAX / 2, 1, 2, u32::MAX)); // so would this
+}
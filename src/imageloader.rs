@@ -0,0 +1,407 @@
+//! imageloader.rs -- load images in the background.
+//
+//  Images are decoded on worker threads. The caller gets an ImageHandle
+//  at once, showing a placeholder texture. When decoding finishes, the
+//  texture is created on the main thread and the handle switches to it.
+//
+//  Decoded images are cached on disk, keyed by the MD5 of the
+//  undecoded file, so the next run skips decoding. Decoded images
+//  are big, so the cache has a size limit. The least recently used
+//  images go first.
+//
+//  An image which won't load leaves its handle on the placeholder,
+//  and the handle says why, so the caller can tell the user.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::{create_texture, get_cache_dir, write_atomic, TEMP_FILE_SUFFIX};
+use crate::httpfetch::CachedFetcher;
+use anyhow::{anyhow, Context, Error};
+use crossbeam_channel::{Receiver, Sender};
+use egui::TextureId;
+use rend3::Renderer;
+use rend3_egui::EguiRenderRoutine;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Subdirectory of the cache directory for decoded images.
+const IMAGE_CACHE_DIR_NAME: &str = "images";
+/// Most worker threads to use. Decoding is CPU bound.
+const MAX_IMAGE_WORKERS: usize = 4;
+/// Most bytes of decoded images kept on disk.
+const IMAGE_CACHE_MAX_SIZE: u64 = 256 * 1024 * 1024;
+/// Suffix of decoded image cache files.
+const IMAGE_CACHE_SUFFIX: &str = "rgba";
+/// Temporary files older than this were left by a crash, and are deleted.
+const STALE_TEMP_FILE_AGE: Duration = Duration::from_secs(60);
+
+/// What a handle shows.
+#[derive(Debug)]
+struct ImageSlot {
+    texture: TextureId,    // placeholder, then the image
+    error: Option<String>, // why the image didn't load, if it didn't
+}
+
+/// A texture which may not be loaded yet.
+/// Cloned handles all see the texture when it arrives.
+//  Arc and Mutex because handles travel in events.
+#[derive(Debug, Clone)]
+pub struct ImageHandle(Arc<Mutex<ImageSlot>>);
+
+impl ImageHandle {
+    /// A handle for a texture which is already loaded.
+    pub fn new(texture: TextureId) -> Self {
+        ImageHandle(Arc::new(Mutex::new(ImageSlot {
+            texture,
+            error: None,
+        })))
+    }
+
+    /// The texture to draw now. The placeholder until the image is ready.
+    pub fn get(&self) -> TextureId {
+        self.0.lock().unwrap().texture
+    }
+
+    /// Why the image didn't load, if it didn't. The placeholder stays.
+    pub fn error(&self) -> Option<String> {
+        self.0.lock().unwrap().error.clone()
+    }
+
+    /// Switch to the real texture.
+    fn set(&self, texture: TextureId) {
+        self.0.lock().unwrap().texture = texture;
+    }
+
+    /// Loading failed.
+    fn fail(&self, error: String) {
+        self.0.lock().unwrap().error = Some(error);
+    }
+}
+
+/// An image, decoded to RGBA.
+struct DecodedImage {
+    dimensions: (u32, u32), // width, height
+    rgba: Vec<u8>,          // 4 bytes per pixel
+}
+
+/// Where the undecoded image comes from.
+enum ImageSource {
+    File(PathBuf),                   // image file
+    Bytes(String, Vec<u8>),          // name, for messages, and image file contents
+    Url(String, Arc<CachedFetcher>), // web image, and how to get it
+}
+
+/// Work for a worker thread.
+struct ImageJob {
+    source: ImageSource, // what to decode
+    target: ImageHandle, // where the result goes
+}
+
+/// Result from a worker thread.
+struct ImageDone {
+    name: String,                        // for messages
+    target: ImageHandle,                 // where the result goes
+    result: Result<DecodedImage, Error>, // the image, or why not
+}
+
+/// The image loading service.
+//  One per program. Owned by the main thread.
+pub struct ImageLoader {
+    job_send: Sender<ImageJob>,     // work for the workers
+    done_recv: Receiver<ImageDone>, // results from the workers
+    pending: usize,                 // jobs not yet finished
+}
+
+impl ImageLoader {
+    /// Usual new. Starts the worker threads.
+    pub fn new() -> Self {
+        let cache_dir_opt = match get_cache_dir() {
+            Ok(dir) => Some(dir.join(IMAGE_CACHE_DIR_NAME)),
+            Err(e) => {
+                log::warn!("No image cache: {:?}", e);
+                None
+            }
+        };
+        let (job_send, job_recv) = crossbeam_channel::unbounded::<ImageJob>();
+        let (done_send, done_recv) = crossbeam_channel::unbounded();
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1)
+            .min(MAX_IMAGE_WORKERS);
+        for _ in 0..workers {
+            let job_recv = job_recv.clone();
+            let done_send: Sender<ImageDone> = done_send.clone();
+            let cache_dir_opt = cache_dir_opt.clone();
+            //  Workers exit when the loader is dropped.
+            std::thread::spawn(move || {
+                while let Ok(job) = job_recv.recv() {
+                    let (name, result) = decode_job(job.source, cache_dir_opt.as_deref());
+                    if done_send
+                        .send(ImageDone {
+                            name,
+                            target: job.target,
+                            result,
+                        })
+                        .is_err()
+                    {
+                        break; // loader is gone
+                    }
+                }
+            });
+        }
+        ImageLoader {
+            job_send,
+            done_recv,
+            pending: 0,
+        }
+    }
+
+    /// Load an image file. Shows the placeholder until loaded.
+    /// If the image can't be loaded, the placeholder stays, and the handle's error says why.
+    pub fn load_file(&mut self, path: &Path, placeholder: TextureId) -> ImageHandle {
+        self.start(ImageSource::File(path.to_path_buf()), placeholder)
+    }
+
+    /// Load an image from the web. Cached copies are used when offline.
+    pub fn load_url(
+        &mut self,
+        url: &str,
+        fetcher: &Arc<CachedFetcher>,
+        placeholder: TextureId,
+    ) -> ImageHandle {
+        self.start(
+            ImageSource::Url(url.to_string(), Arc::clone(fetcher)),
+            placeholder,
+        )
+    }
+
+    /// Load an image from the contents of an image file.
+    pub fn load_bytes(
+        &mut self,
+        name: &str,
+        bytes: Vec<u8>,
+        placeholder: TextureId,
+    ) -> ImageHandle {
+        self.start(ImageSource::Bytes(name.to_string(), bytes), placeholder)
+    }
+
+    /// Queue a job.
+    fn start(&mut self, source: ImageSource, placeholder: TextureId) -> ImageHandle {
+        let target = ImageHandle::new(placeholder);
+        self.pending += 1;
+        self.job_send
+            .send(ImageJob {
+                source,
+                target: target.clone(),
+            })
+            .expect("Image workers have exited"); // workers only exit when we are dropped
+        target
+    }
+
+    /// Create textures for finished images. Call once per frame, on the main thread.
+    /// Returns true if images are still loading, so the caller can keep redrawing.
+    pub fn update(
+        &mut self,
+        egui_routine: &mut EguiRenderRoutine,
+        renderer: &Arc<Renderer>,
+    ) -> bool {
+        while let Ok(done) = self.done_recv.try_recv() {
+            self.pending -= 1;
+            match done.result {
+                Ok(image) => done.target.set(create_texture(
+                    &image.rgba,
+                    image.dimensions,
+                    &done.name,
+                    egui_routine,
+                    renderer,
+                )),
+                Err(e) => {
+                    log::error!("Unable to load image {}: {:?}", done.name, e);
+                    done.target.fail(format!("{:#}", e));
+                }
+            }
+        }
+        self.pending > 0
+    }
+}
+
+impl Default for ImageLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Do one job, on a worker thread.
+fn decode_job(
+    source: ImageSource,
+    cache_dir_opt: Option<&Path>,
+) -> (String, Result<DecodedImage, Error>) {
+    match source {
+        ImageSource::File(path) => {
+            let name = path.to_string_lossy().to_string();
+            let result = std::fs::read(&path)
+                .with_context(|| format!("Unable to read image file {:?}", path))
+                .and_then(|bytes| decode_cached(&bytes, cache_dir_opt));
+            (name, result)
+        }
+        ImageSource::Bytes(name, bytes) => {
+            let result = decode_cached(&bytes, cache_dir_opt);
+            (name, result)
+        }
+        ImageSource::Url(url, fetcher) => {
+            let result = fetcher
+                .fetch(&url)
+                .and_then(|bytes| decode_cached(&bytes, cache_dir_opt));
+            (url, result)
+        }
+    }
+}
+
+/// Decode, using the disk cache if possible.
+//  Cache trouble is never fatal. The image is just decoded again.
+fn decode_cached(bytes: &[u8], cache_dir_opt: Option<&Path>) -> Result<DecodedImage, Error> {
+    let cache_file_opt = cache_dir_opt
+        .map(|dir| dir.join(format!("{:x}.{}", md5::compute(bytes), IMAGE_CACHE_SUFFIX)));
+    if let Some(cache_file) = &cache_file_opt {
+        if let Ok(cached) = std::fs::read(cache_file) {
+            match read_cache_entry(&cached) {
+                Ok(image) => {
+                    //  Just used, so last to go.
+                    let _ = std::fs::File::options()
+                        .write(true)
+                        .open(cache_file)
+                        .and_then(|f| f.set_modified(SystemTime::now()));
+                    return Ok(image);
+                }
+                Err(e) => log::warn!("Bad image cache file {:?}: {:?}", cache_file, e),
+            }
+        }
+    }
+    //  Any format the image crate can read, converted to RGBA.
+    let image = image::load_from_memory(bytes).context("Unable to decode image")?;
+    let rgba = image.to_rgba8();
+    let decoded = DecodedImage {
+        dimensions: rgba.dimensions(),
+        rgba: rgba.into_raw(),
+    };
+    if let (Some(cache_file), Some(cache_dir)) = (&cache_file_opt, cache_dir_opt) {
+        if let Err(e) = write_cache_entry(cache_file, &decoded) {
+            log::warn!("Unable to write image cache file {:?}: {:?}", cache_file, e);
+        }
+        if let Err(e) = trim_cache(cache_dir, IMAGE_CACHE_MAX_SIZE) {
+            log::warn!("Unable to trim image cache {:?}: {:?}", cache_dir, e);
+        }
+    }
+    Ok(decoded)
+}
+
+/// Delete the least recently used cache files until the cache fits.
+/// Also delete temporary files left by a crash.
+//  Other workers may be trimming too, so files already gone are not an error.
+//  Other workers may be writing too, so only old temporary files are deleted.
+fn trim_cache(cache_dir: &Path, max_size: u64) -> Result<(), Error> {
+    let mut files = Vec::new(); // (last used, size, path)
+    let mut total: u64 = 0;
+    for entry in std::fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        let Some(ext) = path.extension().and_then(|ext| ext.to_str()) else {
+            continue;
+        };
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        if ext == IMAGE_CACHE_SUFFIX {
+            total += metadata.len();
+            files.push((metadata.modified()?, metadata.len(), path));
+        } else if ext.starts_with(TEMP_FILE_SUFFIX) {
+            let age = metadata.modified()?.elapsed().unwrap_or_default();
+            if age > STALE_TEMP_FILE_AGE {
+                match std::fs::remove_file(&path) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if total <= max_size {
+        return Ok(());
+    }
+    files.sort();
+    for (_, size, path) in files {
+        if total <= max_size {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => total -= size,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => total -= size,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(())
+}
+
+/// Cache file format: width and height as little-endian u32, then RGBA bytes.
+fn read_cache_entry(cached: &[u8]) -> Result<DecodedImage, Error> {
+    if cached.len() < 8 {
+        return Err(anyhow!("Too short"));
+    }
+    let width = u32::from_le_bytes(cached[0..4].try_into()?);
+    let height = u32::from_le_bytes(cached[4..8].try_into()?);
+    let rgba = cached[8..].to_vec();
+    if rgba.len() != width as usize * height as usize * 4 {
+        return Err(anyhow!("Wrong size for {} x {} image", width, height));
+    }
+    Ok(DecodedImage {
+        dimensions: (width, height),
+        rgba,
+    })
+}
+
+/// Write cache file. Written whole, so other workers never see a partial file.
+fn write_cache_entry(cache_file: &Path, image: &DecodedImage) -> Result<(), Error> {
+    if let Some(dir) = cache_file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut content = Vec::with_capacity(8 + image.rgba.len());
+    content.extend_from_slice(&image.dimensions.0.to_le_bytes());
+    content.extend_from_slice(&image.dimensions.1.to_le_bytes());
+    content.extend_from_slice(&image.rgba);
+    write_atomic(cache_file, &content)
+}
+
+#[test]
+fn test_image_cache_entry() {
+    //  A 2x1 grayscale PNG would panic in the old as_rgba8 path. Make one and decode it.
+    let gray = image::GrayImage::from_raw(2, 1, vec![0, 255]).unwrap();
+    let mut png = std::io::Cursor::new(Vec::new());
+    image::DynamicImage::ImageLuma8(gray)
+        .write_to(&mut png, image::ImageOutputFormat::Png)
+        .unwrap();
+    let cache_dir = std::env::temp_dir().join(format!("imageloader-test-{}", std::process::id()));
+    let decoded = decode_cached(png.get_ref(), Some(&cache_dir)).unwrap();
+    assert_eq!(decoded.dimensions, (2, 1));
+    assert_eq!(decoded.rgba, vec![0, 0, 0, 255, 255, 255, 255, 255]);
+    //  Second time comes from the cache.
+    let cached = decode_cached(png.get_ref(), Some(&cache_dir)).unwrap();
+    assert_eq!(cached.rgba, decoded.rgba);
+    //  Trimming to fit. A stale temporary file from a crash goes, a new one stays.
+    let stale_file = cache_dir.join("crashed.tmpThreadId(3)");
+    std::fs::write(&stale_file, "partial").unwrap();
+    std::fs::File::options()
+        .write(true)
+        .open(&stale_file)
+        .unwrap()
+        .set_modified(SystemTime::now() - 2 * STALE_TEMP_FILE_AGE)
+        .unwrap();
+    let new_file = cache_dir.join("writing.1-0.tmp");
+    std::fs::write(&new_file, "partial").unwrap();
+    trim_cache(&cache_dir, 1000).unwrap();
+    assert!(!stale_file.exists());
+    std::fs::remove_file(&new_file).unwrap();
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+    trim_cache(&cache_dir, 0).unwrap();
+    assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 0);
+    let _ = std::fs::remove_dir_all(&cache_dir);
+}
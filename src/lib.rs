@@ -8,19 +8,46 @@
 //  That is checked with Tracy, as being under span "GUI".
 //
 mod basicintl;
+mod crashreport;
+mod dialogbuilder;
+mod docklayer;
 mod guistate;
 mod guiutil;
+mod httpfetch;
+mod iconregistry;
+mod imageloader;
+mod logconsole;
+mod logcontrol;
+mod logfiles;
 mod menunone;
+mod metrics;
+mod menustack;
+mod modalstack;
 mod navarrows; // a widget
 mod piemenu;
+mod statemachine;
 mod statgraph; // a widget // a widget
+mod uiscale;
+mod windowlayout;
 
 use core::any::Any;
 use core::cell::RefCell;
 use std::rc::Rc;
 
+//  Derived code names this crate as ::libui, which has to work inside it too.
+extern crate self as libui;
+
+/// Derive macros for the boilerplate parts of windows and menu groups
+pub use libui_derive::{GuiWindow, MenuGroup};
+
 /// Internationalization
 pub use basicintl::Dictionary;
+/// Crash reports
+pub use crashreport::{install_crash_handler, CrashReport, CrashReportDialog};
+/// Dialogs assembled from parts
+pub use dialogbuilder::{ConfirmRequest, DialogBuilder, DialogResponse};
+/// Docking windows at screen edges
+pub use docklayer::{DockEdge, DockLayer};
 pub use guistate::panic_dialog;
 pub use guistate::{
     AppState, CommonState, ExecutableVersion, GuiAssets, GuiCommonEvent, GuiParams, GuiState,
@@ -28,45 +55,120 @@ pub use guistate::{
 };
 /// Utility functions.
 pub use guiutil::{
-    get_asset_dir, get_cache_dir, get_executable_name, get_log_file_name, load_canned_icon,
-    load_image, set_default_styles,
+    get_asset_dir, get_cache_dir, get_config_dir, get_executable_name, get_log_dir,
+    load_canned_icon, load_image, move_aside, set_default_styles, write_atomic,
+};
+/// Web content
+pub use httpfetch::{CachedFetcher, FetchResult, HttpFetcher, LocalFileFetcher, UreqFetcher};
+/// Icons by name
+pub use iconregistry::{Icon, IconRegistry};
+/// Background image loading
+pub use imageloader::{ImageHandle, ImageLoader};
+/// Log console
+pub use logconsole::{LogConsole, LogFilter, LogRecord};
+/// Log levels, changed while running
+pub use logcontrol::{LogController, LogSettings};
+/// Log files, by session, and diagnostics
+pub use logfiles::{LogFiles, RotatingLogWriter};
+/// Menu group navigation
+pub use menustack::{MenuChange, MenuTransition};
+/// Performance metrics
+pub use metrics::{
+    CounterHandle, GaugeHandle, Metric, MetricInfo, MetricKind, MetricsRegistry, MetricsSender,
+    METRICS_HISTORY_LENGTH, METRICS_INTERVAL,
 };
+/// Modal dialogs
+pub use modalstack::{MessageDialog, ModalButton, ModalDialog, ModalDialogLink};
 /// Widgets
 pub use navarrows::{NavAction, NavArrows};
 pub use piemenu::PieMenu;
-pub use statgraph::StatGraph;
+pub use statgraph::{SeriesSummary, StatGraph};
+/// Application state machines
+pub use statemachine::{MenuHost, StateMachine};
+/// UI scaling
+pub use uiscale::{UiScale, UiScaleSettings, UI_SCALE_MAX, UI_SCALE_MIN};
+/// Window layout persistence
+pub use windowlayout::{WindowFactory, WindowLayout};
 
 //  Traits
+/// Downcasting, for windows and menu groups.
+//  Implemented for every 'static type, so nobody writes it by hand.
+//  Windows and menu groups have to be 'static anyway, to go in a link.
+//  Ones built from borrowed images should hold them as Image<'static>.
+//  Call it on the window, not on the Ref or Rc holding it. Those are
+//  'static types too, and would be what gets downcast.
+pub trait AsAny {
+    /// For downcasting.
+    fn as_any(&self) -> &dyn Any;
+    /// For downcasting.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// The part of a MenuGroup which is the same for all of them. Use #[derive(MenuGroup)].
+pub trait MenuGroupCore {
+    /// Name for debug and logging purposes only
+    fn get_name(&self) -> &'static str;
+}
+
 /// A group of menus. Libui user sets what menus are to be shown.
-pub trait MenuGroup {
+pub trait MenuGroup: MenuGroupCore + AsAny {
     /// Draw the item. Called every frame.
     fn draw(&mut self, state: &mut CommonState) -> bool; // returns true if menu is in use
     ///  Pass event to a GUI item. Override to get events.
     fn pass_event(&mut self, _state: &mut CommonState, _event: &SendAnyBoxed) {}
-    /// Name for debug and logging purposes only
-    fn get_name(&self) -> &'static str;
-    /// For downcasting. Little used, may be removed.
-    fn as_any(&self) -> &dyn Any;
-    /// For downcasting.
-    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Called when the group is shown, after a push, set, or pop of the group above.
+    fn on_enter(&mut self, _state: &mut CommonState) {}
+    /// Called when the group stops being shown, however that happened.
+    fn on_exit(&mut self, _state: &mut CommonState) {}
+    /// Escape or the mouse back button. Return false to stay, having handled it.
+    fn on_back(&mut self, _state: &mut CommonState) -> bool {
+        true
+    }
+}
+
+/// The part of a GuiWindow which is the same for all of them. Use #[derive(GuiWindow)].
+pub trait GuiWindowCore {
+    /// GetID  of window.
+    fn get_id(&self) -> egui::Id;
+    /// Retain this window? False when the window has closed itself.
+    fn retain(&self) -> bool {
+        true
+    }
+    /// Title, for the Window menu. Windows without one are not listed.
+    fn get_title(&self) -> Option<&str> {
+        None
+    }
 }
 
 /// A GUI window
-pub trait GuiWindow {
+pub trait GuiWindow: GuiWindowCore + AsAny {
     /// Draw the item. Called every frame.
     fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState); // called every frame
-    /// Retain this window? Override and set to false for a window to close itself.
-    fn retain(&self) -> bool {
-        true
-    } // override and set to false when done
     ///  Pass event to a GUI item. Override to get events.
     fn pass_event(&mut self, _state: &mut CommonState, _event: &SendAnyBoxed) {}
-    /// GetID  of window.
-    fn get_id(&self) -> egui::Id;
-    /// For downcasting.
-    fn as_any(&self) -> &dyn Any;
-    /// For downcasting.
-    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Title for a dock tab. Windows which can be docked override this to return Some.
+    fn dock_title(&self) -> Option<&str> {
+        None
+    }
+    /// Draw the contents, without the window around them. Used when docked.
+    /// Dockable windows should draw their floating window with this too, so both look the same.
+    fn draw_contents(&mut self, _ui: &mut egui::Ui, _state: &mut CommonState) {}
+    /// Called when the window is added to the open windows.
+    fn on_open(&mut self, _state: &mut CommonState) {}
+    /// Called when the window is removed from the open windows, however it was closed.
+    fn on_close(&mut self, _state: &mut CommonState) {}
+    /// Called when the window comes to the front.
+    fn on_focus(&mut self, _state: &mut CommonState) {}
 }
 
 pub type GuiWindowLink = Rc<RefCell<dyn GuiWindow>>;
@@ -0,0 +1,419 @@
+//! logconsole.rs -- the log console window.
+//
+//  Log records sent to the GUI are kept whole, in a ring buffer,
+//  so the newest are kept and memory use is bounded.
+//  The console filters them by level, target and text, colors them
+//  by level, and can copy or export what is shown.
+//  Long messages are cut off in the list. Click one to see all of it.
+//
+//  Animats
+//  October 2026
+//
+use crate::{t, CommonState, GuiWindow, GuiWindowLink};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+/// Most records kept. Older ones are dropped.
+const LOG_CONSOLE_LIMIT: usize = 2000;
+/// Tallest the full message view gets, in points.
+const DETAIL_MAX_HEIGHT: f32 = 150.0;
+/// Width of the target and search fields, in points.
+const FILTER_FIELD_WIDTH: f32 = 100.0;
+/// Time format for log lines.
+const LOG_TIME_FORMAT: &[time::format_description::FormatItem] =
+    time::macros::format_description!("[hour]:[minute]:[second].[subsecond digits:3]");
+
+/// One log record, all of it.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// Severity
+    pub level: log::Level,
+    /// Log target, usually the module path
+    pub target: String,
+    /// Module, if known
+    pub module: Option<String>,
+    /// When logged
+    pub timestamp: SystemTime,
+    /// The message, not cut off
+    pub message: String,
+}
+
+impl LogRecord {
+    /// A record made here, not by the logging system.
+    pub fn new(level: log::Level, target: &str, message: String) -> Self {
+        LogRecord {
+            level,
+            target: target.to_string(),
+            module: None,
+            timestamp: SystemTime::now(),
+            message,
+        }
+    }
+
+    /// Copy of a record from the logging system.
+    pub fn from_record(record: &log::Record<'_>) -> Self {
+        LogRecord {
+            level: record.level(),
+            target: record.target().to_string(),
+            module: record.module_path().map(|s| s.to_string()),
+            timestamp: SystemTime::now(),
+            message: record.args().to_string(),
+        }
+    }
+
+    /// Time of day, local time if known, else UTC, for display.
+    pub fn time_string(&self) -> String {
+        let time = time::OffsetDateTime::from(self.timestamp);
+        time::UtcOffset::local_offset_at(time)
+            .map(|offset| time.to_offset(offset))
+            .unwrap_or(time)
+            .format(LOG_TIME_FORMAT)
+            .unwrap_or_default()
+    }
+
+    /// As one line of text, for copying and export.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{} [{}] ({}): {}",
+            self.time_string(),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+/// Log records, oldest first. When full, the oldest are dropped.
+pub struct LogBuffer {
+    records: VecDeque<(u64, LogRecord)>, // (serial number, record)
+    limit: usize,                        // most records kept
+    next_serial: u64,                    // serial number of next record
+}
+
+impl LogBuffer {
+    /// Usual new. Keeps at least one record.
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        LogBuffer {
+            records: VecDeque::with_capacity(limit),
+            limit,
+            next_serial: 0,
+        }
+    }
+
+    /// Add a record, dropping the oldest if full.
+    pub fn push(&mut self, record: LogRecord) {
+        while self.records.len() >= self.limit {
+            self.records.pop_front();
+        }
+        self.records.push_back((self.next_serial, record));
+        self.next_serial += 1;
+    }
+
+    /// Number of records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// True if empty.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Drop all records.
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// The records, with serial numbers, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &(u64, LogRecord)> {
+        self.records.iter()
+    }
+}
+
+/// Which records the console shows.
+#[derive(Debug, Clone)]
+pub struct LogFilter {
+    /// Least severe level shown
+    pub level: log::LevelFilter,
+    /// Target must contain this
+    pub target: String,
+    /// Message must contain this, ignoring case. Lower case.
+    search: String,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        LogFilter {
+            level: log::LevelFilter::Trace,
+            target: String::new(),
+            search: String::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    /// Message must contain this, ignoring case.
+    pub fn set_search(&mut self, search: &str) {
+        self.search = search.to_lowercase();
+    }
+
+    /// Does this record pass?
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        record.level <= self.level
+            && record.target.contains(self.target.as_str())
+            && contains_ignoring_case(&record.message, &self.search)
+    }
+}
+
+/// Does the text contain this lower case string, ignoring case?
+//  Called for every record, every frame, so nothing is allocated.
+fn contains_ignoring_case(text: &str, lower: &str) -> bool {
+    lower.is_empty()
+        || text.char_indices().any(|(pos, _)| {
+            let mut rest = text[pos..].chars().flat_map(char::to_lowercase);
+            lower.chars().all(|c| rest.next() == Some(c))
+        })
+}
+
+/// The log console.
+//  There is only one, kept in CommonState, so records are kept while it is closed.
+#[derive(GuiWindow)]
+pub struct LogConsole {
+    #[gui(title)]
+    title: String, // title of window
+    #[gui(id)]
+    id: egui::Id, // unique ID
+    #[gui(is_open)]
+    is_open: bool, // true if open
+    records: LogBuffer,    // the log
+    filter: LogFilter,     // what is shown
+    search: String,        // search text, as typed
+    selected: Option<u64>, // serial number of record shown in full
+}
+
+impl LogConsole {
+    /// Name, for remembering the window layout.
+    pub const WINDOW_NAME: &'static str = "Messages";
+
+    /// Usual new
+    pub fn new(id: &str, title: &str) -> Self {
+        LogConsole {
+            title: title.to_string(),
+            id: egui::Id::new(id),
+            is_open: true,
+            records: LogBuffer::new(LOG_CONSOLE_LIMIT),
+            filter: LogFilter::default(),
+            search: String::new(),
+            selected: None,
+        }
+    }
+
+    /// Open the log console. Also used to reopen it next session.
+    pub fn create(state: &mut CommonState) -> GuiWindowLink {
+        state.log_console.borrow_mut().is_open = true;
+        state.log_console.clone()
+    }
+
+    /// Add a record.
+    pub fn add_record(&mut self, record: LogRecord) {
+        self.records.push(record);
+    }
+
+    /// The records shown, as text, one per line.
+    fn shown_text(&self) -> String {
+        let mut text = String::new();
+        for (_, record) in self.records.iter().filter(|(_, r)| self.filter.matches(r)) {
+            let _ = writeln!(text, "{}", record.to_line());
+        }
+        text
+    }
+
+    /// Filter controls and buttons.
+    fn draw_toolbar(&mut self, ui: &mut egui::Ui, state: &mut CommonState) {
+        ui.horizontal(|ui| {
+            ui.label(t!("menu.log.level", state.get_lang()));
+            egui::ComboBox::from_id_salt(self.id.with("level"))
+                .selected_text(self.filter.level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in log::LevelFilter::iter().skip(1) {
+                        ui.selectable_value(&mut self.filter.level, level, level.as_str());
+                    }
+                });
+            ui.label(t!("menu.log.target", state.get_lang()));
+            ui.add(
+                egui::TextEdit::singleline(&mut self.filter.target)
+                    .desired_width(FILTER_FIELD_WIDTH),
+            );
+            ui.label(t!("menu.log.search", state.get_lang()));
+            if ui
+                .add(egui::TextEdit::singleline(&mut self.search).desired_width(FILTER_FIELD_WIDTH))
+                .changed()
+            {
+                self.filter.set_search(&self.search);
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button(t!("menu.log.copy", state.get_lang())).clicked() {
+                if let Err(e) = state.copy_to_clipboard(&self.shown_text()) {
+                    log::error!("Unable to copy log to clipboard: {:?}", e);
+                }
+            }
+            if ui.button(t!("menu.log.export", state.get_lang())).clicked() {
+                let text = self.shown_text();
+                state.save_file_async(
+                    t!("menu.log.export", state.get_lang()),
+                    "log.txt",
+                    move |path| Ok(std::fs::write(path, text)?),
+                );
+            }
+            if ui.button(t!("menu.log.clear", state.get_lang())).clicked() {
+                self.records.clear();
+                self.selected = None;
+            }
+        });
+    }
+
+    /// The selected record, in full.
+    fn draw_detail(&self, ui: &mut egui::Ui) {
+        let Some(selected) = self.selected else {
+            return;
+        };
+        let Some((_, record)) = self.records.iter().find(|(serial, _)| *serial == selected) else {
+            return; // dropped from the buffer
+        };
+        ui.separator();
+        ui.label(format!(
+            "{} [{}] {} {}",
+            record.time_string(),
+            record.level,
+            record.target,
+            record.module.as_deref().unwrap_or("")
+        ));
+        egui::ScrollArea::vertical()
+            .id_salt(self.id.with("detail"))
+            .max_height(DETAIL_MAX_HEIGHT)
+            .show(ui, |ui| {
+                ui.add(egui::Label::new(record.message.as_str()).wrap());
+            });
+    }
+}
+
+impl GuiWindow for LogConsole {
+    /// Draw the console window.
+    fn draw(&mut self, ctx: &egui::Context, state: &mut CommonState) {
+        if self.is_open {
+            let mut not_cancelled = true;
+            let window = egui::containers::Window::new(self.title.as_str())
+                .id(self.id)
+                .open(&mut not_cancelled);
+            window.show(ctx, |ui| self.draw_contents(ui, state));
+            if !not_cancelled {
+                self.is_open = false;
+            } // do here to avoid borrow clash
+        }
+    }
+
+    /// Toolbar, then the records, scrolling, then the selected record in full.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, state: &mut CommonState) {
+        self.draw_toolbar(ui, state);
+        ui.separator();
+        let shown: Vec<&(u64, LogRecord)> = self
+            .records
+            .iter()
+            .filter(|(_, r)| self.filter.matches(r))
+            .collect();
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        let mut clicked = None;
+        egui::ScrollArea::both()
+            .id_salt(self.id.with("records"))
+            .stick_to_bottom(true)
+            .auto_shrink([false, true])
+            .show_rows(ui, row_height, shown.len(), |ui, row_range| {
+                for (serial, record) in &shown[row_range] {
+                    let color = level_color(ui.visuals(), record.level);
+                    let text = egui::RichText::new(record.to_line()).color(color);
+                    let text = if self.selected == Some(*serial) {
+                        text.strong()
+                    } else {
+                        text
+                    };
+                    if ui
+                        .add(
+                            egui::Label::new(text)
+                                .truncate()
+                                .sense(egui::Sense::click()),
+                        )
+                        .clicked()
+                    {
+                        clicked = Some(*serial);
+                    }
+                }
+            });
+        if let Some(serial) = clicked {
+            //  Clicking the selected record again hides it.
+            self.selected = if self.selected == Some(serial) {
+                None
+            } else {
+                Some(serial)
+            };
+        }
+        self.draw_detail(ui);
+    }
+
+    /// Can be docked.
+    fn dock_title(&self) -> Option<&str> {
+        Some(&self.title)
+    }
+}
+
+/// Color for a log level.
+fn level_color(visuals: &egui::Visuals, level: log::Level) -> egui::Color32 {
+    match level {
+        log::Level::Error => visuals.error_fg_color,
+        log::Level::Warn => visuals.warn_fg_color,
+        log::Level::Info => visuals.text_color(),
+        log::Level::Debug | log::Level::Trace => visuals.weak_text_color(),
+    }
+}
+
+#[test]
+fn test_log_buffer() {
+    let mut buffer = LogBuffer::new(3);
+    for n in 0..5 {
+        buffer.push(LogRecord::new(
+            log::Level::Info,
+            "test",
+            format!("Message {}", n),
+        ));
+    }
+    //  Oldest dropped.
+    assert_eq!(buffer.len(), 3);
+    let (serial, first) = buffer.iter().next().unwrap();
+    assert_eq!(*serial, 2);
+    assert_eq!(first.message, "Message 2");
+    //  Filters
+    let record = LogRecord::new(
+        log::Level::Debug,
+        "libui::guistate",
+        "Window OPENED".to_string(),
+    );
+    let mut filter = LogFilter::default();
+    assert!(filter.matches(&record));
+    filter.set_search("OpEnEd"); // ignores case
+    assert!(filter.matches(&record));
+    filter.set_search("closed");
+    assert!(!filter.matches(&record));
+    filter.set_search("");
+    filter.target = "libui".to_string();
+    assert!(filter.matches(&record));
+    filter.level = log::LevelFilter::Info;
+    assert!(!filter.matches(&record));
+    assert!(contains_ignoring_case("Straße Ärger", "ärger"));
+    assert!(!contains_ignoring_case("short", "shorter"));
+    //  Zero limit still keeps one.
+    let mut buffer = LogBuffer::new(0);
+    buffer.push(record);
+    assert_eq!(buffer.len(), 1);
+}
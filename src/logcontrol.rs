@@ -0,0 +1,272 @@
+//! logcontrol.rs -- log levels, changeable while running.
+//
+//  Log records go to a file and to the GUI log console.
+//  Each has its own level, and modules can have levels of their own,
+//  in place of those, for when one module needs watching.
+//
+//  Once a logger is installed, the logging system owns it, so
+//  the levels are kept in shared settings the logger reads.
+//  The LogController changes them, and saves them as a preference.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::{get_config_dir, write_atomic};
+use crate::logfiles::LogFiles;
+use crate::{LogRecord, MessageLogger, SendAnyBoxed};
+use anyhow::{anyhow, Context, Error};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use simplelog::SharedLogger;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Log settings file, in the config directory.
+pub(crate) const LOG_SETTINGS_FILE_NAME: &str = "log_settings.json";
+
+/// Log levels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogSettings {
+    /// Level for the log console
+    pub gui_level: LevelFilter,
+    /// Level for the log file
+    pub file_level: LevelFilter,
+    /// (module, level). For records from the module, this level replaces both of the above.
+    pub module_levels: Vec<(String, LevelFilter)>,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            gui_level: LevelFilter::Error,
+            file_level: LevelFilter::Warn,
+            module_levels: Vec::new(),
+        }
+    }
+}
+
+impl LogSettings {
+    /// Level for a target, given the level for the destination.
+    /// The most specific module match wins.
+    pub fn level_for(&self, target: &str, destination_level: LevelFilter) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .filter(|(module, _)| {
+                target == module
+                    || (target.starts_with(module.as_str())
+                        && target[module.len()..].starts_with("::"))
+            })
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(destination_level)
+    }
+
+    /// Level for one module, or None to go back to the usual levels.
+    /// A module which already has a level keeps its place.
+    pub fn set_module_level(&mut self, module: &str, level: Option<LevelFilter>) {
+        match (
+            self.module_levels.iter().position(|(m, _)| m == module),
+            level,
+        ) {
+            (Some(pos), Some(level)) => self.module_levels[pos].1 = level,
+            (None, Some(level)) => self.module_levels.push((module.to_string(), level)),
+            (_, None) => self.module_levels.retain(|(m, _)| m != module),
+        }
+    }
+
+    /// Most verbose level anything wants. Nothing beyond this is even formatted.
+    fn max_level(&self) -> LevelFilter {
+        self.module_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .chain([self.gui_level, self.file_level])
+            .max()
+            .unwrap_or(LevelFilter::Off)
+    }
+
+    /// Read the settings from the config directory. Missing file means defaults.
+    fn load(path: &Path) -> Result<Self, Error> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read log settings file {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Unable to parse log settings file {:?}", path))
+    }
+}
+
+/// Changes log levels while running.
+//  Cheap to clone. All clones control the same loggers.
+#[derive(Debug, Clone, Default)]
+pub struct LogController {
+    settings: Arc<RwLock<LogSettings>>, // shared with the logger
+    path: Option<PathBuf>,              // where settings are saved
+    log_files: Option<LogFiles>,        // where the logs are
+}
+
+impl LogController {
+    /// Start logging, to a new session log file and to the GUI, with the saved levels.
+    /// Can only be done once per program.
+    pub fn init(
+        log_files: LogFiles,
+        send_channel: crossbeam_channel::Sender<SendAnyBoxed>,
+    ) -> Result<Self, Error> {
+        let path = get_config_dir()?.join(LOG_SETTINGS_FILE_NAME);
+        //  Bad settings aren't fatal, but can't be logged until there is a logger.
+        let (settings, settings_error) = match LogSettings::load(&path) {
+            Ok(settings) => (settings, None),
+            Err(e) => (Default::default(), Some(e)),
+        };
+        let controller = LogController {
+            settings: Arc::new(RwLock::new(settings)),
+            path: Some(path),
+            log_files: Some(log_files.clone()),
+        };
+        //  The loggers take everything. Filtering happens here.
+        let file = log_files.start_session()?;
+        let config = simplelog::ConfigBuilder::new()
+            .set_time_format_rfc3339()
+            .build(); // date and time on each line
+        let logger = ControlledLogger {
+            settings: Arc::clone(&controller.settings),
+            file_logger: simplelog::WriteLogger::new(LevelFilter::Trace, config, file),
+            gui_logger: MessageLogger::new_logger(LevelFilter::Trace, send_channel),
+        };
+        log::set_boxed_logger(Box::new(logger))
+            .map_err(|e| anyhow!("Unable to start logging: {:?}", e))?;
+        controller.update_max_level();
+        if let Some(e) = settings_error {
+            log::error!("Using default log levels: {:?}", e);
+        }
+        Ok(controller)
+    }
+
+    /// The log files, if logging has started.
+    pub fn log_files(&self) -> Option<&LogFiles> {
+        self.log_files.as_ref()
+    }
+
+    /// The current settings.
+    pub fn get_settings(&self) -> LogSettings {
+        self.settings.read().unwrap().clone()
+    }
+
+    /// Change all the settings. Takes effect at once.
+    pub fn set_settings(&self, settings: LogSettings) {
+        *self.settings.write().unwrap() = settings;
+        self.update_max_level();
+    }
+
+    /// Level for the log console.
+    pub fn set_gui_level(&self, level: LevelFilter) {
+        self.settings.write().unwrap().gui_level = level;
+        self.update_max_level();
+    }
+
+    /// Level for the log file.
+    pub fn set_file_level(&self, level: LevelFilter) {
+        self.settings.write().unwrap().file_level = level;
+        self.update_max_level();
+    }
+
+    /// Save the settings, for next time.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Log settings have no file"))?;
+        write_atomic(
+            path,
+            serde_json::to_string_pretty(&self.get_settings())?.as_bytes(),
+        )
+        .with_context(|| format!("Unable to write log settings file {:?}", path))
+    }
+
+    /// Tell the logging system what it can skip.
+    fn update_max_level(&self) {
+        log::set_max_level(self.settings.read().unwrap().max_level());
+    }
+}
+
+/// Sends each record to the file and the GUI, if their levels allow.
+struct ControlledLogger {
+    settings: Arc<RwLock<LogSettings>>, // levels, changed by the LogController
+    file_logger: Box<dyn SharedLogger>, // to the log file
+    gui_logger: Box<dyn SharedLogger>,  // to the log console
+}
+
+impl log::Log for ControlledLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        let settings = self.settings.read().unwrap();
+        let target = metadata.target();
+        metadata.level() <= settings.level_for(target, settings.gui_level)
+            || metadata.level() <= settings.level_for(target, settings.file_level)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        //  Levels are read first, so a logger that logs can't deadlock.
+        let (to_file, to_gui) = {
+            let settings = self.settings.read().unwrap();
+            let target = record.target();
+            (
+                record.level() <= settings.level_for(target, settings.file_level),
+                record.level() <= settings.level_for(target, settings.gui_level),
+            )
+        };
+        if to_file || to_gui {
+            crate::crashreport::note_log_line(LogRecord::from_record(record).to_line());
+        }
+        if to_file {
+            self.file_logger.log(record);
+            self.file_logger.flush(); // end of record, where the file can rotate
+        }
+        if to_gui {
+            self.gui_logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.file_logger.flush();
+        self.gui_logger.flush();
+    }
+}
+
+#[test]
+fn test_log_settings() {
+    let mut settings = LogSettings::default();
+    settings.set_module_level("libui", Some(LevelFilter::Info));
+    settings.set_module_level("libui::httpfetch", Some(LevelFilter::Debug));
+    settings.set_module_level("libui::httpfetch", Some(LevelFilter::Trace)); // replaces
+    settings.set_module_level("libui::guistate", Some(LevelFilter::Trace));
+    settings.set_module_level("libui::guistate", None); // usual levels again
+    assert_eq!(
+        settings.module_levels,
+        vec![
+            ("libui".to_string(), LevelFilter::Info),
+            ("libui::httpfetch".to_string(), LevelFilter::Trace)
+        ]
+    );
+    assert_eq!(
+        settings.level_for("ui_mock", LevelFilter::Warn),
+        LevelFilter::Warn
+    );
+    assert_eq!(
+        settings.level_for("libui::guistate", LevelFilter::Warn),
+        LevelFilter::Info
+    );
+    assert_eq!(
+        settings.level_for("libui::httpfetch", LevelFilter::Warn),
+        LevelFilter::Trace
+    );
+    assert_eq!(
+        settings.level_for("libuix", LevelFilter::Warn),
+        LevelFilter::Warn
+    ); // not a submodule
+    assert_eq!(settings.max_level(), LevelFilter::Trace);
+    //  Saved and restored.
+    let json = serde_json::to_string(&settings).unwrap();
+    let read_back: LogSettings = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_back, settings);
+}
@@ -0,0 +1,293 @@
+//! logfiles.rs -- log files, one per session, and diagnostics bundles.
+//
+//  Each run of the program logs to its own file, named for the time
+//  it started and the process ID, so the log of a session which crashed
+//  is still there next time, and two sessions started together don't
+//  share a file. The last few sessions are kept and older ones deleted.
+//  A session log which grows too big is rotated, keeping one previous part.
+//  Rotation happens between log records, never within one.
+//
+//  For bug reports, the recent logs, system information and
+//  preferences can be zipped into one file.
+//
+//  Animats
+//  October 2026
+//
+use crate::crashreport::{CRASH_REPORT_FILE_NAME, LAST_CRASH_REPORT_FILE_NAME};
+use crate::guiutil::get_config_dir;
+use anyhow::{Context, Error};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Log file names are this, the session start time, the process ID, and the suffix.
+const LOG_FILE_PREFIX: &str = "log-";
+/// Suffix of log files.
+const LOG_FILE_SUFFIX: &str = ".txt";
+/// Sessions kept, counting the current one.
+pub const DEFAULT_MAX_SESSIONS: usize = 5;
+/// Size at which a session log is rotated.
+pub const DEFAULT_MAX_LOG_SIZE: u64 = 10_000_000;
+/// Session start time, in file names. Sorts by time.
+const SESSION_TIME_FORMAT: &[time::format_description::FormatItem] = time::macros::format_description!(
+    "[year][month][day]-[hour][minute][second]-[subsecond digits:3]"
+);
+/// Preference files of libui, in the config directory, for diagnostics.
+const LIBUI_PREFERENCE_FILES: &[&str] = &[
+    crate::uiscale::UI_SCALE_FILE_NAME,
+    crate::logcontrol::LOG_SETTINGS_FILE_NAME,
+    crate::windowlayout::LAYOUT_FILE_NAME,
+];
+
+/// The log files in a directory.
+#[derive(Debug, Clone)]
+pub struct LogFiles {
+    dir: PathBuf,        // where the logs are
+    max_sessions: usize, // sessions kept
+    max_size: u64,       // rotate at this size
+}
+
+impl LogFiles {
+    /// Logs in this directory, with the default limits.
+    pub fn new(dir: &Path) -> Self {
+        LogFiles {
+            dir: dir.to_path_buf(),
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            max_size: DEFAULT_MAX_LOG_SIZE,
+        }
+    }
+
+    /// Change the limits.
+    pub fn with_limits(self, max_sessions: usize, max_size: u64) -> Self {
+        LogFiles {
+            max_sessions: max_sessions.max(1),
+            max_size,
+            ..self
+        }
+    }
+
+    /// Start the log for this session. Logs of old sessions beyond the limit are deleted.
+    pub fn start_session(&self) -> Result<RotatingLogWriter, Error> {
+        let stamp = time::OffsetDateTime::now_utc().format(SESSION_TIME_FORMAT)?;
+        let names = self.log_file_names()?;
+        for name in old_sessions(&names, self.max_sessions - 1) {
+            let path = self.dir.join(name);
+            if let Err(e) = std::fs::remove_file(&path) {
+                //  Not fatal, and there's no logger yet.
+                eprintln!("Unable to remove old log file {:?}: {:?}", path, e);
+            }
+        }
+        let path = self.dir.join(format!(
+            "{}{}-{}{}",
+            LOG_FILE_PREFIX,
+            stamp,
+            std::process::id(),
+            LOG_FILE_SUFFIX
+        ));
+        RotatingLogWriter::new(path, self.max_size)
+    }
+
+    /// Log files in the directory, by name, oldest session first.
+    fn log_file_names(&self) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)
+            .with_context(|| format!("Unable to read log directory {:?}", self.dir))?
+        {
+            let name = entry?.file_name().to_string_lossy().to_string();
+            if session_of(&name).is_some() {
+                names.push(name);
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Log files of the sessions still kept, oldest first.
+    pub fn recent_logs(&self) -> Result<Vec<PathBuf>, Error> {
+        Ok(self
+            .log_file_names()?
+            .iter()
+            .map(|name| self.dir.join(name))
+            .collect())
+    }
+
+    /// Zip the recent logs and crash reports, the system information, and the preferences into one file.
+    /// Preference files are named relative to the config directory, and added to libui's own.
+    /// Missing preference files are skipped.
+    pub fn collect_diagnostics(
+        &self,
+        out: &Path,
+        system_info: &[String],
+        preference_files: &[&str],
+    ) -> Result<(), Error> {
+        let file = File::create(out)
+            .with_context(|| format!("Unable to create diagnostics file {:?}", out))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("system_info.txt", options)?;
+        for line in system_info {
+            writeln!(zip, "{}", line)?;
+        }
+        for path in self.recent_logs()? {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            zip.start_file(format!("logs/{}", name), options)?;
+            std::io::copy(&mut File::open(&path)?, &mut zip)
+                .with_context(|| format!("Unable to read log file {:?}", path))?;
+        }
+        for name in [CRASH_REPORT_FILE_NAME, LAST_CRASH_REPORT_FILE_NAME] {
+            let path = self.dir.join(name);
+            if path.exists() {
+                zip.start_file(format!("logs/{}", name), options)?;
+                std::io::copy(&mut File::open(&path)?, &mut zip)
+                    .with_context(|| format!("Unable to read crash report {:?}", path))?;
+            }
+        }
+        let config_dir = get_config_dir()?;
+        for name in LIBUI_PREFERENCE_FILES.iter().chain(preference_files) {
+            let path = config_dir.join(name);
+            if path.exists() {
+                zip.start_file(format!("preferences/{}", name), options)?;
+                std::io::copy(&mut File::open(&path)?, &mut zip)
+                    .with_context(|| format!("Unable to read preference file {:?}", path))?;
+            }
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// The session a log file belongs to, if it is a log file.
+/// "log-20261018-120000-123-4567.1.txt" is part of session "20261018-120000-123-4567".
+fn session_of(file_name: &str) -> Option<&str> {
+    let stem = file_name
+        .strip_prefix(LOG_FILE_PREFIX)?
+        .strip_suffix(LOG_FILE_SUFFIX)?;
+    stem.split('.').next()
+}
+
+/// Log files of all but the newest "keep" sessions. Names must be sorted.
+fn old_sessions(names: &[String], keep: usize) -> Vec<&str> {
+    let mut sessions: Vec<&str> = names.iter().filter_map(|n| session_of(n)).collect();
+    sessions.dedup();
+    let Some(&oldest_kept) = sessions.iter().rev().take(keep).next_back() else {
+        return names.iter().map(|n| n.as_str()).collect(); // keep none
+    };
+    names
+        .iter()
+        .filter(|n| session_of(n).is_some_and(|s| s < oldest_kept))
+        .map(|n| n.as_str())
+        .collect()
+}
+
+/// Writes a session log. When the file gets too big, it becomes the previous part
+/// and a new file is started.
+//  Rotation is done on flush, which the logger does after each record,
+//  so a record is never split between parts.
+pub struct RotatingLogWriter {
+    path: PathBuf, // current log file
+    file: File,    // open on path
+    written: u64,  // bytes in current file
+    max_size: u64, // rotate at this size
+}
+
+impl RotatingLogWriter {
+    /// Usual new. Creates the file, which must not already exist.
+    fn new(path: PathBuf, max_size: u64) -> Result<Self, Error> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .with_context(|| format!("Unable to create log file {:?}", path))?;
+        Ok(RotatingLogWriter {
+            path,
+            file,
+            written: 0,
+            max_size,
+        })
+    }
+
+    /// Path of the current log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the current file to the previous part and start again.
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        std::fs::rename(&self.path, self.path.with_extension("1.txt"))?;
+        self.file = File::create(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    /// End of a record. Rotate if the file is now too big.
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.written >= self.max_size {
+            self.rotate()
+        } else {
+            self.file.flush()
+        }
+    }
+}
+
+#[test]
+fn test_log_retention() {
+    assert_eq!(
+        session_of("log-20261018-120000.txt"),
+        Some("20261018-120000")
+    );
+    assert_eq!(
+        session_of("log-20261018-120000.1.txt"),
+        Some("20261018-120000")
+    );
+    assert_eq!(session_of("log.txt"), None);
+    let names: Vec<String> = [
+        "log-20261016-090000.txt",
+        "log-20261017-090000.1.txt",
+        "log-20261017-090000.txt",
+        "log-20261018-090000.txt",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect();
+    //  Keeping two sessions drops the oldest.
+    assert_eq!(old_sessions(&names, 2), vec!["log-20261016-090000.txt"]);
+    //  Keeping one drops both parts of the middle one too.
+    assert_eq!(old_sessions(&names, 1).len(), 3);
+    assert!(old_sessions(&names, 5).is_empty());
+    assert_eq!(old_sessions(&names, 0).len(), 4);
+    assert_eq!(
+        session_of("log-20261018-120000-123-4567.1.txt"),
+        Some("20261018-120000-123-4567")
+    );
+}
+
+#[test]
+fn test_log_rotation() {
+    let dir = std::env::temp_dir().join(format!("logfiles-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("log-20261018-120000-000-1.txt");
+    let mut writer = RotatingLogWriter::new(path.clone(), 10).unwrap();
+    //  Never reuses a file.
+    assert!(RotatingLogWriter::new(path.clone(), 10).is_err());
+    //  A record is never split, even if it goes past the limit.
+    write!(writer, "first ").unwrap();
+    writeln!(writer, "record").unwrap();
+    writer.flush().unwrap();
+    writeln!(writer, "second").unwrap();
+    writer.flush().unwrap();
+    assert_eq!(
+        std::fs::read_to_string(path.with_extension("1.txt")).unwrap(),
+        "first record\n"
+    );
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
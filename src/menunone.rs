@@ -8,7 +8,6 @@
 //  November 2022
 //
 use crate::{CommonState, MenuGroup, MenuGroupLink};
-use core::any::Any;
 use core::cell::RefCell;
 use std::rc::Rc;
 
@@ -17,6 +16,8 @@ use std::rc::Rc;
 //
 //  The overlay on the main screen. Menus disappear when not used.
 //  Cursor to top or bottom of window restores them.
+#[derive(MenuGroup)]
+#[gui(name = "---")]
 pub struct MenuNone {}
 
 impl MenuNone {
@@ -34,18 +35,4 @@ impl MenuGroup for MenuNone {
         //  Nothing to do, this is MenuNone.
         true
     }
-    /// Ident for debug purposes
-    fn get_name(&self) -> &'static str {
-        "---"
-    }
-
-    /// For downcasting
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
-    /// For downcasting
-    fn as_any_mut(&mut self) -> &mut dyn Any {
-        self
-    }
 }
@@ -0,0 +1,258 @@
+//! menustack.rs -- the stack of menu groups.
+//
+//  The menu group on top of the stack is the one shown.
+//  Pushing a group over another keeps the one beneath, with its state,
+//  and popping goes back to it. Escape, or the mouse back button, pops.
+//  Setting a menu group clears the stack, for a change of state
+//  with no going back.
+//
+//  Changes take effect at the start of the next frame, so a menu
+//  group can push or pop from inside its own draw.
+//
+//  A change can be animated. Fade fades the new group in from the
+//  panel color. Slide slides it in from the side, the right going
+//  forward and the left going back. Windows don't move.
+//
+//  Animats
+//  October 2026
+//
+use crate::{CommonState, MenuGroupLink};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// How long a transition takes.
+const TRANSITION_TIME: Duration = Duration::from_millis(250);
+
+/// How a new menu group appears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MenuTransition {
+    #[default]
+    None, // at once
+    Fade,  // fade in from the panel color
+    Slide, // slide in from the side
+}
+
+/// A change to the menu stack.
+pub enum MenuChange {
+    Set(MenuGroupLink),                  // replace the whole stack
+    Push(MenuGroupLink, MenuTransition), // on top of the current one
+    Pop,                                 // back to the one beneath
+}
+
+/// A menu group on the stack.
+struct MenuEntry {
+    group: MenuGroupLink,       // the menu group
+    transition: MenuTransition, // how it came in, and so how it goes out
+}
+
+/// A transition in progress.
+struct TransitionState {
+    kind: MenuTransition, // fade or slide
+    forward: bool,        // push, as opposed to pop
+    start: Instant,       // when it began
+}
+
+/// The stack of menu groups. The top one is shown.
+pub struct MenuStack {
+    entries: Vec<MenuEntry>,             // bottom to top, never empty
+    pending: Vec<MenuChange>,            // changes for the next frame
+    transition: Option<TransitionState>, // animation in progress, if any
+}
+
+impl MenuStack {
+    /// Usual new, with the first menu group.
+    pub fn new(group: MenuGroupLink) -> Self {
+        MenuStack {
+            entries: vec![MenuEntry {
+                group,
+                transition: MenuTransition::None,
+            }],
+            pending: Vec::new(),
+            transition: None,
+        }
+    }
+
+    /// Replace the whole stack with one menu group.
+    pub fn set(&mut self, group: MenuGroupLink) {
+        self.pending.push(MenuChange::Set(group));
+    }
+
+    /// Push a menu group over the current one.
+    pub fn push(&mut self, group: MenuGroupLink, transition: MenuTransition) {
+        self.pending.push(MenuChange::Push(group, transition));
+    }
+
+    /// Pop back to the group beneath. The top group leaves the way it came in.
+    pub fn pop(&mut self) {
+        self.pending.push(MenuChange::Pop);
+    }
+
+    /// Number of groups on the stack, counting changes not yet made.
+    pub fn depth(&self) -> usize {
+        self.pending
+            .iter()
+            .fold(self.entries.len(), |depth, change| match change {
+                MenuChange::Set(_) => 1,
+                MenuChange::Push(_, _) => depth + 1,
+                MenuChange::Pop => (depth - 1).max(1),
+            })
+    }
+
+    /// The menu group being shown.
+    pub fn top(&self) -> MenuGroupLink {
+        Rc::clone(&self.entries.last().expect("Menu stack empty").group)
+    }
+
+    /// Make the pending changes.
+    /// Returns the groups which were exited and entered, in order, for their hooks.
+    fn make_changes(&mut self) -> Vec<(MenuGroupLink, bool)> {
+        let mut hooks = Vec::new(); // (group, entered)
+        for change in std::mem::take(&mut self.pending) {
+            //  The bottom group can't be popped.
+            if matches!(change, MenuChange::Pop) && self.entries.len() < 2 {
+                log::warn!(
+                    "Menu group {} is the last one, not popping it.",
+                    self.top().borrow().get_name()
+                );
+                continue;
+            }
+            hooks.push((self.top(), false));
+            let transition = match change {
+                MenuChange::Set(group) => {
+                    self.entries.clear();
+                    self.entries.push(MenuEntry {
+                        group,
+                        transition: MenuTransition::None,
+                    });
+                    None
+                }
+                MenuChange::Push(group, transition) => {
+                    self.entries.push(MenuEntry { group, transition });
+                    Some((transition, true))
+                }
+                MenuChange::Pop => self.entries.pop().map(|entry| (entry.transition, false)),
+            };
+            self.transition = transition
+                .filter(|(kind, _)| *kind != MenuTransition::None)
+                .map(|(kind, forward)| TransitionState {
+                    kind,
+                    forward,
+                    start: Instant::now(),
+                });
+            log::info!("Displaying menu group {}", self.top().borrow().get_name());
+            hooks.push((self.top(), true));
+        }
+        hooks
+    }
+}
+
+/// Make pending changes to the menu stack, and tell the groups.
+/// Called before the menu group draws, and before events are passed to it.
+pub(crate) fn apply_menu_changes(state: &mut CommonState) {
+    let hooks = state.menu_stack.make_changes();
+    if !hooks.is_empty() {
+        crate::crashreport::note_menu_group(state.menu_stack.top().borrow().get_name());
+        //  A slide cut short leaves the panels moved.
+        state.context.set_transform_layer(
+            egui::LayerId::background(),
+            egui::emath::TSTransform::IDENTITY,
+        );
+    }
+    //  Hooks may change the stack again. Those changes wait for the next frame.
+    for (group, entered) in hooks {
+        if entered {
+            group.borrow_mut().on_enter(state);
+        } else {
+            group.borrow_mut().on_exit(state);
+        }
+    }
+}
+
+/// Escape or the mouse back button pops the menu stack.
+/// The top group can refuse, by handling it itself.
+pub(crate) fn handle_back(ctx: &egui::Context, state: &mut CommonState) {
+    //  Modal dialogs get Escape first.
+    if state.menu_stack.depth() < 2 || state.is_modal_open() {
+        return;
+    }
+    //  A text field being edited keeps Escape.
+    let wants_keyboard = ctx.wants_keyboard_input();
+    let back = ctx.input_mut(|input| {
+        (!wants_keyboard && input.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+            || input.pointer.button_pressed(egui::PointerButton::Extra1)
+    });
+    if back {
+        let group = state.menu_stack.top();
+        if group.borrow_mut().on_back(state) {
+            state.menu_stack.pop();
+        }
+    }
+}
+
+/// Draw the transition in progress, over the menu group. Returns true while animating.
+pub(crate) fn draw_transition(ctx: &egui::Context, state: &mut CommonState) -> bool {
+    let panels = egui::LayerId::background(); // menu groups draw in panels
+    let Some(transition) = &state.menu_stack.transition else {
+        return false;
+    };
+    let elapsed = transition.start.elapsed().as_secs_f32() / TRANSITION_TIME.as_secs_f32();
+    if elapsed >= 1.0 {
+        if transition.kind == MenuTransition::Slide {
+            ctx.set_transform_layer(panels, egui::emath::TSTransform::IDENTITY);
+        }
+        state.menu_stack.transition = None;
+        return false;
+    }
+    let remaining = 1.0 - egui::emath::easing::cubic_out(elapsed); // 1 to 0
+    let screen = ctx.screen_rect();
+    match transition.kind {
+        MenuTransition::Fade => {
+            //  Over the panels, under the windows.
+            let fill = ctx.style().visuals.panel_fill.gamma_multiply(remaining);
+            ctx.layer_painter(egui::LayerId::new(
+                egui::Order::PanelResizeLine,
+                egui::Id::new("menu_transition"),
+            ))
+            .rect_filled(screen, 0.0, fill);
+        }
+        MenuTransition::Slide => {
+            let direction = if transition.forward { 1.0 } else { -1.0 };
+            let offset = egui::vec2(direction * remaining * screen.width(), 0.0);
+            ctx.set_transform_layer(panels, egui::emath::TSTransform::from_translation(offset));
+        }
+        MenuTransition::None => {}
+    }
+    ctx.request_repaint();
+    true
+}
+
+#[test]
+fn test_menu_stack() {
+    use crate::menunone::MenuNone;
+    let start = MenuNone::new_link();
+    let login = MenuNone::new_link();
+    let mut stack = MenuStack::new(Rc::clone(&start));
+    stack.pop(); // can't pop the last one
+    assert!(stack.make_changes().is_empty());
+    stack.push(Rc::clone(&login), MenuTransition::Slide);
+    assert_eq!(stack.depth(), 2); // counts before the change is made
+    let hooks = stack.make_changes();
+    assert!(Rc::ptr_eq(&hooks[0].0, &start) && !hooks[0].1); // start exited
+    assert!(Rc::ptr_eq(&hooks[1].0, &login) && hooks[1].1); // login entered
+    assert!(stack.transition.as_ref().is_some_and(|t| t.forward));
+    //  Pop goes back to the same start group, leaving as it came in.
+    stack.pop();
+    stack.make_changes();
+    assert!(Rc::ptr_eq(&stack.top(), &start));
+    assert!(stack
+        .transition
+        .as_ref()
+        .is_some_and(|t| t.kind == MenuTransition::Slide && !t.forward));
+    //  Set clears the stack.
+    stack.push(Rc::clone(&login), MenuTransition::None);
+    stack.set(MenuNone::new_link());
+    assert_eq!(stack.depth(), 1);
+    stack.make_changes();
+    assert_eq!(stack.entries.len(), 1);
+    assert!(stack.transition.is_none());
+}
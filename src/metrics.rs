@@ -0,0 +1,374 @@
+//! metrics.rs -- performance metrics, from anywhere.
+//
+//  Producers, on any thread, get a MetricsSender and register named
+//  metrics. Registering returns a handle, which sets or adds to the
+//  metric's value, atomically, with no allocation and no message.
+//  The registry, on the GUI thread, once per interval takes one sample
+//  of each metric for its history. The statistics window graphs the history.
+//
+//  A gauge is a level, such as a queue length. Its sample is the largest
+//  value set during the interval, so short spikes show, or the last value
+//  set, if none was set during the interval. A counter counts things,
+//  such as bytes loaded. Its sample is the rate, per second, over the interval.
+//
+//  A metric can have thresholds, such as a frame time budget,
+//  drawn as lines across its graph.
+//
+//  Adding a metric needs no new fields in the registry. Register it,
+//  keep the handle, and use that.
+//
+//      let metrics = state.metrics.sender();
+//      let packets_sent = metrics.register_counter("net.packets_sent", "Packets sent", [0.0, 100.0]);
+//      packets_sent.add(1);
+//
+//  Animats
+//  October 2026
+//
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Time between samples.
+pub const METRICS_INTERVAL: Duration = Duration::from_secs(1);
+/// Samples kept for each metric.
+pub const METRICS_HISTORY_LENGTH: usize = 100;
+
+/// What sort of metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,   // a level, set from time to time
+    Counter, // a count, graphed as a rate
+}
+
+/// Description of a metric.
+#[derive(Debug, Clone)]
+pub struct MetricInfo {
+    /// Unique name, such as "net.packets_sent"
+    pub name: String,
+    /// Title for graphs, translated
+    pub title: String,
+    /// Gauge or counter
+    pub kind: MetricKind,
+    /// Usual range of samples, for graphs
+    pub y_range: [f32; 2],
+    /// Lines across the graph, (label, value)
+    pub thresholds: Vec<(String, f32)>,
+}
+
+/// Messages from producers. Only for changes to metrics, not samples.
+enum MetricMessage {
+    Register(MetricInfo, Arc<MetricValue>), // new metric, and its value
+    Threshold(String, String, f32),         // (name, label, value) line across graph
+}
+
+/// A metric's value, shared by its handles and the registry.
+//  f32 values are kept as their bits. A peak of NaN means none set this interval.
+#[derive(Debug)]
+struct MetricValue {
+    count: AtomicU64, // counter: count since start
+    last: AtomicU32,  // gauge: last value set
+    peak: AtomicU32,  // gauge: largest value set this interval
+}
+
+impl Default for MetricValue {
+    fn default() -> Self {
+        MetricValue {
+            count: AtomicU64::new(0),
+            last: AtomicU32::new(0.0f32.to_bits()),
+            peak: AtomicU32::new(f32::NAN.to_bits()),
+        }
+    }
+}
+
+impl MetricValue {
+    /// Gauge sample for the interval just ended. Starts a new interval.
+    fn take_gauge_sample(&self) -> f32 {
+        let peak = f32::from_bits(self.peak.swap(f32::NAN.to_bits(), Ordering::Relaxed));
+        if peak.is_nan() {
+            f32::from_bits(self.last.load(Ordering::Relaxed))
+        } else {
+            peak
+        }
+    }
+}
+
+/// Sets a gauge. Cheap to clone, and can go to any thread.
+#[derive(Debug, Clone)]
+pub struct GaugeHandle {
+    value: Arc<MetricValue>, // shared with the registry
+}
+
+impl GaugeHandle {
+    /// Set the gauge.
+    pub fn set(&self, value: f32) {
+        self.value.last.store(value.to_bits(), Ordering::Relaxed);
+        let _ = self
+            .value
+            .peak
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |peak| {
+                let peak = f32::from_bits(peak);
+                (peak.is_nan() || value > peak).then_some(value.to_bits())
+            });
+    }
+}
+
+/// Adds to a counter. Cheap to clone, and can go to any thread.
+#[derive(Debug, Clone)]
+pub struct CounterHandle {
+    value: Arc<MetricValue>, // shared with the registry
+}
+
+impl CounterHandle {
+    /// Add to the counter.
+    pub fn add(&self, count: u64) {
+        self.value.count.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+/// Registers metrics. Cheap to clone, and can go to any thread.
+//  If the registry is gone, as at shutdown, handles still work, but nothing reads them.
+#[derive(Clone)]
+pub struct MetricsSender {
+    channel: crossbeam_channel::Sender<MetricMessage>, // to the registry
+    values: Arc<Mutex<HashMap<String, Arc<MetricValue>>>>, // by name, so registering again shares
+}
+
+impl MetricsSender {
+    /// Register a gauge. Registering again changes the title and range.
+    pub fn register_gauge(&self, name: &str, title: &str, y_range: [f32; 2]) -> GaugeHandle {
+        GaugeHandle {
+            value: self.register(name, title, MetricKind::Gauge, y_range),
+        }
+    }
+
+    /// Register a counter. The range is for the rate.
+    pub fn register_counter(&self, name: &str, title: &str, y_range: [f32; 2]) -> CounterHandle {
+        CounterHandle {
+            value: self.register(name, title, MetricKind::Counter, y_range),
+        }
+    }
+
+    /// Register a metric. Returns its value, shared with the registry.
+    fn register(
+        &self,
+        name: &str,
+        title: &str,
+        kind: MetricKind,
+        y_range: [f32; 2],
+    ) -> Arc<MetricValue> {
+        let value = self
+            .values
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name.to_string())
+            .or_default()
+            .clone();
+        let _ = self.channel.send(MetricMessage::Register(
+            MetricInfo {
+                name: name.to_string(),
+                title: title.to_string(),
+                kind,
+                y_range,
+                thresholds: Vec::new(),
+            },
+            value.clone(),
+        ));
+        value
+    }
+
+    /// Draw a line across a registered metric's graph, such as a budget.
+    pub fn add_threshold(&self, name: &str, label: &str, value: f32) {
+        let _ = self.channel.send(MetricMessage::Threshold(
+            name.to_string(),
+            label.to_string(),
+            value,
+        ));
+    }
+}
+
+/// One metric and its history.
+pub struct Metric {
+    info: MetricInfo,        // what it is
+    value: Arc<MetricValue>, // shared with the handles
+    last_total: u64,         // counter: count at last sample
+    history: VecDeque<f32>,  // one sample per interval, oldest first
+}
+
+impl Metric {
+    /// Usual new
+    fn new(info: MetricInfo, value: Arc<MetricValue>) -> Self {
+        Metric {
+            info,
+            value,
+            last_total: 0,
+            history: VecDeque::with_capacity(METRICS_HISTORY_LENGTH),
+        }
+    }
+
+    /// Description.
+    pub fn info(&self) -> &MetricInfo {
+        &self.info
+    }
+
+    /// Counter total since start. Zero for gauges.
+    pub fn total(&self) -> u64 {
+        match self.info.kind {
+            MetricKind::Gauge => 0,
+            MetricKind::Counter => self.value.count.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Samples, one per interval, oldest first. Counters are rates per second.
+    pub fn history(&self) -> &VecDeque<f32> {
+        &self.history
+    }
+
+    /// Take the sample for an interval which lasted this long.
+    fn sample(&mut self, elapsed: Duration) {
+        let sample = match self.info.kind {
+            MetricKind::Gauge => self.value.take_gauge_sample(),
+            MetricKind::Counter => {
+                let total = self.value.count.load(Ordering::Relaxed);
+                let count = total.wrapping_sub(self.last_total);
+                self.last_total = total;
+                count as f32 / elapsed.as_secs_f32().max(f32::EPSILON)
+            }
+        };
+        while self.history.len() >= METRICS_HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(sample);
+    }
+}
+
+/// All the metrics, in order registered.
+pub struct MetricsRegistry {
+    send_channel: crossbeam_channel::Sender<MetricMessage>, // cloned for producers
+    recv_channel: crossbeam_channel::Receiver<MetricMessage>, // from producers
+    values: Arc<Mutex<HashMap<String, Arc<MetricValue>>>>,  // shared by producers
+    metrics: Vec<Metric>,                                   // in order registered
+    last_sample_time: Instant,                              // end of last interval
+    sample_count: u64,                                      // intervals so far
+}
+
+impl Default for MetricsRegistry {
+    fn default() -> Self {
+        let (send_channel, recv_channel) = crossbeam_channel::unbounded();
+        MetricsRegistry {
+            send_channel,
+            recv_channel,
+            values: Arc::new(Mutex::new(HashMap::new())),
+            metrics: Vec::new(),
+            last_sample_time: Instant::now(),
+            sample_count: 0,
+        }
+    }
+}
+
+impl MetricsRegistry {
+    /// For producers.
+    pub fn sender(&self) -> MetricsSender {
+        MetricsSender {
+            channel: self.send_channel.clone(),
+            values: self.values.clone(),
+        }
+    }
+
+    /// The metrics, in order registered.
+    pub fn metrics(&self) -> &[Metric] {
+        &self.metrics
+    }
+
+    /// Number of samples taken of each metric so far. Metrics registered late have fewer.
+    pub fn sample_count(&self) -> u64 {
+        self.sample_count
+    }
+
+    /// Collect metrics the producers registered, and sample if an interval is up.
+    /// Called every frame. Returns true if a sample was taken.
+    pub fn update(&mut self, now: Instant) -> bool {
+        while let Ok(message) = self.recv_channel.try_recv() {
+            self.handle_message(message);
+        }
+        let elapsed = now.duration_since(self.last_sample_time);
+        if elapsed < METRICS_INTERVAL {
+            return false;
+        }
+        for metric in &mut self.metrics {
+            metric.sample(elapsed);
+        }
+        self.last_sample_time = now;
+        self.sample_count += 1;
+        true
+    }
+
+    /// One message from a producer.
+    fn handle_message(&mut self, message: MetricMessage) {
+        match message {
+            MetricMessage::Register(info, value) => {
+                match self.metrics.iter_mut().find(|m| m.info.name == info.name) {
+                    Some(metric) => {
+                        //  Thresholds are added separately, and kept. The value is shared already.
+                        let thresholds = std::mem::take(&mut metric.info.thresholds);
+                        metric.info = MetricInfo { thresholds, ..info };
+                    }
+                    None => self.metrics.push(Metric::new(info, value)),
+                }
+            }
+            MetricMessage::Threshold(name, label, value) => {
+                match self.metrics.iter_mut().find(|m| m.info.name == name) {
+                    Some(metric) => metric.info.thresholds.push((label, value)),
+                    None => log::warn!("Threshold for metric {} which was not registered", name),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_metrics_registry() {
+    let mut registry = MetricsRegistry::default();
+    let sender = registry.sender();
+    let producer = std::thread::spawn(move || {
+        let queue = sender.register_gauge("queue", "Queue length", [0.0, 10.0]);
+        let bytes = sender.register_counter("bytes", "Bytes loaded", [0.0, 1000.0]);
+        sender.add_threshold("queue", "Limit", 8.0);
+        sender.add_threshold("lost", "Limit", 1.0); // not registered, ignored
+        let queue_again = sender.register_gauge("queue", "Queue", [0.0, 10.0]); // keeps threshold
+        queue.set(2.0);
+        queue_again.set(3.0); // same gauge
+        bytes.add(500);
+        bytes.add(1500);
+        queue
+    });
+    let queue = producer.join().unwrap();
+    let start = registry.last_sample_time;
+    assert!(!registry.update(start)); // too soon
+    assert!(registry.update(start + Duration::from_secs(2)));
+    assert_eq!(registry.sample_count(), 1);
+    let metrics = registry.metrics();
+    assert_eq!(metrics.len(), 2);
+    assert_eq!(metrics[0].history()[0], 3.0);
+    assert_eq!(metrics[0].info().title, "Queue");
+    assert_eq!(
+        metrics[0].info().thresholds,
+        vec![("Limit".to_string(), 8.0)]
+    );
+    assert_eq!(metrics[1].total(), 2000);
+    assert_eq!(metrics[1].history()[0], 1000.0); // per second
+    assert_eq!(metrics[1].info().kind, MetricKind::Counter);
+    //  Counters start again each interval. Gauges hold.
+    assert!(registry.update(start + Duration::from_secs(3)));
+    let metrics = registry.metrics();
+    assert_eq!(metrics[0].history()[1], 3.0);
+    assert_eq!(metrics[1].history()[1], 0.0);
+    //  A spike between samples shows, for that interval only.
+    queue.set(9.0);
+    queue.set(1.0);
+    assert!(registry.update(start + Duration::from_secs(4)));
+    assert!(registry.update(start + Duration::from_secs(5)));
+    let metrics = registry.metrics();
+    assert_eq!(metrics[0].history()[2], 9.0);
+    assert_eq!(metrics[0].history()[3], 1.0);
+}
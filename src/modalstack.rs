@@ -0,0 +1,305 @@
+//! modalstack.rs -- modal dialogs.
+//
+//  A modal dialog is drawn above everything else, with the rest of
+//  the screen dimmed. Nothing beneath it, windows, menus or the 3D world,
+//  gets mouse clicks or keyboard input until it is closed.
+//
+//  Modals stack. A dialog can open another, such as an error message,
+//  and only the top one is live. Keyboard focus stays inside the top dialog.
+//  Enter is its default button and Escape is its cancel button.
+//
+//  Dialogs report what the user did by sending events, typed
+//  as the application wants, on the usual GUI event channel.
+//
+//  Animats
+//  October 2026
+//
+use crate::CommonState;
+use core::cell::RefCell;
+use std::rc::Rc;
+
+/// Widest a modal dialog gets, in points.
+const MODAL_MAX_WIDTH: f32 = 500.0;
+/// Tallest a message gets before it scrolls, in points.
+const MESSAGE_MAX_HEIGHT: f32 = 300.0;
+
+/// Which button closed a modal dialog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModalButton {
+    Default,      // the default button, or Enter
+    Other(usize), // one of the extra buttons
+    Cancel,       // the cancel button, or Escape
+}
+
+/// A modal dialog.
+//  The stack draws the title and the buttons. The dialog draws the rest.
+pub trait ModalDialog {
+    /// Title, shown at the top.
+    fn title(&self) -> &str;
+    /// Labels for the default button and the cancel button, if there is one.
+    /// With no cancel button, Escape means the default button.
+    fn button_labels(&self) -> (&str, Option<&str>);
+    /// Labels for more buttons, drawn between the default and cancel buttons.
+    fn extra_button_labels(&self) -> Vec<&str> {
+        Vec::new()
+    }
+    /// Draw the contents, between title and buttons. Called every frame.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, state: &mut CommonState);
+    /// Can the default button be used now? If not, it is greyed out and Enter does nothing.
+    fn can_accept(&self) -> bool {
+        true
+    }
+    /// A button was used. Send the result as an event.
+    /// Return false to stay open, for bad input.
+    fn finish(&mut self, state: &mut CommonState, button: ModalButton) -> bool;
+    /// Unique ID of the dialog.
+    fn get_id(&self) -> egui::Id;
+}
+
+pub type ModalDialogLink = Rc<RefCell<dyn ModalDialog>>;
+
+/// The open modal dialogs, bottom to top.
+#[derive(Default)]
+pub struct ModalStack {
+    dialogs: Vec<ModalDialogLink>, // open dialogs, top last
+    pending: Vec<ModalDialogLink>, // opened during this frame
+    keys: (bool, bool),            // (Enter, Escape) taken this frame for the top dialog
+}
+
+impl ModalStack {
+    /// Open a dialog, on top of any others. Takes effect at the next draw.
+    pub fn push(&mut self, dialog: ModalDialogLink) {
+        self.pending.push(dialog);
+    }
+
+    /// Is any modal dialog open? If so, input beneath it is blocked.
+    pub fn is_open(&self) -> bool {
+        !self.dialogs.is_empty() || !self.pending.is_empty()
+    }
+
+    /// Is a dialog with this ID open?
+    fn contains(&self, id: egui::Id) -> bool {
+        self.dialogs.iter().any(|d| d.borrow().get_id() == id)
+    }
+
+    /// Put newly opened dialogs on the stack.
+    /// Opening a dialog that is already open is a bug in the caller, so it is logged.
+    fn add_pending(&mut self) {
+        for dialog in std::mem::take(&mut self.pending) {
+            let id = dialog.borrow().get_id();
+            if self.contains(id) {
+                log::warn!(
+                    "Modal dialog \"{}\" is already open, not opening it again.",
+                    dialog.borrow().title()
+                );
+            } else {
+                self.dialogs.push(dialog);
+            }
+        }
+    }
+}
+
+/// Take Enter and Escape for the top dialog, if one is open.
+/// Called once per frame, before the menus are drawn, so they never see those keys.
+pub(crate) fn take_modal_keys(ctx: &egui::Context, state: &mut CommonState) {
+    state.modal_stack.add_pending();
+    state.modal_stack.keys = if state.modal_stack.is_open() {
+        ctx.input_mut(|input| {
+            (
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        })
+    } else {
+        (false, false)
+    };
+}
+
+/// Draw all the modal dialogs. Called once per frame, after everything else.
+pub(crate) fn draw_modals(ctx: &egui::Context, state: &mut CommonState) {
+    state.modal_stack.add_pending();
+    //  List made outside "state" to avoid a double mutable borrow.
+    let dialogs: Vec<ModalDialogLink> = state.modal_stack.dialogs.iter().map(Rc::clone).collect();
+    let mut closed = Vec::new();
+    for (n, dialog) in dialogs.iter().enumerate() {
+        let is_top = n + 1 == dialogs.len();
+        if draw_modal(ctx, state, &mut *dialog.borrow_mut(), is_top) {
+            closed.push(Rc::clone(dialog));
+        }
+    }
+    state
+        .modal_stack
+        .dialogs
+        .retain(|d| !closed.iter().any(|c| Rc::ptr_eq(c, d)));
+}
+
+/// Draw one modal dialog. Returns true if it closed.
+fn draw_modal(
+    ctx: &egui::Context,
+    state: &mut CommonState,
+    dialog: &mut dyn ModalDialog,
+    is_top: bool,
+) -> bool {
+    //  Enter and Escape belong to the top dialog.
+    //  They were taken before anything was drawn, so menus and text fields don't see them.
+    let (enter, escape) = if is_top {
+        std::mem::take(&mut state.modal_stack.keys)
+    } else {
+        (false, false)
+    };
+    let title = dialog.title().to_string();
+    let (default_label, cancel_label) = {
+        let (default_label, cancel_label) = dialog.button_labels();
+        (
+            default_label.to_string(),
+            cancel_label.map(|s| s.to_string()),
+        )
+    };
+    let extra_labels: Vec<String> = dialog
+        .extra_button_labels()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let mut button = None;
+    let modal = egui::Modal::new(dialog.get_id()).show(ctx, |ui| {
+        ui.set_max_width(MODAL_MAX_WIDTH);
+        ui.vertical_centered(|ui| ui.heading(title.as_str()));
+        ui.separator();
+        dialog.draw_contents(ui, state);
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    dialog.can_accept(),
+                    egui::Button::new(default_label.as_str()),
+                )
+                .clicked()
+            {
+                button = Some(ModalButton::Default);
+            }
+            for (n, label) in extra_labels.iter().enumerate() {
+                if ui.button(label.as_str()).clicked() {
+                    button = Some(ModalButton::Other(n));
+                }
+            }
+            if let Some(cancel_label) = &cancel_label {
+                if ui.button(cancel_label.as_str()).clicked() {
+                    button = Some(ModalButton::Cancel);
+                }
+            }
+        });
+    });
+    if enter && dialog.can_accept() {
+        button = Some(ModalButton::Default);
+    }
+    if escape {
+        button = Some(if cancel_label.is_some() {
+            ModalButton::Cancel
+        } else {
+            ModalButton::Default
+        });
+    }
+    if is_top {
+        keep_focus_in(ctx, modal.response.layer_id);
+    }
+    match button {
+        Some(button) => dialog.finish(state, button),
+        None => false,
+    }
+}
+
+/// Keyboard focus must not stay on a widget beneath the top dialog.
+/// A text field focused when the dialog opened would still get typing.
+fn keep_focus_in(ctx: &egui::Context, layer_id: egui::LayerId) {
+    if let Some(focused) = ctx.memory(|memory| memory.focused()) {
+        //  Widgets new this frame have no response yet. Those are in the dialog.
+        if ctx
+            .read_response(focused)
+            .is_some_and(|response| response.layer_id != layer_id)
+        {
+            ctx.memory_mut(|memory| memory.surrender_focus(focused));
+        }
+    }
+}
+
+/// A message, with an OK button. Used for error messages.
+pub struct MessageDialog {
+    title: String,        // title of dialog
+    id: egui::Id,         // unique ID
+    message: Vec<String>, // dialog text
+    ok_label: String,     // "OK" button
+}
+
+impl MessageDialog {
+    /// Usual new
+    pub fn new(id: egui::Id, title: &str, message: &[&str], ok_label: &str) -> Self {
+        MessageDialog {
+            title: title.to_string(),
+            id,
+            message: message.iter().map(|s| s.to_string()).collect(),
+            ok_label: ok_label.to_string(),
+        }
+    }
+
+    /// As link
+    pub fn new_link(
+        id: egui::Id,
+        title: &str,
+        message: &[&str],
+        ok_label: &str,
+    ) -> ModalDialogLink {
+        Rc::new(RefCell::new(Self::new(id, title, message, ok_label)))
+    }
+}
+
+impl ModalDialog for MessageDialog {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn button_labels(&self) -> (&str, Option<&str>) {
+        (&self.ok_label, None)
+    }
+
+    /// The message. Long ones scroll.
+    fn draw_contents(&mut self, ui: &mut egui::Ui, _state: &mut CommonState) {
+        egui::ScrollArea::vertical()
+            .max_height(MESSAGE_MAX_HEIGHT)
+            .show(ui, |ui| {
+                for line in &self.message {
+                    ui.label(line.as_str());
+                }
+            });
+    }
+
+    /// Nobody needs to know.
+    fn finish(&mut self, _state: &mut CommonState, _button: ModalButton) -> bool {
+        true
+    }
+
+    fn get_id(&self) -> egui::Id {
+        self.id
+    }
+}
+
+#[test]
+fn test_modal_stack() {
+    let mut stack = ModalStack::default();
+    assert!(!stack.is_open());
+    let id = egui::Id::new("error");
+    stack.push(MessageDialog::new_link(id, "Error", &["Oops"], "OK"));
+    assert!(stack.is_open()); // blocks input at once, before it is drawn
+    stack.add_pending();
+    assert!(stack.contains(id));
+    //  A second copy is not opened.
+    stack.push(MessageDialog::new_link(id, "Error", &["Oops"], "OK"));
+    stack.push(MessageDialog::new_link(
+        egui::Id::new("other"),
+        "Error",
+        &["Again"],
+        "OK",
+    ));
+    stack.add_pending();
+    assert_eq!(stack.dialogs.len(), 2);
+    assert_eq!(stack.dialogs[1].borrow().get_id(), egui::Id::new("other"));
+}
@@ -16,10 +16,10 @@ use std::f32::consts::PI;
 
 /// NavArrows -- a 4-way arrow with an optional button in the center.
 //  The persistent part.
-pub struct NavArrows<'a> {
+pub struct NavArrows {
     button: (egui::TextureId, egui::Vec2), // the button image
-    arrow: egui::Image<'a>,                // the arrow image for pressed direction
-    center_button: egui::Image<'a>,        // the center button
+    arrow: egui::Image<'static>,           // the arrow image for pressed direction
+    center_button: egui::Image<'static>,   // the center button
     hover_text: WidgetText,                // hover text for help
     center_button_size: f32,               // center button of arrows, if nonzero
 }
@@ -44,7 +44,7 @@ impl Index<NavAction> for [usize; 6] {
     }
 }
 
-impl NavArrows<'_> {
+impl NavArrows {
     /// Image, dimensions of button,
     pub fn new(
         button: (egui::TextureId, egui::Vec2),
@@ -128,7 +128,7 @@ impl NavArrows<'_> {
 }
 
 /// The widget is an image button plus a drawn arrow.
-impl egui::Widget for &mut NavArrows<'_> {
+impl egui::Widget for &mut NavArrows {
     fn ui(self, ui: &mut Ui) -> Response {
         let response =
             ui.add(egui::widgets::ImageButton::new((self.button.0, self.button.1)).frame(false));
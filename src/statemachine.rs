@@ -0,0 +1,295 @@
+//! statemachine.rs -- application state machines.
+//
+//  The application moves between a few main states, such as
+//  startup, login, and connected. Each state can have a menu group,
+//  and actions to run on entry and exit. Only declared transitions
+//  are allowed. Anything else is an error, and the state stays put.
+//
+//      let machine = StateMachine::new("mode", Mode::Startup)
+//          .menu(Mode::Startup, |ctx| make_start_menu(ctx))
+//          .push_menu(Mode::Login, |_| MenuLogin::new_link(), MenuTransition::Slide)
+//          .on_enter(Mode::Connected, |ctx| ctx.common_state.restore_windows())
+//          .allow(Mode::Startup, &[Mode::Login])
+//          .allow(Mode::Login, &[Mode::Startup, Mode::Connected]);
+//
+//  A state's menu group either replaces the whole menu stack,
+//  or is pushed over the menu group of the state it came from.
+//  Going back to that state pops, and its menu group is as it was left.
+//
+//  The context, C, is whatever the actions need. It must have somewhere
+//  to show menu groups. Nothing here draws, so it can be tested alone.
+//
+//  Animats
+//  October 2026
+//
+use crate::menustack::{MenuChange, MenuTransition};
+use crate::{AppState, CommonState, GuiState, MenuGroupLink};
+use anyhow::{anyhow, Error};
+use std::fmt::{Debug, Write};
+
+/// Somewhere to show a state's menu group.
+pub trait MenuHost {
+    /// Change the menu stack.
+    fn change_menu(&mut self, change: MenuChange);
+}
+
+impl MenuHost for CommonState {
+    fn change_menu(&mut self, change: MenuChange) {
+        match change {
+            MenuChange::Set(group) => self.set_menu_group(group),
+            MenuChange::Push(group, transition) => self.push_menu_group(group, transition),
+            MenuChange::Pop => self.pop_menu_group(),
+        }
+    }
+}
+
+impl<T: AppState> MenuHost for GuiState<T> {
+    fn change_menu(&mut self, change: MenuChange) {
+        self.common_state.change_menu(change)
+    }
+}
+
+/// An entry or exit action.
+type Action<C> = Box<dyn FnMut(&mut C)>;
+/// Makes the menu group for a state.
+type MenuFactory<C> = Box<dyn FnMut(&mut C) -> MenuGroupLink>;
+
+/// How a state's menu group is shown.
+enum StateMenu<C> {
+    None,                                 // leave the menus alone
+    Set(MenuFactory<C>),                  // replace the menu stack
+    Push(MenuFactory<C>, MenuTransition), // over the previous state's menus
+}
+
+/// One state.
+struct StateDef<S, C> {
+    state: S,                 // which state
+    on_enter: Vec<Action<C>>, // run on entry, in order
+    on_exit: Vec<Action<C>>,  // run on exit, in order
+    menu: StateMenu<C>,       // menu group for this state
+}
+
+/// A state machine, with states of type S and actions on a C.
+pub struct StateMachine<S, C> {
+    name: &'static str,          // for messages and graphs
+    initial: S,                  // starting state
+    current: S,                  // current state
+    states: Vec<StateDef<S, C>>, // in order declared
+    transitions: Vec<(S, S)>,    // allowed (from, to)
+    menu_states: Vec<S>,         // states whose menu groups are on the menu stack, bottom first
+}
+
+impl<S: Copy + PartialEq + Debug, C: MenuHost> StateMachine<S, C> {
+    /// Usual new. Starts in the initial state, with no actions run.
+    //  Transition into the initial state to build its menu, if allowed.
+    pub fn new(name: &'static str, initial: S) -> Self {
+        let mut machine = StateMachine {
+            name,
+            initial,
+            current: initial,
+            states: Vec::new(),
+            transitions: Vec::new(),
+            menu_states: Vec::new(),
+        };
+        machine.state_mut(initial);
+        machine
+    }
+
+    /// The definition of a state. Created if needed.
+    fn state_mut(&mut self, state: S) -> &mut StateDef<S, C> {
+        let pos = match self.states.iter().position(|def| def.state == state) {
+            Some(pos) => pos,
+            None => {
+                self.states.push(StateDef {
+                    state,
+                    on_enter: Vec::new(),
+                    on_exit: Vec::new(),
+                    menu: StateMenu::None,
+                });
+                self.states.len() - 1
+            }
+        };
+        &mut self.states[pos]
+    }
+
+    /// Declare a state. States used in transitions are declared anyway.
+    pub fn state(mut self, state: S) -> Self {
+        self.state_mut(state);
+        self
+    }
+
+    /// Run this on entry to a state.
+    pub fn on_enter(mut self, state: S, action: impl FnMut(&mut C) + 'static) -> Self {
+        self.state_mut(state).on_enter.push(Box::new(action));
+        self
+    }
+
+    /// Run this on exit from a state.
+    pub fn on_exit(mut self, state: S, action: impl FnMut(&mut C) + 'static) -> Self {
+        self.state_mut(state).on_exit.push(Box::new(action));
+        self
+    }
+
+    /// The state's menu group replaces all menus.
+    pub fn menu(
+        mut self,
+        state: S,
+        factory: impl FnMut(&mut C) -> MenuGroupLink + 'static,
+    ) -> Self {
+        self.state_mut(state).menu = StateMenu::Set(Box::new(factory));
+        self
+    }
+
+    /// The state's menu group is pushed over the previous state's, and popped on the way back.
+    pub fn push_menu(
+        mut self,
+        state: S,
+        factory: impl FnMut(&mut C) -> MenuGroupLink + 'static,
+        transition: MenuTransition,
+    ) -> Self {
+        self.state_mut(state).menu = StateMenu::Push(Box::new(factory), transition);
+        self
+    }
+
+    /// Allow transitions from one state to others. A state can go to itself only if allowed.
+    pub fn allow(mut self, from: S, to: &[S]) -> Self {
+        self.state_mut(from);
+        for &to in to {
+            self.state_mut(to);
+            if !self.can_go(from, to) {
+                self.transitions.push((from, to));
+            }
+        }
+        self
+    }
+
+    /// Is this transition declared?
+    fn can_go(&self, from: S, to: S) -> bool {
+        self.transitions.contains(&(from, to))
+    }
+
+    /// Current state.
+    pub fn current(&self) -> S {
+        self.current
+    }
+
+    /// Can we go from the current state to this one?
+    pub fn can_transition(&self, to: S) -> bool {
+        self.can_go(self.current, to)
+    }
+
+    /// Go to a new state. Runs the exit actions of the old state, shows the
+    /// new state's menu group, and runs the entry actions of the new state.
+    /// An undeclared transition is an error, and nothing happens.
+    pub fn transition(&mut self, ctx: &mut C, to: S) -> Result<(), Error> {
+        let from = self.current;
+        if !self.can_go(from, to) {
+            return Err(anyhow!(
+                "Illegal state change in {}: {:?} -> {:?}",
+                self.name,
+                from,
+                to
+            ));
+        }
+        log::info!("State change in {}: {:?} -> {:?}", self.name, from, to);
+        crate::crashreport::note_app_state(self.name, format!("{:?}", to));
+        for action in &mut self.state_mut(from).on_exit {
+            action(ctx);
+        }
+        self.current = to;
+        self.show_menu(ctx, to);
+        for action in &mut self.state_mut(to).on_enter {
+            action(ctx);
+        }
+        Ok(())
+    }
+
+    /// Show the menu group for a state being entered.
+    fn show_menu(&mut self, ctx: &mut C, to: S) {
+        //  Back to the state beneath on the menu stack. Its menu group is still there.
+        if self.menu_states.len() > 1 && self.menu_states[self.menu_states.len() - 2] == to {
+            self.menu_states.pop();
+            ctx.change_menu(MenuChange::Pop);
+            return;
+        }
+        let change = match &mut self.state_mut(to).menu {
+            StateMenu::None => return,
+            StateMenu::Set(factory) => MenuChange::Set(factory(ctx)),
+            StateMenu::Push(factory, transition) => MenuChange::Push(factory(ctx), *transition),
+        };
+        if matches!(change, MenuChange::Set(_)) {
+            self.menu_states.clear();
+        }
+        self.menu_states.push(to);
+        ctx.change_menu(change);
+    }
+
+    /// The state graph, in Graphviz DOT form. The initial state is double circled.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph \"{}\" {{", self.name);
+        for def in &self.states {
+            let shape = if def.state == self.initial {
+                "doublecircle"
+            } else {
+                "circle"
+            };
+            let _ = writeln!(dot, "    \"{:?}\" [shape={}];", def.state, shape);
+        }
+        for (from, to) in &self.transitions {
+            let _ = writeln!(dot, "    \"{:?}\" -> \"{:?}\";", from, to);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[test]
+fn test_state_machine() {
+    use crate::menunone::MenuNone;
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Mode {
+        Start,
+        Login,
+        Run,
+    }
+    /// Records what was done.
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+    impl MenuHost for Log {
+        fn change_menu(&mut self, change: MenuChange) {
+            self.0.push(match change {
+                MenuChange::Set(_) => "set",
+                MenuChange::Push(_, _) => "push",
+                MenuChange::Pop => "pop",
+            })
+        }
+    }
+    let mut machine = StateMachine::<Mode, Log>::new("test", Mode::Start)
+        .menu(Mode::Start, |_| MenuNone::new_link())
+        .push_menu(Mode::Login, |_| MenuNone::new_link(), MenuTransition::Slide)
+        .menu(Mode::Run, |_| MenuNone::new_link())
+        .on_exit(Mode::Start, |log| log.0.push("exit start"))
+        .on_enter(Mode::Run, |log| log.0.push("enter run"))
+        .allow(Mode::Start, &[Mode::Start, Mode::Login])
+        .allow(Mode::Login, &[Mode::Start, Mode::Run]);
+    let mut log = Log::default();
+    machine.transition(&mut log, Mode::Start).unwrap();
+    machine.transition(&mut log, Mode::Login).unwrap();
+    machine.transition(&mut log, Mode::Start).unwrap(); // back pops
+    assert_eq!(
+        log.0,
+        vec!["exit start", "set", "exit start", "push", "pop"]
+    );
+    //  Illegal change is refused.
+    assert!(machine.transition(&mut log, Mode::Run).is_err());
+    assert_eq!(machine.current(), Mode::Start);
+    log.0.clear();
+    machine.transition(&mut log, Mode::Login).unwrap();
+    machine.transition(&mut log, Mode::Run).unwrap();
+    assert_eq!(log.0, vec!["exit start", "push", "set", "enter run"]);
+    assert!(!machine.can_transition(Mode::Start));
+    let dot = machine.to_dot();
+    assert!(dot.contains("\"Start\" [shape=doublecircle];"));
+    assert!(dot.contains("\"Login\" -> \"Run\";"));
+}
@@ -3,49 +3,209 @@
 //! A general-use 'egui' widget.
 //!
 //! Graphs of time-dependent variables, for performance measurement.
+//! Several named series can share a graph, with a legend.
+//! The X axis is time, in seconds before the latest sample.
+//! The Y axis scales to fit, or is logarithmic.
+//! Threshold lines, such as a frame time budget, can be drawn across,
+//! and each series can have a summary: min, max, average and percentiles.
+//
+//  Plot points are kept contiguous, so drawing borrows them, without copying.
+//  Drawing allocates nothing per point. The title, series names and threshold
+//  labels are still copied each frame, since egui and egui_plot take them by value.
+//  Min, max and sum are kept up to date as values are added. The rest of
+//  a summary is worked out only when wanted, and only if values changed.
 //
 //  Animats
 //  April, 2023
 //
-////use core::ops::Index;
 use egui::{Response, Ui, WidgetText};
-////use egui_plot::{Line, Plot, PlotPoints};
+use egui_plot::{HLine, Legend, Line, LineStyle, Plot, PlotPoint, PlotPoints};
 use std::collections::VecDeque;
+use std::time::Instant;
 //  Always write TextureId, Vec2, Rect fully qualified to avoid name confusion.
 
-/// Time series of equally spaced data
+/// Values are scaled to this for the histogram, which takes integers.
+const HISTOGRAM_SCALE: f64 = 1_000_000.0;
+/// Smallest value on a log axis. Zero and below are drawn here.
+const LOG_MIN: f64 = 1e-9;
+
+/// Summary of a series.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SeriesSummary {
+    /// Smallest value
+    pub min: f32,
+    /// Largest value
+    pub max: f32,
+    /// Average
+    pub mean: f32,
+    /// Median
+    pub p50: f32,
+    /// 95th percentile
+    pub p95: f32,
+}
+
+/// Time series, with times in seconds.
 struct TimeSeries {
+    /// Name, for the legend
+    name: String,
     /// Length
     length: usize,
-    /// The points
+    /// The values, as given
     values: VecDeque<f32>,
+    /// The points, as plotted. Always contiguous.
+    points: VecDeque<PlotPoint>,
+    /// Sum of the values
+    sum: f64,
+    /// Smallest value, unless rescan is set
+    min: f32,
+    /// Largest value, unless rescan is set
+    max: f32,
+    /// A dropped value was the smallest or largest, so min and max need a rescan
+    rescan: bool,
+    /// Values changed since the summary was worked out
+    stale: bool,
+    /// Summary of the values
+    summary: SeriesSummary,
+    /// Summary, as text
+    summary_text: String,
 }
 
 impl TimeSeries {
     /// Usual new
-    fn new(length: usize) -> Self {
+    fn new(name: &str, length: usize) -> Self {
         assert!(length > 0);
         Self {
+            name: name.to_string(),
             length,
             values: VecDeque::with_capacity(length),
+            points: VecDeque::with_capacity(length),
+            sum: 0.0,
+            min: f32::INFINITY,
+            max: f32::NEG_INFINITY,
+            rescan: false,
+            stale: false,
+            summary: SeriesSummary::default(),
+            summary_text: String::new(),
         }
     }
 
     /// Add to time series
-    fn push(&mut self, v: f32) {
+    fn push(&mut self, time: f64, v: f32, log_y: bool) {
         while self.values.len() >= self.length {
             // if oversize, drain
-            let _ = self.values.pop_front();
+            if let Some(old) = self.values.pop_front() {
+                self.sum -= old as f64;
+                self.rescan |= old <= self.min || old >= self.max;
+            }
+            let _ = self.points.pop_front();
         }
         self.values.push_back(v);
+        self.sum += v as f64;
+        self.min = self.min.min(v);
+        self.max = self.max.max(v);
+        self.stale = true;
+        self.points
+            .push_back(PlotPoint::new(time, plot_y(v, log_y)));
+        self.points.make_contiguous(); // so drawing can borrow a slice
     }
 
-    /// Return time series as a generator of plot points
-    fn as_plot_points(&self) -> impl Iterator<Item = egui_plot::PlotPoint> + '_ {
-        self.values
-            .iter()
-            .enumerate()
-            .map(|(i, &y)| egui_plot::PlotPoint::new(i as f64, y as f64))
+    /// Replot, after a change of Y axis.
+    fn replot(&mut self, log_y: bool) {
+        for (point, &v) in self.points.iter_mut().zip(self.values.iter()) {
+            point.y = plot_y(v, log_y);
+        }
+    }
+
+    /// The points, as a slice, in time order.
+    fn plot_points(&self) -> &[PlotPoint] {
+        self.points.as_slices().0
+    }
+
+    /// Work out the summary, if values changed since last time.
+    fn summarize(&mut self, histogram: &mut histogram::Histogram) {
+        if !self.stale {
+            return;
+        }
+        self.stale = false;
+        if self.values.is_empty() {
+            self.summary = SeriesSummary::default();
+            self.summary_text.clear();
+            return;
+        }
+        if self.rescan {
+            self.min = self.values.iter().copied().fold(f32::INFINITY, f32::min);
+            self.max = self
+                .values
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max);
+            self.rescan = false;
+        }
+        let (min, max) = (self.min, self.max);
+        let mean = (self.sum / self.values.len() as f64) as f32;
+        //  The histogram takes integers. Scale the range to fit.
+        let scale = HISTOGRAM_SCALE / ((max - min) as f64).max(f64::EPSILON);
+        histogram.clear();
+        for &v in &self.values {
+            let _ = histogram.increment(((v - min) as f64 * scale) as u64);
+        }
+        let percentile = |p: f64| {
+            histogram
+                .percentile(p)
+                .map(|x| min + (x as f64 / scale) as f32)
+                .unwrap_or(mean)
+                .clamp(min, max)
+        };
+        self.summary = SeriesSummary {
+            min,
+            max,
+            mean,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+        };
+        self.summary_text = format!(
+            "{}min {}  max {}  avg {}  p50 {}  p95 {}",
+            if self.name.is_empty() {
+                String::new()
+            } else {
+                format!("{}: ", self.name)
+            },
+            format_value(min),
+            format_value(max),
+            format_value(mean),
+            format_value(self.summary.p50),
+            format_value(self.summary.p95)
+        );
+    }
+}
+
+/// Y as plotted.
+fn plot_y(v: f32, log_y: bool) -> f64 {
+    if log_y {
+        (v as f64).max(LOG_MIN).log10()
+    } else {
+        v as f64
+    }
+}
+
+/// Y as given, from Y as plotted.
+fn value_y(y: f64, log_y: bool) -> f64 {
+    if log_y {
+        10f64.powf(y)
+    } else {
+        y
+    }
+}
+
+/// Value, with precision to suit its size.
+fn format_value(v: impl Into<f64>) -> String {
+    let v = v.into();
+    if v.abs() >= 100.0 {
+        format!("{:.0}", v)
+    } else if v.abs() >= 1.0 {
+        format!("{:.2}", v)
+    } else {
+        format!("{:.4}", v)
     }
 }
 
@@ -54,53 +214,221 @@ impl TimeSeries {
 pub struct StatGraph {
     /// Title of graph
     title: WidgetText,
-    /// Y range
+    /// Y range always shown. More if the data needs it.
     y_range: [f32; 2],
     /// Unique ID
     id: egui::Id,
-    /// The actual data.
-    time_series: TimeSeries,
+    /// The actual data, one or more series.
+    series: Vec<TimeSeries>,
+    /// Horizontal lines, (label, value)
+    thresholds: Vec<(String, f32)>,
+    /// Logarithmic Y axis
+    log_y: bool,
+    /// Show summaries under the graph
+    show_summary: bool,
+    /// Time zero, for push without a time
+    start: Instant,
+    /// For percentiles. Reused.
+    histogram: histogram::Histogram,
 }
 
 impl StatGraph {
-    /// Usual new
+    /// Usual new. One series, with no name.
     pub fn new(title: impl Into<WidgetText>, y_range: [f32; 2], length: usize, id: &str) -> Self {
         Self {
             title: title.into(),
             y_range,
             id: egui::Id::new(id),
-            time_series: TimeSeries::new(length),
+            series: vec![TimeSeries::new("", length)],
+            thresholds: Vec::new(),
+            log_y: false,
+            show_summary: false,
+            start: Instant::now(),
+            histogram: histogram::Histogram::new(),
         }
     }
 
-    /// Add a value to the time series.
+    /// Named series, in place of the one with no name. Shown with a legend.
+    pub fn with_series(mut self, names: &[&str]) -> Self {
+        let length = self.series[0].length;
+        self.series = names
+            .iter()
+            .map(|name| TimeSeries::new(name, length))
+            .collect();
+        self
+    }
+
+    /// Logarithmic Y axis.
+    pub fn log_y(mut self, log_y: bool) -> Self {
+        self.set_log_y(log_y);
+        self
+    }
+
+    /// Change the Y axis between linear and logarithmic.
+    pub fn set_log_y(&mut self, log_y: bool) {
+        if log_y != self.log_y {
+            self.log_y = log_y;
+            for series in &mut self.series {
+                series.replot(log_y);
+            }
+        }
+    }
+
+    /// True if the Y axis is logarithmic.
+    pub fn is_log_y(&self) -> bool {
+        self.log_y
+    }
+
+    /// Horizontal line across the graph, such as a time budget.
+    pub fn threshold(mut self, label: &str, value: f32) -> Self {
+        self.thresholds.push((label.to_string(), value));
+        self
+    }
+
+    /// Replace the horizontal lines.
+    pub fn set_thresholds(&mut self, thresholds: &[(String, f32)]) {
+        self.thresholds = thresholds.to_vec();
+    }
+
+    /// Show min, max, average and percentiles under the graph.
+    pub fn show_summary(mut self, show_summary: bool) -> Self {
+        self.show_summary = show_summary;
+        self
+    }
+
+    /// Add a value to the first series, now.
     pub fn push(&mut self, v: f32) {
-        self.time_series.push(v);
+        let time = self.start.elapsed().as_secs_f64();
+        self.push_at(0, time, v);
+    }
+
+    /// Add a value to a series, at a time in seconds.
+    /// Times must increase, and should be on the same clock for all series.
+    pub fn push_at(&mut self, series: usize, time: f64, v: f32) {
+        let Some(series) = self.series.get_mut(series) else {
+            log::error!(
+                "Stat graph {:?} has no series {}",
+                self.title.text(),
+                series
+            );
+            return;
+        };
+        series.push(time, v, self.log_y);
+    }
+
+    /// Summary of a series.
+    pub fn summary(&mut self, series: usize) -> Option<SeriesSummary> {
+        let series = self.series.get_mut(series)?;
+        series.summarize(&mut self.histogram);
+        Some(series.summary)
+    }
+
+    /// Time of the latest point, in seconds.
+    fn latest_time(&self) -> f64 {
+        self.series
+            .iter()
+            .filter_map(|s| s.points.back().map(|p| p.x))
+            .fold(0.0, f64::max)
     }
 }
 
 /// The widget is a graph
 impl egui::Widget for &mut StatGraph {
-    /// Draw. Called every frame if open.
+    /// Draw. Called every frame if open. No allocation per point.
     fn ui(self, ui: &mut Ui) -> Response {
         ui.vertical(|ui| {
             ui.label(self.title.clone());
-            let values = self.time_series.as_plot_points(); // returns an iterator.
-                                                            //  Unfortunately, Line wont't yet take an iterator.
-            let temp_values_1: Vec<egui_plot::PlotPoint> = values.collect(); // so we have to make a list of values
-            let temp_values = egui_plot::PlotPoints::Owned(temp_values_1);
-            egui_plot::Plot::new(self.id)
+            let latest = self.latest_time();
+            let log_y = self.log_y;
+            let with_legend = self.series.len() > 1 || !self.thresholds.is_empty();
+            let mut plot = Plot::new(self.id)
                 .view_aspect(5.0)
-                .include_x(0.0)
-                .include_x(self.time_series.length as f64)
-                .include_y(self.y_range[0])
-                .include_y(self.y_range[1])
-                .show_x(false)
-                .show(ui, |plot_ui| {
-                    plot_ui.line(egui_plot::Line::new(temp_values).fill(0.0))
-                })
-                .response
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .include_y(plot_y(self.y_range[0], log_y))
+                .include_y(plot_y(self.y_range[1], log_y))
+                .x_axis_formatter(move |mark, _range| format!("{:.0} s", mark.value - latest))
+                .y_axis_formatter(move |mark, _range| format_value(value_y(mark.value, log_y)))
+                .label_formatter(move |name, value| {
+                    format!(
+                        "{}{:.1} s\n{}",
+                        if name.is_empty() {
+                            String::new()
+                        } else {
+                            format!("{}\n", name)
+                        },
+                        value.x - latest,
+                        format_value(value_y(value.y, log_y))
+                    )
+                });
+            if with_legend {
+                plot = plot.legend(Legend::default());
+            }
+            plot.show(ui, |plot_ui| {
+                for series in &self.series {
+                    let line = Line::new(PlotPoints::Borrowed(series.plot_points()));
+                    let line = if with_legend {
+                        line.name(&series.name)
+                    } else if !log_y {
+                        line.fill(0.0)
+                    } else {
+                        line
+                    };
+                    plot_ui.line(line);
+                }
+                for (label, value) in &self.thresholds {
+                    plot_ui.hline(
+                        HLine::new(plot_y(*value, log_y))
+                            .name(label)
+                            .style(LineStyle::dashed_loose()),
+                    );
+                }
+            });
+            if self.show_summary {
+                for series in &mut self.series {
+                    series.summarize(&mut self.histogram);
+                    ui.label(series.summary_text.as_str());
+                }
+            }
         })
         .response
     }
 }
+
+#[test]
+fn test_stat_graph() {
+    let mut graph = StatGraph::new("Frame time", [0.0, 0.1], 4, "test")
+        .with_series(&["average", "longest"])
+        .threshold("budget", 0.0167);
+    for (i, v) in [0.010, 0.020, 0.030, 0.040, 0.050].iter().enumerate() {
+        graph.push_at(0, i as f64, *v);
+    }
+    graph.push_at(1, 4.0, 0.1);
+    //  No such series, ignored.
+    graph.push_at(2, 4.0, 0.1);
+    //  Only the last 4 are kept.
+    let summary = graph.summary(0).unwrap();
+    assert_eq!(summary.min, 0.020);
+    assert_eq!(summary.max, 0.050);
+    assert!((summary.mean - 0.035).abs() < 1e-6);
+    //  Percentiles come from histogram buckets, good to 3 significant figures.
+    assert!(summary.p50 >= 0.020 && summary.p50 <= 0.040 * 1.001);
+    assert!(summary.p95 >= summary.p50 && summary.p95 <= 0.050);
+    assert_eq!(graph.series[0].plot_points().len(), 4);
+    assert_eq!(graph.series[0].plot_points()[0].x, 1.0); // oldest first
+    assert_eq!(graph.latest_time(), 4.0);
+    //  Log axis replots.
+    graph.set_log_y(true);
+    assert!((graph.series[1].plot_points()[0].y - -1.0).abs() < 1e-6);
+    graph.set_log_y(false);
+    assert!((graph.series[1].plot_points()[0].y - 0.1).abs() < 1e-6);
+    //  Dropping the largest and smallest values updates the summary.
+    for (i, v) in [0.030, 0.030, 0.030, 0.030].iter().enumerate() {
+        graph.push_at(0, 5.0 + i as f64, *v);
+    }
+    let summary = graph.summary(0).unwrap();
+    assert_eq!((summary.min, summary.max), (0.030, 0.030));
+    assert!((summary.mean - 0.030).abs() < 1e-6);
+    assert!(graph.summary(3).is_none());
+}
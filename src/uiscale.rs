@@ -0,0 +1,164 @@
+//! uiscale.rs -- how big the user interface is drawn.
+//
+//  The scale is the product of:
+//  - The operating system's scale factor, for HiDPI screens.
+//    egui_winit supplies this, and updates it when the window
+//    moves to another monitor.
+//  - The user's preference.
+//  - Fit to window. If the window is smaller than the UI is
+//    laid out for, everything shrinks to fit.
+//
+//  The last two are applied as egui's zoom factor. All sizes
+//  given to egui, fonts and custom widgets included, are in points,
+//  so they all follow. Only code which compares against window
+//  sizes in pixels has to convert.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::{get_config_dir, write_atomic};
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// UI scale settings file, in the config directory.
+pub(crate) const UI_SCALE_FILE_NAME: &str = "ui_scale.json";
+/// Smallest user scale.
+pub const UI_SCALE_MIN: f32 = 0.5;
+/// Largest user scale.
+pub const UI_SCALE_MAX: f32 = 3.0;
+/// Window size, in points, the UI is laid out for.
+const DESIGN_SIZE: egui::Vec2 = egui::vec2(1024.0, 640.0);
+/// Fit to window never shrinks more than this. Smaller text is unreadable.
+const FIT_MIN: f32 = 0.6;
+/// Ignore zoom changes smaller than this, to avoid redrawing for rounding errors.
+const ZOOM_EPSILON: f32 = 0.001;
+
+/// The user's scale settings.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiScaleSettings {
+    pub user_scale: f32,     // 1.0 is normal size
+    pub fit_to_window: bool, // shrink UI to fit small windows
+}
+
+impl Default for UiScaleSettings {
+    fn default() -> Self {
+        UiScaleSettings {
+            user_scale: 1.0,
+            fit_to_window: true,
+        }
+    }
+}
+
+impl UiScaleSettings {
+    /// Usable settings. Scale is kept in range, and a scale which isn't a number is normal size.
+    fn valid(self) -> Self {
+        let user_scale = if self.user_scale.is_nan() {
+            UiScaleSettings::default().user_scale
+        } else {
+            self.user_scale.clamp(UI_SCALE_MIN, UI_SCALE_MAX)
+        };
+        UiScaleSettings { user_scale, ..self }
+    }
+}
+
+/// UI scale control.
+#[derive(Debug, Default)]
+pub struct UiScale {
+    settings: UiScaleSettings, // current settings
+    path: Option<PathBuf>,     // where they are saved
+}
+
+impl UiScale {
+    /// Read the settings from the config directory. Missing file means defaults.
+    /// A scale out of range, from editing the file by hand, is brought into range.
+    pub fn load() -> Result<Self, Error> {
+        let path = get_config_dir()?.join(UI_SCALE_FILE_NAME);
+        let settings: UiScaleSettings = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Unable to read UI scale file {:?}", path))?;
+            serde_json::from_str(&content)
+                .with_context(|| format!("Unable to parse UI scale file {:?}", path))?
+        } else {
+            Default::default()
+        };
+        Ok(UiScale {
+            settings: settings.valid(),
+            path: Some(path),
+        })
+    }
+
+    /// Write the settings back where they came from.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("UI scale settings have no file"))?;
+        write_atomic(
+            path,
+            serde_json::to_string_pretty(&self.settings)?.as_bytes(),
+        )
+        .with_context(|| format!("Unable to write UI scale file {:?}", path))
+    }
+
+    /// The current settings.
+    pub fn get_settings(&self) -> UiScaleSettings {
+        self.settings
+    }
+
+    /// Change the settings. Takes effect on the next frame.
+    pub fn set_settings(&mut self, settings: UiScaleSettings) {
+        self.settings = settings.valid();
+    }
+
+    /// Zoom factor for a window of the given size, in points before zooming.
+    pub fn zoom_factor(&self, unzoomed_size: egui::Vec2) -> f32 {
+        let fit = if self.settings.fit_to_window {
+            (unzoomed_size.x / DESIGN_SIZE.x)
+                .min(unzoomed_size.y / DESIGN_SIZE.y)
+                .clamp(FIT_MIN, 1.0)
+        } else {
+            1.0
+        };
+        self.settings.user_scale * fit
+    }
+
+    /// Apply to egui. Call once per frame, after the frame has begun.
+    //  A new zoom factor takes effect on the next frame.
+    pub fn update(&self, ctx: &egui::Context) {
+        let current = ctx.zoom_factor();
+        //  The screen size before zooming does not change with zoom, so this can't oscillate.
+        let unzoomed_size = ctx.screen_rect().size() * current;
+        let wanted = self.zoom_factor(unzoomed_size);
+        if (wanted - current).abs() > ZOOM_EPSILON {
+            ctx.set_zoom_factor(wanted);
+        }
+    }
+}
+
+#[test]
+fn test_zoom_factor() {
+    let mut ui_scale = UiScale::default();
+    assert_eq!(ui_scale.zoom_factor(egui::vec2(1920.0, 1080.0)), 1.0);
+    assert_eq!(ui_scale.zoom_factor(egui::vec2(512.0, 1080.0)), 0.6); // limited by FIT_MIN
+    assert_eq!(ui_scale.zoom_factor(egui::vec2(1920.0, 480.0)), 0.75); // fit height
+    ui_scale.set_settings(UiScaleSettings {
+        user_scale: 2.0,
+        fit_to_window: false,
+    });
+    assert_eq!(ui_scale.zoom_factor(egui::vec2(512.0, 480.0)), 2.0);
+    ui_scale.set_settings(UiScaleSettings {
+        user_scale: 100.0,
+        fit_to_window: false,
+    });
+    assert_eq!(ui_scale.get_settings().user_scale, UI_SCALE_MAX);
+    ui_scale.set_settings(UiScaleSettings {
+        user_scale: f32::NAN,
+        fit_to_window: false,
+    });
+    assert_eq!(ui_scale.get_settings().user_scale, 1.0);
+    //  As read from a file edited by hand.
+    let settings: UiScaleSettings = serde_json::from_str(r#"{"user_scale": 0.01}"#).unwrap();
+    assert_eq!(settings.valid().user_scale, UI_SCALE_MIN);
+}
@@ -0,0 +1,187 @@
+//! windowlayout.rs -- remember window layout between sessions.
+//
+//  egui keeps window positions, sizes and collapsed state in its Memory,
+//  keyed by window Id. That is saved in the config directory at shutdown
+//  and read back at startup.
+//
+//  Ids must be the same from run to run for this to work, so windows
+//  which are to be remembered are registered by name. A registered
+//  window's Id must be egui::Id::new(name). The names of the registered
+//  windows open at shutdown are saved, and the windows can be reopened
+//  next session. So is which windows are docked where.
+//
+//  Animats
+//  October 2026
+//
+use crate::guiutil::{get_config_dir, move_aside, write_atomic};
+use crate::{CommonState, DockLayer, GuiWindowLink};
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Layout file, in the config directory.
+pub(crate) const LAYOUT_FILE_NAME: &str = "window_layout.json";
+
+/// Creates a registered window. The window's Id must be egui::Id::new(name).
+pub type WindowFactory = fn(&mut CommonState) -> GuiWindowLink;
+
+/// What is saved.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct LayoutFile {
+    open_windows: Vec<String>, // registered windows open at shutdown
+    docks: DockLayer,          // which windows are docked where
+    memory: egui::Memory,      // positions, sizes, collapsed state
+}
+
+/// Window layout persistence.
+#[derive(Default)]
+pub struct WindowLayout {
+    pub docks: DockLayer,                      // docked windows
+    factories: HashMap<String, WindowFactory>, // registered windows, by name
+    saved_open_windows: Vec<String>,           // open last session, not yet reopened
+    reset_pending: bool,                       // reset at start of next frame
+    path: Option<PathBuf>,                     // where the layout is saved
+}
+
+impl WindowLayout {
+    /// Read the saved layout into egui. Call before the first frame.
+    /// Missing file means the default layout. So does a bad one, which is
+    /// moved aside, so the layout can still be saved.
+    /// Returns the layout, and what went wrong, if anything.
+    pub fn load(ctx: &egui::Context) -> (Self, Option<Error>) {
+        let path = match get_config_dir() {
+            Ok(dir) => dir.join(LAYOUT_FILE_NAME),
+            Err(e) => return (Default::default(), Some(e)),
+        };
+        let mut layout = WindowLayout {
+            path: Some(path.clone()),
+            ..Default::default()
+        };
+        match Self::read_layout_file(&path) {
+            Ok(Some(layout_file)) => {
+                set_memory_keeping_options(ctx, layout_file.memory);
+                layout.saved_open_windows = layout_file.open_windows;
+                layout.docks = layout_file.docks;
+                (layout, None)
+            }
+            Ok(None) => (layout, None),
+            Err(e) => match move_aside(&path) {
+                Ok(bad_path) => (layout, Some(e.context(format!("Moved to {:?}", bad_path)))),
+                //  Still there, so don't overwrite it.
+                Err(move_error) => (Default::default(), Some(e.context(move_error))),
+            },
+        }
+    }
+
+    /// Read the layout file, if there is one.
+    fn read_layout_file(path: &Path) -> Result<Option<LayoutFile>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Unable to read window layout file {:?}", path))?;
+        let layout_file = serde_json::from_str(&content)
+            .with_context(|| format!("Unable to parse window layout file {:?}", path))?;
+        Ok(Some(layout_file))
+    }
+
+    /// Save the layout. Call between frames.
+    pub fn save(&self, ctx: &egui::Context, open_ids: &[egui::Id]) -> Result<(), Error> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or_else(|| anyhow!("Window layout has no file"))?;
+        let layout_file = LayoutFile {
+            open_windows: self.open_window_names(open_ids),
+            docks: self.docks.clone(),
+            memory: ctx.memory(|memory| memory.clone()),
+        };
+        write_atomic(path, serde_json::to_string(&layout_file)?.as_bytes())
+            .with_context(|| format!("Unable to write window layout file {:?}", path))
+    }
+
+    /// Register a window which can be reopened by name.
+    pub fn register(&mut self, name: &str, factory: WindowFactory) {
+        self.factories.insert(name.to_string(), factory);
+    }
+
+    /// Names of the registered windows among the given Ids.
+    fn open_window_names(&self, open_ids: &[egui::Id]) -> Vec<String> {
+        open_ids
+            .iter()
+            .filter_map(|id| {
+                self.factories
+                    .keys()
+                    .find(|name| egui::Id::new(name.as_str()) == *id)
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// Factories for the windows open last session, once only.
+    /// Windows not registered are not reopened.
+    pub fn take_saved_windows(&mut self) -> Vec<WindowFactory> {
+        std::mem::take(&mut self.saved_open_windows)
+            .iter()
+            .filter_map(|name| self.factories.get(name).copied())
+            .collect()
+    }
+
+    /// Forget all window positions and sizes, and undock everything, at the start of the next frame.
+    pub fn reset(&mut self) {
+        self.reset_pending = true;
+    }
+
+    /// Do any pending reset. Call between frames.
+    //  Replacing egui's memory in the middle of a frame confuses it.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        if self.reset_pending {
+            self.reset_pending = false;
+            set_memory_keeping_options(ctx, Default::default());
+            self.docks = Default::default();
+        }
+    }
+}
+
+/// Replace egui's memory, but keep the options, which hold our styles and zoom settings.
+fn set_memory_keeping_options(ctx: &egui::Context, new_memory: egui::Memory) {
+    ctx.memory_mut(|memory| {
+        let options = memory.options.clone();
+        *memory = new_memory;
+        memory.options = options;
+    });
+}
+
+#[test]
+fn test_layout_file() {
+    let ctx = egui::Context::default();
+    let mut layout = WindowLayout::default();
+    fn unused_factory(_state: &mut CommonState) -> GuiWindowLink {
+        unreachable!()
+    }
+    layout.register("stats", unused_factory);
+    layout.register("about", unused_factory);
+    //  Only registered windows are remembered.
+    let open_ids = [
+        egui::Id::new("about"),
+        egui::Id::new(12345),
+        egui::Id::new("stats"),
+    ];
+    assert_eq!(layout.open_window_names(&open_ids), vec!["about", "stats"]);
+    //  Memory survives a round trip through JSON.
+    let layout_file = LayoutFile {
+        open_windows: layout.open_window_names(&open_ids),
+        docks: Default::default(),
+        memory: ctx.memory(|memory| memory.clone()),
+    };
+    let json = serde_json::to_string(&layout_file).unwrap();
+    let read_back: LayoutFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(read_back.open_windows, layout_file.open_windows);
+    //  Saved windows come back once.
+    layout.saved_open_windows = read_back.open_windows;
+    layout.saved_open_windows.push("no such window".to_string());
+    assert_eq!(layout.take_saved_windows().len(), 2);
+    assert!(layout.take_saved_windows().is_empty());
+}